@@ -48,6 +48,13 @@ def load(filename, expr=1):
 	# rest are datapoints
 	for line in hDAT:
 		data = line.strip().split()
+
+		# a blank line starts a new block, so break the curve there
+		if len(data) == 0:
+			wv.x.append(float('nan'))
+			wv.y.append(float('nan'))
+			continue
+
 		wv.x.append(float(data[0]))
 		wv.y.append(float(data[op[1]]))
 		if op[0] == '-':
@@ -10,6 +10,7 @@ pub enum Kind {
     DcOperatingPoint,
     DcSweep,
     Transient,
+    Ac,
 }
 
 impl fmt::Display for Kind {
@@ -18,10 +19,102 @@ impl fmt::Display for Kind {
             Kind::Transient => write!(f, "Transient"),
             Kind::DcOperatingPoint => write!(f, "DC Operating Point"),
             Kind::DcSweep => write!(f, "DC Sweep"),
+            Kind::Ac => write!(f, "AC Small-Signal"),
         }
     }
 }
 
+/// How storage elements are integrated in a transient analysis
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub enum Method {
+    /// `method=euler` - backward Euler. Very stable, but it damps
+    /// resonances.
+    Euler,
+    /// `method=trap` - the trapezoidal rule. More accurate, but can ring
+    /// after sudden changes.
+    #[default]
+    Trapezoidal,
+    /// `method=gear` - backward differentiation formulae, up to order
+    /// `maxord`. Good for stiff circuits.
+    Gear,
+}
+
+impl fmt::Display for Method {
+    fn fmt (&self, f:&mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Method::Euler => write!(f, "euler"),
+            Method::Trapezoidal => write!(f, "trap"),
+            Method::Gear => write!(f, "gear"),
+        }
+    }
+}
+
+/// How the frequency points of an AC analysis are spaced
+#[derive(Clone, Default, Debug, PartialEq)]
+pub enum AcSweep {
+    /// `dec` - points per decade
+    #[default]
+    Decade,
+    /// `oct` - points per octave
+    Octave,
+    /// `lin` - total number of linearly-spaced points
+    Linear,
+}
+
+/// A DC sweep of a single independent source
+#[derive(Clone, Default, Debug)]
+pub struct DcSweepSpec {
+    /// Identifier of the `V` or `I` source to sweep
+    pub src: String,
+    pub start: f64,
+    pub stop: f64,
+    pub step: f64,
+}
+
+impl DcSweepSpec {
+
+    /// List the source values to sweep through, start and stop inclusive
+    pub fn values(&self) -> Vec<f64> {
+        let mut vals = vec![];
+        if self.step == 0.0 || (self.stop - self.start) * self.step < 0.0 {
+            println!("*ERROR* bad DC sweep: {} to {} by {}",
+                     self.start, self.stop, self.step);
+            return vals;
+        }
+
+        // floating point steps aren't exact, so allow a little slop
+        let c_steps = ((self.stop - self.start) / self.step + 1e-9).floor() as usize;
+        for i in 0..=c_steps {
+            vals.push(self.start + self.step * i as f64);
+        }
+        vals
+    }
+
+}
+
+/// A signal to write to a WAV file
+#[derive(Clone, Debug, PartialEq)]
+pub enum WavSignal {
+    /// `v(<node>)`, or `v(<node1>,<node2>)` for the difference
+    Voltage(String, Option<String>),
+    /// `i(<ident>)`, the branch current of a voltage source or inductor
+    Current(String),
+}
+
+/// A `wav` command: write transient results to a WAV file
+#[derive(Clone, Debug)]
+pub struct WavOutput {
+    pub filename: String,
+    /// One signal for mono, two for stereo
+    pub signals: Vec<WavSignal>,
+    /// Samples per second
+    pub rate: u32,
+    /// Bits per sample: 8, 16 or 24 for PCM, or 32 for float
+    pub bits: u16,
+    /// Scale so the peak sample is full scale, rather than 1V or 1A
+    pub normalise: bool,
+}
+
 pub struct Statistics {
     pub kind: Kind,
     pub end: f64,
@@ -63,6 +156,13 @@ pub struct Configuration {
     /// Current absolute tolerance
     pub ABSTOL: f64,
 
+    /// Charge absolute tolerance, for the truncation error of capacitors
+    pub CHGTOL: f64,
+
+    /// Truncation error overestimation factor for transient analysis.
+    /// Bigger takes longer timesteps.
+    pub TRTOL: f64,
+
     /// Newton solver maximum number of iterations
     pub ITL1: usize,
 
@@ -87,8 +187,34 @@ pub struct Configuration {
     /// Largest delta-time step allowed factor
     pub RMAX: f64,
 
+    /// Largest delta-time step allowed for transient analysis, from `tran`.
+    /// If it isn't given, `RMAX * TSTEP` is used.
+    pub TMAX: Option<f64>,
+
+    /// Skip the DC operating point, and start the transient analysis from
+    /// the initial conditions
+    pub uic: bool,
+
+    /// Initial node voltages from `.ic`: (node name, voltage)
+    pub initial_conditions: Vec<(String, f64)>,
+
+    /// Starting guesses for the DC solution from `.nodeset`:
+    /// (node name, voltage)
+    pub nodesets: Vec<(String, f64)>,
+
+    /// Integration method for transient analysis
+    pub method: Method,
+
+    /// Highest order for `method=gear`, from 2 to 6
+    pub maxord: usize,
+
+    /// Write transient results on the `TSTEP` grid, interpolated between
+    /// timepoints, instead of at every timepoint
+    pub interp: bool,
+
     /// 'Easy' iteration count limit for transient analysis.
-    /// If we solve in fewer iterations, increase delta-time.
+    /// If we solve in fewer iterations, increase delta-time. Only used when
+    /// there's no truncation error estimate, e.g. no storage elements.
     pub ITL3: usize,
 
     /// 'Struggling' iteration count for transient analysis.
@@ -96,11 +222,32 @@ pub struct Configuration {
     /// step, reduce the delta-time step and restart the solution attempt.
     pub ITL4: usize,
 
+    /// Frequency point spacing for AC analysis
+    pub ac_sweep: AcSweep,
+
+    /// Number of points per decade/octave, or total for linear AC sweeps
+    pub NPOINTS: usize,
+
+    /// Start frequency for AC analysis
+    pub FSTART: f64,
+
+    /// Stop frequency for AC analysis
+    pub FSTOP: f64,
+
+    /// DC sweep settings
+    pub dc_sweep: Option<DcSweepSpec>,
+
+    /// Outer DC sweep settings, for families of curves
+    pub dc_sweep_outer: Option<DcSweepSpec>,
+
     /// Name of circuit (taken from SPICE filename)
     pub ckt_name: String,
 
     /// Name of file to write waveform data to
     pub wavefile: String,
+
+    /// WAV files to write after a transient analysis
+    pub wav_outputs: Vec<WavOutput>,
 }
 
 
@@ -120,6 +267,8 @@ impl Configuration {
             RELTOL: 0.0001,
             VNTOL: 1.0e-6,
             ABSTOL: 1.0e-9,
+            CHGTOL: 1.0e-14,
+            TRTOL: 7.0,
 
             // DC operating
             ITL1: 50,
@@ -133,11 +282,29 @@ impl Configuration {
             FT: 0.25,
             RMIN: 1e03,
             RMAX: 5.0,
+            TMAX: None,
+            uic: false,
+            initial_conditions: vec![],
+            nodesets: vec![],
+            method: Method::Trapezoidal,
+            maxord: 2,
+            interp: false,
             ITL3: 6,
             ITL4: 50,
 
+            // AC
+            ac_sweep: AcSweep::Decade,
+            NPOINTS: 10,
+            FSTART: 1.0,
+            FSTOP: 1.0e6,
+
+            // DC Sweep
+            dc_sweep: None,
+            dc_sweep_outer: None,
+
             ckt_name: "Default_Circuit_Name".to_string(),
             wavefile: "waves/default.dat".to_string(),
+            wav_outputs: vec![],
 
         }
     }
@@ -151,16 +318,103 @@ impl Configuration {
         self.TSTART = tstart;
     }
 
+    // Largest delta-time step allowed in a transient analysis
+    pub fn t_delta_max(&self) -> f64 {
+        self.TMAX.unwrap_or(self.TSTEP * self.RMAX)
+    }
+
     // Configure the simulation engine for a DC operating point analysis
     pub fn set_dc_operating_point(&mut self) {
         self.kind = Some(Kind::DcOperatingPoint);
     }
 
+    // Configure the simulation engine for a DC sweep of a source
+    pub fn set_dc_sweep(&mut self, src: &str, start: f64, stop: f64, step: f64) {
+        self.kind = Some(Kind::DcSweep);
+        self.dc_sweep = Some(DcSweepSpec {
+            src: src.to_string(),
+            start,
+            stop,
+            step,
+        });
+    }
+
+    // Add an outer sweep to the DC sweep, ngspice's `dc V1 ... V2 ...` form
+    pub fn set_dc_sweep_outer(&mut self, src: &str, start: f64, stop: f64, step: f64) {
+        self.dc_sweep_outer = Some(DcSweepSpec {
+            src: src.to_string(),
+            start,
+            stop,
+            step,
+        });
+    }
+
+    // Configure the simulation engine for an AC small-signal analysis
+    pub fn set_ac(&mut self, sweep: AcSweep, npoints: usize, fstart: f64, fstop: f64) {
+        self.kind = Some(Kind::Ac);
+        self.ac_sweep = sweep;
+        self.NPOINTS = npoints;
+        self.FSTART = fstart;
+        self.FSTOP = fstop;
+    }
+
+    /// List the frequencies an AC analysis is evaluated at
+    pub fn ac_frequencies(&self) -> Vec<f64> {
+        let mut freqs = vec![];
+        if self.NPOINTS == 0 || self.FSTART <= 0.0 || self.FSTOP < self.FSTART {
+            println!("*ERROR* bad AC sweep: {} points from {} to {} Hz",
+                     self.NPOINTS, self.FSTART, self.FSTOP);
+            return freqs;
+        }
+
+        match self.ac_sweep {
+            AcSweep::Linear => {
+                if self.NPOINTS == 1 {
+                    return vec![self.FSTART];
+                }
+                let f_step = (self.FSTOP - self.FSTART) / (self.NPOINTS - 1) as f64;
+                for i in 0..self.NPOINTS {
+                    freqs.push(self.FSTART + f_step * i as f64);
+                }
+            },
+            AcSweep::Decade | AcSweep::Octave => {
+                let base: f64 = if self.ac_sweep == AcSweep::Decade { 10.0 } else { 2.0 };
+                // logs aren't exact, so allow a little slop at the top end
+                let f_last = self.FSTOP * (1.0 + 1e-9);
+                let mut i = 0;
+                loop {
+                    let f = self.FSTART * base.powf(i as f64 / self.NPOINTS as f64);
+                    if f > f_last {
+                        break;
+                    }
+                    freqs.push(f);
+                    i += 1;
+                }
+            },
+        }
+        freqs
+    }
+
     // name file for writing waveforms to
     pub fn set_wavefile(&mut self, filename: &str) {
         self.wavefile = filename.to_string();
     }
 
+    // set the initial voltage of a node for a transient analysis
+    pub fn add_initial_condition(&mut self, node: &str, value: f64) {
+        self.initial_conditions.push((node.to_string(), value));
+    }
+
+    // set a starting guess for the voltage of a node in DC solves
+    pub fn add_nodeset(&mut self, node: &str, value: f64) {
+        self.nodesets.push((node.to_string(), value));
+    }
+
+    // add a WAV file to write after a transient analysis
+    pub fn add_wav_output(&mut self, wav: WavOutput) {
+        self.wav_outputs.push(wav);
+    }
+
     // Print the configuration settings
     pub fn print_options(&self) { 
         println!("*************************************************************");
@@ -170,8 +424,11 @@ impl Configuration {
                  self.FS, self.FT);
         println!("*OPTION* RMIN = {}; RMAX = {}",
                  self.RMIN, self.RMAX);
+        println!("*OPTION* INTERP = {}", self.interp);
+        println!("*OPTION* METHOD = {}; MAXORD = {}", self.method, self.maxord);
         println!("*OPTION* RELTOL = {:0.12}; VNTOL = {:0.12}; ABSTOL = {:0.12}",
                  self.RELTOL, self.VNTOL, self.ABSTOL);
+        println!("*OPTION* CHGTOL = {:0.12}; TRTOL = {}", self.CHGTOL, self.TRTOL);
         println!("*************************************************************\n");
     }
 }
@@ -3,17 +3,25 @@ use std::fmt;
 use std::collections::HashMap;
 
 pub use crate::parameter::Parameter;
+pub use crate::model::{Model, ModelKind};
 
 pub use crate::element::Element;
 pub use crate::element::diode::Diode;
+pub use crate::element::bjt::Bjt;
+pub use crate::element::mosfet::Mosfet;
+pub use crate::element::jfet::Jfet;
+pub use crate::element::switch::{Switch, Control};
 pub use crate::element::isine::CurrentSourceSine;
 pub use crate::element::vsine::VoltageSourceSine;
 pub use crate::element::capacitor::Capacitor;
+pub use crate::element::inductor::Inductor;
+pub use crate::element::mutual::MutualInductance;
 pub use crate::element::resistor::Resistor;
 pub use crate::element::independent::CurrentSource;
 pub use crate::element::independent::VoltageSource;
-pub use crate::element::vpwl::VoltageSourcePwl;
-pub use crate::element::vdepsrc::{Vcvs, Vccs};
+pub use crate::element::waveform::{Waveform, VoltageSourceWaveform, CurrentSourceWaveform};
+pub use crate::element::vdepsrc::{Vcvs, Vccs, Cccs, Ccvs};
+pub use crate::element::behavioural::{BehaviouralSource, BehaviouralKind, Var};
 
 
 /// Program execution trace macro - prefix `<circuit>`
@@ -45,6 +53,9 @@ pub struct Instance {
     pub subckt: String,
     pub conns: Vec<NodeId>,
     pub params: Vec<Parameter>,
+    pub refs: Vec<String>, // other elements referred to by name, e.g. by `K`
+    pub model: Option<String>, // `.model` name for devices that use one
+    pub behaviour: Option<String>, // `V=...` or `I=...` for `B` sources
 }
 
 impl Instance {
@@ -55,6 +66,9 @@ impl Instance {
             subckt: String::from(subckt),
             conns: vec![],
             params: vec![],
+            refs: vec![],
+            model: None,
+            behaviour: None,
         }
     }
 
@@ -87,9 +101,11 @@ pub struct Circuit {
     pub v_idx_next: usize,
     pub nodes: HashMap<String, NodeId>,
     pub node_id_lut: HashMap<NodeId, String>,
+    pub branch_id_lut: HashMap<usize, String>,
     pub instances: Vec<Instance>,
     pub num_ports: usize,
     pub params: Vec<Parameter>,
+    pub models: Vec<Model>,
 }
 
 impl Circuit {
@@ -106,9 +122,11 @@ impl Circuit {
             v_idx_next: 0,
             nodes,
             node_id_lut: HashMap::new(),
+            branch_id_lut: HashMap::new(),
             instances: vec![],
             num_ports: 0,
             params: vec![],
+            models: vec![],
         }
     }
 
@@ -119,6 +137,18 @@ impl Circuit {
         }
     }
 
+    /// List the device models defined in the circuit
+    pub fn list_models(&self) {
+        for model in &self.models {
+            println!(" model: {}", model);
+        }
+    }
+
+    /// Find a device model defined in this circuit
+    pub fn find_model(&self, name: &str) -> Option<&Model> {
+        self.models.iter().find(|m| m.name.eq_ignore_ascii_case(name))
+    }
+
     /// List the elements of the circuit
     pub fn list_elements(&self) {
         for el in &self.elements {
@@ -221,7 +251,27 @@ impl Circuit {
                             c_nodes += 1;
                         }
                     }
-                    Element::Vpwl(VoltageSourcePwl{ ref p, ref n, ..}) => {
+                    Element::L(Inductor{ ref a, ref b, .. }) => {
+                        if !seen[*a] {
+                            seen[*a] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*b] {
+                            seen[*b] = true;
+                            c_nodes += 1;
+                        }
+                    }
+                    Element::Iwave(CurrentSourceWaveform{ ref p, ref n, ..}) => {
+                        if !seen[*p] {
+                            seen[*p] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*n] {
+                            seen[*n] = true;
+                            c_nodes += 1;
+                        }
+                    }
+                    Element::Vwave(VoltageSourceWaveform{ ref p, ref n, ..}) => {
                         if !seen[*p] {
                             seen[*p] = true;
                             c_nodes += 1;
@@ -267,6 +317,111 @@ impl Circuit {
                             c_nodes += 1;
                         }
                     }
+                    Element::Cccs(Cccs{ ref p, ref n, ..}) => {
+                        if !seen[*p] {
+                            seen[*p] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*n] {
+                            seen[*n] = true;
+                            c_nodes += 1;
+                        }
+                    }
+                    Element::B(BehaviouralSource{ ref p, ref n, ref vars, ..}) => {
+                        if !seen[*p] {
+                            seen[*p] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*n] {
+                            seen[*n] = true;
+                            c_nodes += 1;
+                        }
+                        for var in vars {
+                            if let Var::Node(_, nid) = *var {
+                                if !seen[nid] {
+                                    seen[nid] = true;
+                                    c_nodes += 1;
+                                }
+                            }
+                        }
+                    }
+                    Element::Ccvs(Ccvs{ ref p, ref n, ..}) => {
+                        if !seen[*p] {
+                            seen[*p] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*n] {
+                            seen[*n] = true;
+                            c_nodes += 1;
+                        }
+                    }
+                    Element::Q(Bjt{ ref c, ref b, ref e, ..}) => {
+                        if !seen[*c] {
+                            seen[*c] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*b] {
+                            seen[*b] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*e] {
+                            seen[*e] = true;
+                            c_nodes += 1;
+                        }
+                    }
+                    Element::M(Mosfet{ ref d, ref g, ref s, ref b, ..}) => {
+                        if !seen[*d] {
+                            seen[*d] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*g] {
+                            seen[*g] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*s] {
+                            seen[*s] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*b] {
+                            seen[*b] = true;
+                            c_nodes += 1;
+                        }
+                    }
+                    Element::J(Jfet{ ref d, ref g, ref s, ..}) => {
+                        if !seen[*d] {
+                            seen[*d] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*g] {
+                            seen[*g] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*s] {
+                            seen[*s] = true;
+                            c_nodes += 1;
+                        }
+                    }
+                    Element::S(Switch{ ref p, ref n, ref control, ..}) => {
+                        if !seen[*p] {
+                            seen[*p] = true;
+                            c_nodes += 1;
+                        }
+                        if !seen[*n] {
+                            seen[*n] = true;
+                            c_nodes += 1;
+                        }
+                        if let Control::Voltage(cp, cn) = *control {
+                            if !seen[cp] {
+                                seen[cp] = true;
+                                c_nodes += 1;
+                            }
+                            if !seen[cn] {
+                                seen[cn] = true;
+                                c_nodes += 1;
+                            }
+                        }
+                    }
+                    Element::K(_) => {} // no nodes of its own
                 }
         }
         c_nodes
@@ -274,7 +429,8 @@ impl Circuit {
 
     /// Count the voltage sources in the circuit
     ///
-    /// Counts both `V` and `VSIN`.
+    /// Counts both `V` and `VSIN`. Inductors are counted too, as their
+    /// currents are solved for in the same way.
     pub fn count_voltage_sources(&self) -> usize {
 
         // number of voltage sources in the circuit
@@ -288,12 +444,21 @@ impl Circuit {
                 Element::Vsin(VoltageSourceSine{..}) => {
                         c_vsrc += 1;
                 },
-                Element::Vpwl(VoltageSourcePwl{..}) => {
+                Element::Vwave(VoltageSourceWaveform{..}) => {
                         c_vsrc += 1;
                 },
                 Element::Vcvs(Vcvs{..}) => {
                         c_vsrc += 1;
                 },
+                Element::Ccvs(Ccvs{..}) => {
+                        c_vsrc += 1;
+                },
+                Element::B(BehaviouralSource{ kind: BehaviouralKind::Voltage, ..}) => {
+                        c_vsrc += 1;
+                },
+                Element::L(Inductor{..}) => {
+                        c_vsrc += 1;
+                },
                 _ => {}
             }
         }
@@ -302,9 +467,12 @@ impl Circuit {
 
     /// Add DC current source
     pub fn add_i(&mut self, p: NodeId, n: NodeId, value: f64) {
-        self.elements.push(
-            Element::I(CurrentSource{p, n, value})
-        );
+        self.add_i_src(CurrentSource::new(p, n, value));
+    }
+
+    /// Add DC current source, with its identifier and AC spec
+    pub fn add_i_src(&mut self, isrc: CurrentSource) {
+        self.elements.push(Element::I(isrc));
     }
 
     /// Add AC current source
@@ -320,20 +488,30 @@ impl Circuit {
         self.v_idx_next += 1;
     }
 
-    /// Add piecewise linear voltage source
-    pub fn add_v_pwl(&mut self, v_pwl: VoltageSourcePwl) {
-        let mut v_pwl_upd = v_pwl.clone();
-        v_pwl_upd.idx = self.v_idx_next;
-        self.elements.push(Element::Vpwl(v_pwl_upd));
+    /// Add voltage source with a waveform such as `PULSE()`
+    pub fn add_v_wave(&mut self, v_wave: VoltageSourceWaveform) {
+        let mut v_wave_upd = v_wave.clone();
+        v_wave_upd.idx = self.v_idx_next;
+        self.elements.push(Element::Vwave(v_wave_upd));
         self.v_idx_next += 1;
     }
 
+    /// Add current source with a waveform such as `PULSE()`
+    pub fn add_i_wave(&mut self, i_wave: CurrentSourceWaveform) {
+        self.elements.push(Element::Iwave(i_wave));
+    }
+
 
     /// Add DC voltage source
     pub fn add_v(&mut self, p: NodeId, n: NodeId, value: f64) {
-        self.elements.push(
-            Element::V(VoltageSource{p, n, value, idx:self.v_idx_next})
-        );
+        self.add_v_src(VoltageSource::new(p, n, value, 0));
+    }
+
+    /// Add DC voltage source, with its identifier and AC spec
+    pub fn add_v_src(&mut self, vsrc: VoltageSource) {
+        let mut vsrc_upd = vsrc.clone();
+        vsrc_upd.idx = self.v_idx_next;
+        self.elements.push(Element::V(vsrc_upd));
         self.v_idx_next += 1;
     }
 
@@ -347,7 +525,7 @@ impl Circuit {
     /// Add capacitor
     pub fn add_c(&mut self, ident: String, a: NodeId, b: NodeId, value: f64) {
         self.elements.push(
-            Element::C(Capacitor{ident, a, b, value})
+            Element::C(Capacitor{ident, a, b, value, ic: None})
         );
     }
 
@@ -409,9 +587,172 @@ impl Circuit {
 
     /// Build NodeId lookup
     /// Do this after reading in the SPICE circuit
+    ///
+    /// A node connected to subcircuit ports has a name at each level of the
+    /// hierarchy. The one nearest the top is used, then the shortest, so
+    /// the choice doesn't depend on the order of the `HashMap`.
     pub fn build_node_id_lut(&mut self) {
+        let rank = |name: &str| (name.matches('.').count(), name.len(), name.to_string());
         for (name, id) in &self.nodes {
-            self.node_id_lut.insert(*id, String::from(name));
+            let better = match self.node_id_lut.get(id) {
+                Some(other) => rank(name) < rank(other),
+                None => true,
+            };
+            if better {
+                self.node_id_lut.insert(*id, String::from(name));
+            }
+        }
+        self.build_branch_id_lut();
+    }
+
+    /// Fill in the inductor details of the mutual inductances
+    ///
+    /// Can only be done once all subcircuits are expanded, as the `K` can
+    /// appear before the inductors it couples. Returns `true` if any of the
+    /// referenced inductors don't exist.
+    pub fn resolve_mutual_inductances(&mut self) -> bool {
+        let mut there_are_errors = false;
+
+        let mut inductors: HashMap<String, (usize, f64)> = HashMap::new();
+        for el in &self.elements {
+            if let Element::L(ref ind) = *el {
+                inductors.insert(ind.ident.to_string(), (ind.idx, ind.value));
+            }
+        }
+
+        for el in &mut self.elements {
+            if let Element::K(ref mut mutual) = *el {
+                let l1 = inductors.get(&mutual.l1);
+                let l2 = inductors.get(&mutual.l2);
+                if let (Some(&(idx1, val1)), Some(&(idx2, val2))) = (l1, l2) {
+                    mutual.idx1 = idx1;
+                    mutual.idx2 = idx2;
+                    mutual.value = mutual.k * (val1 * val2).sqrt();
+                    trace!("Resolved {}: M = {}", mutual.ident, mutual.value);
+                } else {
+                    for (lname, lfound) in [(&mutual.l1, l1), (&mutual.l2, l2)] {
+                        if lfound.is_none() {
+                            println!("*ERROR* Can't find inductor '{}' for '{}'",
+                                lname, mutual.ident);
+                        }
+                    }
+                    there_are_errors = true;
+                }
+            }
+        }
+        there_are_errors
+    }
+
+    /// Point current-controlled sources and switches, and behavioural
+    /// sources using `i(Vx)`, at their controlling voltage source
+    ///
+    /// Returns `true` if any of the controlling sources can't be found.
+    pub fn resolve_current_controls(&mut self) -> bool {
+        let mut there_are_errors = false;
+
+        let mut vsrcs: HashMap<String, usize> = HashMap::new();
+        for el in &self.elements {
+            match *el {
+                Element::V(ref vsrc) => { vsrcs.insert(vsrc.ident.to_string(), vsrc.idx); },
+                Element::Vsin(ref vsrc) => { vsrcs.insert(vsrc.ident.to_string(), vsrc.idx); },
+                Element::Vwave(ref vsrc) => { vsrcs.insert(vsrc.ident.to_string(), vsrc.idx); },
+                _ => {}
+            }
+        }
+
+        for el in &mut self.elements {
+            if let Element::B(ref mut bsrc) = *el {
+                for var in &mut bsrc.vars {
+                    if let Var::Branch(ref vsrc, ref mut ctl_idx) = *var {
+                        if let Some(&idx) = vsrcs.get(vsrc) {
+                            *ctl_idx = idx;
+                        } else {
+                            println!("*ERROR* Can't find voltage source '{}' used in '{}'",
+                                vsrc, bsrc.ident);
+                            there_are_errors = true;
+                        }
+                    }
+                }
+                continue;
+            }
+            let (ident, vsrc, ctl_idx) = match *el {
+                Element::Cccs(ref mut src) => (&src.ident, &src.vsrc, &mut src.ctl_idx),
+                Element::Ccvs(ref mut src) => (&src.ident, &src.vsrc, &mut src.ctl_idx),
+                Element::S(Switch{ ref ident, control: Control::Current(ref vsrc, ref mut ctl_idx), ..}) => {
+                    (ident, vsrc, ctl_idx)
+                },
+                _ => continue,
+            };
+            if let Some(&idx) = vsrcs.get(vsrc) {
+                *ctl_idx = idx;
+                trace!("Resolved {}: controlled by {} (idx:{})", ident, vsrc, idx);
+            } else {
+                println!("*ERROR* Can't find voltage source '{}' controlling '{}'",
+                    vsrc, ident);
+                there_are_errors = true;
+            }
+        }
+        there_are_errors
+    }
+
+    /// Find the `NodeId`s of hierarchical nodes used by `B` sources
+    ///
+    /// Nodes in the `B` source's own scope are connected during expansion.
+    /// Anything else (`v(X1.out)`) is left as `NodeId::MAX`, so look up its
+    /// full name now that all the subcircuits have been expanded.
+    pub fn resolve_behavioural_nodes(&mut self) -> bool {
+        let mut there_are_errors = false;
+
+        for el in &mut self.elements {
+            if let Element::B(ref mut bsrc) = *el {
+                for var in &mut bsrc.vars {
+                    if let Var::Node(ref name, ref mut nid) = *var {
+                        if *nid != NodeId::MAX {
+                            continue;
+                        }
+                        if let Some(node_id) = self.nodes.get(name) {
+                            *nid = *node_id;
+                            trace!("Resolved {}: node {} (id:{})", bsrc.ident, name, nid);
+                        } else {
+                            println!("*ERROR* Can't find node '{}' used in '{}'",
+                                name, bsrc.ident);
+                            there_are_errors = true;
+                        }
+                    }
+                }
+            }
+        }
+        there_are_errors
+    }
+
+    /// Build branch current index lookup
+    /// Only elements with identifiers make it in here
+    pub fn build_branch_id_lut(&mut self) {
+        for el in &self.elements {
+            match *el {
+                Element::V(ref vsrc) => {
+                    self.branch_id_lut.insert(vsrc.idx, vsrc.ident.to_string());
+                },
+                Element::Vsin(ref vsrc) => {
+                    self.branch_id_lut.insert(vsrc.idx, vsrc.ident.to_string());
+                },
+                Element::Vwave(ref vsrc) => {
+                    self.branch_id_lut.insert(vsrc.idx, vsrc.ident.to_string());
+                },
+                Element::Vcvs(ref vcvs) => {
+                    self.branch_id_lut.insert(vcvs.idx, vcvs.ident.to_string());
+                },
+                Element::Ccvs(ref ccvs) => {
+                    self.branch_id_lut.insert(ccvs.idx, ccvs.ident.to_string());
+                },
+                Element::B(ref bsrc) if bsrc.kind == BehaviouralKind::Voltage => {
+                    self.branch_id_lut.insert(bsrc.idx, bsrc.ident.to_string());
+                },
+                Element::L(ref ind) => {
+                    self.branch_id_lut.insert(ind.idx, ind.ident.to_string());
+                },
+                _ => {}
+            }
         }
     }
 
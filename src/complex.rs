@@ -0,0 +1,134 @@
+//! Complex Numbers
+//!
+//! Just enough complex arithmetic for the small-signal AC solve. Not
+//! bringing in a crate for this.
+
+use std::fmt;
+use std::ops::{Add, Sub, Mul, Div, AddAssign, SubAssign};
+
+/// Complex number in rectangular form
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+
+    pub fn new(re: f64, im: f64) -> Complex {
+        Complex { re, im }
+    }
+
+    /// Build from magnitude and phase (in degrees)
+    pub fn from_polar_deg(mag: f64, phase: f64) -> Complex {
+        let rad = phase.to_radians();
+        Complex { re: mag * rad.cos(), im: mag * rad.sin() }
+    }
+
+    /// Magnitude
+    pub fn norm(&self) -> f64 {
+        self.re.hypot(self.im)
+    }
+
+    /// Phase in degrees
+    pub fn arg_deg(&self) -> f64 {
+        self.im.atan2(self.re).to_degrees()
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.re == 0.0 && self.im == 0.0
+    }
+
+    pub fn is_finite(&self) -> bool {
+        self.re.is_finite() && self.im.is_finite()
+    }
+
+}
+
+impl From<f64> for Complex {
+    fn from(re: f64) -> Complex {
+        Complex { re, im: 0.0 }
+    }
+}
+
+impl Add for Complex {
+    type Output = Complex;
+    fn add(self, rhs: Complex) -> Complex {
+        Complex::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Complex;
+    fn sub(self, rhs: Complex) -> Complex {
+        Complex::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Complex;
+    fn mul(self, rhs: Complex) -> Complex {
+        Complex::new(
+            self.re * rhs.re - self.im * rhs.im,
+            self.re * rhs.im + self.im * rhs.re,
+        )
+    }
+}
+
+impl Div for Complex {
+    type Output = Complex;
+    fn div(self, rhs: Complex) -> Complex {
+        let den = rhs.re * rhs.re + rhs.im * rhs.im;
+        Complex::new(
+            (self.re * rhs.re + self.im * rhs.im) / den,
+            (self.im * rhs.re - self.re * rhs.im) / den,
+        )
+    }
+}
+
+impl AddAssign for Complex {
+    fn add_assign(&mut self, rhs: Complex) {
+        self.re += rhs.re;
+        self.im += rhs.im;
+    }
+}
+
+impl SubAssign for Complex {
+    fn sub_assign(&mut self, rhs: Complex) {
+        self.re -= rhs.re;
+        self.im -= rhs.im;
+    }
+}
+
+impl fmt::Display for Complex {
+    fn fmt (&self, f:&mut fmt::Formatter) -> fmt::Result {
+        if self.im < 0.0 {
+            write!(f, "{}-j{}", self.re, -self.im)
+        } else {
+            write!(f, "{}+j{}", self.re, self.im)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn divide_by_conjugate() {
+        let a = Complex::new(3.0, 4.0);
+        let b = Complex::new(3.0, -4.0);
+        let c = a / b;
+        assert!((c.re - (-7.0/25.0)).abs() < 1e-12);
+        assert!((c.im - (24.0/25.0)).abs() < 1e-12);
+        assert!((c.norm() - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn polar() {
+        let a = Complex::from_polar_deg(2.0, 90.0);
+        assert!(a.re.abs() < 1e-12);
+        assert!((a.im - 2.0).abs() < 1e-12);
+        assert!((a.arg_deg() - 90.0).abs() < 1e-9);
+    }
+}
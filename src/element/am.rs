@@ -0,0 +1,78 @@
+//! Amplitude-Modulated Waveform Implementation
+//!
+//! `AM(va vo mf fc td [phasem phasec])`, for `V` and `I` sources:
+//!
+//! ```text
+//! v(t) = va * (vo + sin(2pi mf (t - td) + phasem)) * sin(2pi fc (t - td) + phasec)
+//! ```
+//!
+//! `mf` is the modulating frequency, `fc` the carrier frequency and `vo`
+//! the offset added to the modulating signal. The waveform is 0 until the
+//! delay `td`. The phases are in degrees, and default to 0.
+
+use std::f64::consts::PI;
+
+/// Sinewave carrier, amplitude-modulated by another sinewave
+#[derive(Clone, Debug)]
+pub struct Am {
+    pub va: f64,
+    pub vo: f64,
+    pub mf: f64,
+    pub fc: f64,
+    pub td: f64,
+    pub phasem: f64, // degrees
+    pub phasec: f64, // degrees
+}
+
+impl Am {
+
+    /// Build the waveform from the list of numbers in `AM(...)`
+    pub fn from_values(vals: &[f64]) -> Option<Am> {
+        if vals.len() < 4 || vals.len() > 7 {
+            return None
+        }
+        Some(Am {
+            va: vals[0],
+            vo: vals[1],
+            mf: vals[2],
+            fc: vals[3],
+            td: vals.get(4).copied().unwrap_or(0.0),
+            phasem: vals.get(5).copied().unwrap_or(0.0),
+            phasec: vals.get(6).copied().unwrap_or(0.0),
+        })
+    }
+
+    // calculate the value at a certain time
+    pub fn evaluate(&self, t: f64) -> f64 {
+        if t < self.td {
+            return 0.0;
+        }
+        let t = t - self.td;
+        let envelope = self.vo + (2.0 * PI * self.mf * t + self.phasem.to_radians()).sin();
+        self.va * envelope * (2.0 * PI * self.fc * t + self.phasec.to_radians()).sin()
+    }
+
+    /// The waveform starts abruptly if there's a carrier phase, so land on
+    /// the delay
+    pub fn breakpoints(&self, t_stop: f64) -> Vec<f64> {
+        if self.td <= t_stop { vec![self.td] } else { vec![] }
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn envelope() {
+        // at the peaks of a 1kHz carrier, the 100Hz envelope shows through
+        let am = Am::from_values(&[2.0, 1.0, 100.0, 1e3, 1e-3]).unwrap();
+        assert_eq!(am.evaluate(0.5e-3), 0.0);
+        for k in 0..10 {
+            let t = (k as f64 + 0.25) * 1e-3;
+            let envelope = 2.0 * (1.0 + (2.0 * PI * 100.0 * t).sin());
+            assert!((am.evaluate(t + 1e-3) - envelope).abs() < 1e-9);
+        }
+    }
+}
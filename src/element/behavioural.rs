@@ -0,0 +1,584 @@
+//! Behavioural Source Implementation
+//!
+//! `B<ident> <n+> <n-> V={<expr>}` or `I={<expr>}`, where the expression can
+//! use node voltages `v(a)`, `v(a,b)` and the branch currents of voltage
+//! sources `i(Vx)`. The braces are optional.
+//!
+//! Expressions support `+ - * / ^`, brackets, numbers with the usual
+//! engineering suffixes and the functions below.
+//!
+//! * `sin`, `cos`, `tan`, `atan`, `sinh`, `cosh`, `tanh`
+//! * `exp`, `ln` (or `log`), `log10`, `sqrt`, `abs`
+//! * `min(a,b)`, `max(a,b)`, `pow(a,b)`
+//!
+//! The Jacobian entries for Newton-Raphson come from evaluating the
+//! expression with forward-mode automatic differentiation.
+
+use crate::circuit::NodeId;
+
+/// Program exectution trace macro - prefix `<bsrc>`
+macro_rules! trace {
+    ($fmt:expr $(, $($arg:tt)*)?) => {
+        // uncomment the line below for tracing prints
+        //println!(concat!("<bsrc> ", $fmt), $($($arg)*)?);
+    };
+}
+
+/// Is the source a voltage or a current?
+#[derive(Clone, Debug, PartialEq)]
+pub enum BehaviouralKind {
+    Voltage,
+    Current,
+}
+
+/// A quantity from the circuit that an expression uses
+///
+/// The names are filled in from the SPICE deck. The `NodeId` and branch
+/// current index are only known once the circuit has been expanded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Var {
+    Node(String, NodeId),
+    Branch(String, usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Func {
+    Sin, Cos, Tan, Atan, Sinh, Cosh, Tanh,
+    Exp, Ln, Log10, Sqrt, Abs,
+    Min, Max, Pow,
+}
+
+impl Func {
+
+    fn from_name(name: &str) -> Option<(Func, usize)> {
+        let f = match name.to_lowercase().as_str() {
+            "sin" => (Func::Sin, 1),
+            "cos" => (Func::Cos, 1),
+            "tan" => (Func::Tan, 1),
+            "atan" => (Func::Atan, 1),
+            "sinh" => (Func::Sinh, 1),
+            "cosh" => (Func::Cosh, 1),
+            "tanh" => (Func::Tanh, 1),
+            "exp" => (Func::Exp, 1),
+            "ln" | "log" => (Func::Ln, 1),
+            "log10" => (Func::Log10, 1),
+            "sqrt" => (Func::Sqrt, 1),
+            "abs" => (Func::Abs, 1),
+            "min" => (Func::Min, 2),
+            "max" => (Func::Max, 2),
+            "pow" => (Func::Pow, 2),
+            _ => return None,
+        };
+        Some(f)
+    }
+
+}
+
+/// Expression tree
+#[derive(Clone, Debug)]
+pub enum Expr {
+    Num(f64),
+    Var(usize), // index into the source's list of `Var`s
+    Neg(Box<Expr>),
+    Add(Box<Expr>, Box<Expr>),
+    Sub(Box<Expr>, Box<Expr>),
+    Mul(Box<Expr>, Box<Expr>),
+    Div(Box<Expr>, Box<Expr>),
+    Pow(Box<Expr>, Box<Expr>),
+    Call(Func, Vec<Expr>),
+}
+
+/// A value, and its partial derivatives with respect to each `Var`
+#[derive(Clone, Debug)]
+struct Dual {
+    v: f64,
+    d: Vec<f64>,
+}
+
+impl Dual {
+
+    fn constant(v: f64, n: usize) -> Dual {
+        Dual { v, d: vec![0.0; n] }
+    }
+
+    /// Apply `f(v)` with derivative `df_dv` using the chain rule
+    fn chain(self, v: f64, df_dv: f64) -> Dual {
+        Dual { v, d: self.d.iter().map(|d| d * df_dv).collect() }
+    }
+
+    /// Combine two values, given `df/da` and `df/db`
+    fn combine(a: &Dual, b: &Dual, v: f64, df_da: f64, df_db: f64) -> Dual {
+        let d = a.d.iter().zip(&b.d)
+            .map(|(da, db)| df_da * da + df_db * db)
+            .collect();
+        Dual { v, d }
+    }
+
+}
+
+impl Expr {
+
+    fn eval(&self, x: &[f64]) -> Dual {
+        let n = x.len();
+        match *self {
+            Expr::Num(v) => Dual::constant(v, n),
+            Expr::Var(k) => {
+                let mut d = Dual::constant(x[k], n);
+                d.d[k] = 1.0;
+                d
+            },
+            Expr::Neg(ref a) => {
+                let a = a.eval(x);
+                let v = -a.v;
+                a.chain(v, -1.0)
+            },
+            Expr::Add(ref a, ref b) => {
+                let (a, b) = (a.eval(x), b.eval(x));
+                Dual::combine(&a, &b, a.v + b.v, 1.0, 1.0)
+            },
+            Expr::Sub(ref a, ref b) => {
+                let (a, b) = (a.eval(x), b.eval(x));
+                Dual::combine(&a, &b, a.v - b.v, 1.0, -1.0)
+            },
+            Expr::Mul(ref a, ref b) => {
+                let (a, b) = (a.eval(x), b.eval(x));
+                Dual::combine(&a, &b, a.v * b.v, b.v, a.v)
+            },
+            Expr::Div(ref a, ref b) => {
+                let (a, b) = (a.eval(x), b.eval(x));
+                Dual::combine(&a, &b, a.v / b.v, 1.0 / b.v, -a.v / (b.v * b.v))
+            },
+            Expr::Pow(ref a, ref b) => {
+                let (a, b) = (a.eval(x), b.eval(x));
+                pow(&a, &b)
+            },
+            Expr::Call(func, ref args) => {
+                let args: Vec<Dual> = args.iter().map(|a| a.eval(x)).collect();
+                call(func, args)
+            },
+        }
+    }
+
+}
+
+fn pow(a: &Dual, b: &Dual) -> Dual {
+    let v = a.v.powf(b.v);
+    let df_da = b.v * a.v.powf(b.v - 1.0);
+    // only defined for positive bases, and only needed for non-constant
+    // exponents
+    let df_db = if a.v > 0.0 { v * a.v.ln() } else { 0.0 };
+    Dual::combine(a, b, v, df_da, df_db)
+}
+
+fn call(func: Func, mut args: Vec<Dual>) -> Dual {
+    let a = args.remove(0);
+    let u = a.v;
+    match func {
+        Func::Sin => a.chain(u.sin(), u.cos()),
+        Func::Cos => a.chain(u.cos(), -u.sin()),
+        Func::Tan => a.chain(u.tan(), 1.0 / (u.cos() * u.cos())),
+        Func::Atan => a.chain(u.atan(), 1.0 / (1.0 + u * u)),
+        Func::Sinh => a.chain(u.sinh(), u.cosh()),
+        Func::Cosh => a.chain(u.cosh(), u.sinh()),
+        Func::Tanh => {
+            let t = u.tanh();
+            a.chain(t, 1.0 - t * t)
+        },
+        Func::Exp => a.chain(u.exp(), u.exp()),
+        Func::Ln => a.chain(u.ln(), 1.0 / u),
+        Func::Log10 => a.chain(u.log10(), 1.0 / (u * std::f64::consts::LN_10)),
+        Func::Sqrt => a.chain(u.sqrt(), 0.5 / u.sqrt()),
+        Func::Abs => a.chain(u.abs(), if u < 0.0 { -1.0 } else { 1.0 }),
+        Func::Min => {
+            let b = args.remove(0);
+            if u <= b.v { a } else { b }
+        },
+        Func::Max => {
+            let b = args.remove(0);
+            if u >= b.v { a } else { b }
+        },
+        Func::Pow => {
+            let b = args.remove(0);
+            pow(&a, &b)
+        },
+    }
+}
+
+
+/// Recursive-descent parser for behavioural expressions
+struct Parser<'a> {
+    text: &'a str,
+    chars: Vec<char>,
+    i: usize,
+    vars: Vec<Var>,
+}
+
+impl<'a> Parser<'a> {
+
+    fn peek(&mut self) -> Option<char> {
+        while self.i < self.chars.len() && self.chars[self.i].is_whitespace() {
+            self.i += 1;
+        }
+        self.chars.get(self.i).copied()
+    }
+
+    fn expect(&mut self, c: char) -> Option<()> {
+        if self.peek() == Some(c) {
+            self.i += 1;
+            Some(())
+        } else {
+            self.error(&format!("expected '{}'", c))
+        }
+    }
+
+    fn error<T>(&self, msg: &str) -> Option<T> {
+        println!("*ERROR* {} at position {} in '{}'", msg, self.i, self.text);
+        None
+    }
+
+    // expr := term (('+'|'-') term)*
+    fn expr(&mut self) -> Option<Expr> {
+        let mut lhs = self.term()?;
+        loop {
+            match self.peek() {
+                Some('+') => {
+                    self.i += 1;
+                    lhs = Expr::Add(Box::new(lhs), Box::new(self.term()?));
+                },
+                Some('-') => {
+                    self.i += 1;
+                    lhs = Expr::Sub(Box::new(lhs), Box::new(self.term()?));
+                },
+                _ => return Some(lhs),
+            }
+        }
+    }
+
+    // term := unary (('*'|'/') unary)*
+    fn term(&mut self) -> Option<Expr> {
+        let mut lhs = self.unary()?;
+        loop {
+            match self.peek() {
+                Some('*') if self.chars.get(self.i + 1) != Some(&'*') => {
+                    self.i += 1;
+                    lhs = Expr::Mul(Box::new(lhs), Box::new(self.unary()?));
+                },
+                Some('/') => {
+                    self.i += 1;
+                    lhs = Expr::Div(Box::new(lhs), Box::new(self.unary()?));
+                },
+                _ => return Some(lhs),
+            }
+        }
+    }
+
+    // unary := ('-'|'+') unary | power
+    fn unary(&mut self) -> Option<Expr> {
+        match self.peek() {
+            Some('-') => {
+                self.i += 1;
+                Some(Expr::Neg(Box::new(self.unary()?)))
+            },
+            Some('+') => {
+                self.i += 1;
+                self.unary()
+            },
+            _ => self.power(),
+        }
+    }
+
+    // power := atom (('^'|'**') unary)?
+    fn power(&mut self) -> Option<Expr> {
+        let base = self.atom()?;
+        match self.peek() {
+            Some('^') => {
+                self.i += 1;
+            },
+            Some('*') if self.chars.get(self.i + 1) == Some(&'*') => {
+                self.i += 2;
+            },
+            _ => return Some(base),
+        }
+        Some(Expr::Pow(Box::new(base), Box::new(self.unary()?)))
+    }
+
+    // atom := number | '(' expr ')' | v(a[,b]) | i(Vx) | func '(' args ')'
+    fn atom(&mut self) -> Option<Expr> {
+        match self.peek() {
+            Some('(') => {
+                self.i += 1;
+                let e = self.expr()?;
+                self.expect(')')?;
+                Some(e)
+            },
+            Some(c) if c.is_ascii_digit() || c == '.' => self.number(),
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.word();
+                self.expect('(')?;
+                match name.to_lowercase().as_str() {
+                    "v" => self.voltage(),
+                    "i" => self.current(),
+                    _ => self.function(&name),
+                }
+            },
+            Some(c) => self.error(&format!("unexpected '{}'", c)),
+            None => self.error("unexpected end of expression"),
+        }
+    }
+
+    fn word(&mut self) -> String {
+        let start = self.i;
+        while self.i < self.chars.len()
+            && (self.chars[self.i].is_ascii_alphanumeric() || self.chars[self.i] == '_')
+        {
+            self.i += 1;
+        }
+        self.chars[start..self.i].iter().collect()
+    }
+
+    /// A node or source name, which can also be hierarchical (`X1.out`,
+    /// `X1.Vs`) or internal (`D1#a`)
+    fn hier_name(&mut self) -> String {
+        let start = self.i;
+        while self.i < self.chars.len()
+            && (self.chars[self.i].is_ascii_alphanumeric()
+                || matches!(self.chars[self.i], '_' | '.' | '#'))
+        {
+            self.i += 1;
+        }
+        self.chars[start..self.i].iter().collect()
+    }
+
+    fn number(&mut self) -> Option<Expr> {
+        let start = self.i;
+        while self.i < self.chars.len()
+            && (self.chars[self.i].is_ascii_digit() || self.chars[self.i] == '.')
+        {
+            self.i += 1;
+        }
+
+        // exponent, only if there's a number after the `e`
+        if matches!(self.chars.get(self.i), Some('e') | Some('E')) {
+            let mut j = self.i + 1;
+            if matches!(self.chars.get(j), Some('+') | Some('-')) {
+                j += 1;
+            }
+            if self.chars.get(j).is_some_and(|c| c.is_ascii_digit()) {
+                self.i = j;
+                while self.i < self.chars.len() && self.chars[self.i].is_ascii_digit() {
+                    self.i += 1;
+                }
+            }
+        }
+        let mantissa: String = self.chars[start..self.i].iter().collect();
+        let Ok(value) = mantissa.parse::<f64>() else {
+            return self.error(&format!("bad number '{}'", mantissa));
+        };
+
+        // engineering suffix, with any units after it ignored
+        let suffix = self.word();
+        let scale = match suffix.chars().next() {
+            Some('k') => 1e3,
+            Some('m') => 1e-3,
+            Some('u') => 1e-6,
+            Some('n') => 1e-9,
+            Some('p') => 1e-12,
+            _ => 1.0,
+        };
+        Some(Expr::Num(value * scale))
+    }
+
+    fn var(&mut self, var: Var) -> Expr {
+        let k = if let Some(k) = self.vars.iter().position(|v| *v == var) {
+            k
+        } else {
+            self.vars.push(var);
+            self.vars.len() - 1
+        };
+        Expr::Var(k)
+    }
+
+    fn voltage(&mut self) -> Option<Expr> {
+        self.peek();
+        let a = self.hier_name();
+        if a.is_empty() {
+            return self.error("expected a node name");
+        }
+        let va = self.var(Var::Node(a, 0));
+        if self.peek() == Some(',') {
+            self.i += 1;
+            self.peek();
+            let b = self.hier_name();
+            if b.is_empty() {
+                return self.error("expected a node name");
+            }
+            let vb = self.var(Var::Node(b, 0));
+            self.expect(')')?;
+            return Some(Expr::Sub(Box::new(va), Box::new(vb)));
+        }
+        self.expect(')')?;
+        Some(va)
+    }
+
+    fn current(&mut self) -> Option<Expr> {
+        self.peek();
+        let vsrc = self.hier_name();
+        if !vsrc.rsplit('.').next().unwrap_or("").starts_with('V') {
+            return self.error(&format!("i() needs a voltage source, not '{}'", vsrc));
+        }
+        self.expect(')')?;
+        Some(self.var(Var::Branch(vsrc, 0)))
+    }
+
+    fn function(&mut self, name: &str) -> Option<Expr> {
+        let Some((func, num_args)) = Func::from_name(name) else {
+            return self.error(&format!("unknown function '{}'", name));
+        };
+        let mut args = vec![self.expr()?];
+        while args.len() < num_args {
+            self.expect(',')?;
+            args.push(self.expr()?);
+        }
+        self.expect(')')?;
+        Some(Expr::Call(func, args))
+    }
+
+}
+
+/// Parse a behavioural expression, returning the expression tree and the
+/// circuit quantities it uses.
+pub fn parse_expression(text: &str) -> Option<(Expr, Vec<Var>)> {
+    let mut parser = Parser {
+        text,
+        chars: text.chars().collect(),
+        i: 0,
+        vars: vec![],
+    };
+    let expr = parser.expr()?;
+    if let Some(c) = parser.peek() {
+        return parser.error(&format!("unexpected '{}'", c));
+    }
+    trace!("{} -> {:?} {:?}", text, expr, parser.vars);
+    Some((expr, parser.vars))
+}
+
+
+#[derive(Clone)]
+pub struct BehaviouralSource {
+    pub ident: String,
+    pub p: NodeId,
+    pub n: NodeId,
+    pub kind: BehaviouralKind,
+    pub expr: Expr,
+    pub vars: Vec<Var>,
+    pub idx: usize, // index of voltage source in "known" column, if `V=`
+}
+
+impl BehaviouralSource {
+
+    pub fn new(
+        ident: &str,
+        p: NodeId,
+        n: NodeId,
+        kind: BehaviouralKind,
+        expr: Expr,
+        vars: Vec<Var>,
+        idx: usize,
+    ) -> Self {
+        BehaviouralSource {
+            ident: ident.to_string(),
+            p, n, kind, expr, vars, idx,
+        }
+    }
+
+    /// Position of a `Var` in the vector of unknowns
+    fn unknown(&self, var: &Var, c_nodes: usize) -> usize {
+        match *var {
+            Var::Node(_, nid) => nid,
+            Var::Branch(_, idx) => c_nodes + idx,
+        }
+    }
+
+    /// Evaluate the expression at the unknowns `x`, returning the value and
+    /// its partial derivatives keyed on position in the vector of unknowns.
+    ///
+    /// Ground never appears in the derivatives.
+    pub fn evaluate(&self, x: &[f64], c_nodes: usize) -> (f64, Vec<(usize, f64)>) {
+        let values: Vec<f64> = self.vars.iter()
+            .map(|var| x[self.unknown(var, c_nodes)])
+            .collect();
+        let result = self.expr.eval(&values);
+
+        let derivs = self.vars.iter()
+            .map(|var| self.unknown(var, c_nodes))
+            .zip(result.d)
+            .filter(|(u, _)| *u != 0)
+            .collect();
+        (result.v, derivs)
+    }
+
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn eval(text: &str, x: &[f64]) -> (f64, Vec<f64>) {
+        let (expr, _) = parse_expression(text).unwrap();
+        let r = expr.eval(x);
+        (r.v, r.d)
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2*3 - 4/2", &[]).0, 5.0);
+        assert_eq!(eval("-2^2", &[]).0, -4.0);
+        assert_eq!(eval("2**3**2", &[]).0, 512.0);
+        assert_eq!(eval("(1+2)*3", &[]).0, 9.0);
+        assert!((eval("10k * 1.5m + 2e-3", &[]).0 - 15.002).abs() < 1e-12);
+    }
+
+    #[test]
+    fn derivatives() {
+        let (v, d) = eval("tanh(v(in)*3)", &[0.1]);
+        assert!((v - 0.3_f64.tanh()).abs() < 1e-12);
+        assert!((d[0] - 3.0 * (1.0 - 0.3_f64.tanh().powi(2))).abs() < 1e-12);
+
+        let (v, d) = eval("v(a,b) * i(Vs) / 2", &[3.0, 1.0, 0.5]);
+        assert_eq!(v, 0.5);
+        assert_eq!(d, vec![0.25, -0.25, 1.0]);
+    }
+
+    #[test]
+    fn vars_are_shared() {
+        let (_, vars) = parse_expression("v(a)*v(a) + v(a,b)").unwrap();
+        assert_eq!(vars, vec![Var::Node("a".to_string(), 0), Var::Node("b".to_string(), 0)]);
+    }
+
+    #[test]
+    fn hierarchical_nodes() {
+        let (_, vars) = parse_expression("v(X1.out)*2 + v(X1.D1#a, 0)").unwrap();
+        assert_eq!(vars, vec![
+            Var::Node("X1.out".to_string(), 0),
+            Var::Node("X1.D1#a".to_string(), 0),
+            Var::Node("0".to_string(), 0),
+        ]);
+    }
+
+    #[test]
+    fn hierarchical_sources() {
+        let (_, vars) = parse_expression("i(X1.Vs) * 1k").unwrap();
+        assert_eq!(vars, vec![Var::Branch("X1.Vs".to_string(), 0)]);
+        assert!(parse_expression("i(X1.R1)").is_none());
+    }
+
+    #[test]
+    fn bad_expressions() {
+        assert!(parse_expression("1 +").is_none());
+        assert!(parse_expression("foo(1)").is_none());
+        assert!(parse_expression("i(R1)").is_none());
+        assert!(parse_expression("(1").is_none());
+        assert!(parse_expression("1 2").is_none());
+    }
+}
@@ -0,0 +1,211 @@
+//! Bipolar Junction Transistor Implementation
+//!
+//! Ebers-Moll transport model, with the base-width modulation (Early
+//! effect) part of Gummel-Poon. No high-injection or series resistances.
+//!
+//! `.model` parameters:
+//!
+//! * `IS`  - transport saturation current (1e-16 A)
+//! * `BF`  - ideal maximum forward beta (100)
+//! * `BR`  - ideal maximum reverse beta (1)
+//! * `VAF` - forward Early voltage (infinite)
+//! * `NF`  - forward current emission coefficient (1)
+//! * `CJE` - base-emitter junction capacitance (0 F)
+//! * `CJC` - base-collector junction capacitance (0 F)
+//! * `TF`  - forward transit time (0 s)
+//!
+//! The junction capacitances are taken as constant, at their zero-bias
+//! values.
+
+use std::cell::Cell;
+use crate::circuit::{NodeId, GMIN};
+use crate::model::{Model, ModelKind};
+use crate::element::diode::{thermal_voltage, critical_voltage, limit_junction_voltage};
+
+/// Program exectution trace macro - prefix `<bjt>`
+macro_rules! trace {
+    ($fmt:expr $(, $($arg:tt)*)?) => {
+        // uncomment the line below for tracing prints
+        //println!(concat!("<bjt> ", $fmt), $($($arg)*)?);
+    };
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Polarity {
+    Npn,
+    Pnp,
+}
+
+/// Linearized BJT at a bias point
+///
+/// Terminal currents flow into the device. The conductances are the
+/// partial derivatives of the terminal currents with respect to `v_be`
+/// and `v_bc`. `v_be` and `v_bc` are the (limited) junction voltages the
+/// device was linearized at.
+#[derive(Clone, Debug)]
+pub struct BjtCompanion {
+    pub i_c: f64,
+    pub i_b: f64,
+    pub g_c_be: f64,
+    pub g_c_bc: f64,
+    pub g_b_be: f64,
+    pub g_b_bc: f64,
+    pub v_be: f64,
+    pub v_bc: f64,
+}
+
+#[derive(Clone)]
+pub struct Bjt {
+    pub ident: String,
+    pub c: NodeId,
+    pub b: NodeId,
+    pub e: NodeId,
+    pub polarity: Polarity,
+    pub i_sat: f64,
+    pub beta_f: f64,
+    pub beta_r: f64,
+    pub v_af: f64, // zero means infinite
+    pub n_f: f64,
+    pub c_je: f64,
+    pub c_jc: f64,
+    pub t_f: f64,
+    pub tdegc: f64,
+    v_thermal: f64,
+    v_crit_be: f64,
+    v_crit_bc: f64,
+    v_be_prev: Cell<f64>,
+    v_bc_prev: Cell<f64>,
+}
+
+impl Bjt {
+
+    pub fn new(ident: &str, c: NodeId, b: NodeId, e: NodeId, model: &Model) -> Bjt {
+        let polarity = match model.kind {
+            ModelKind::Npn => Polarity::Npn,
+            ModelKind::Pnp => Polarity::Pnp,
+            _ => panic!("*FATAL* Model '{}' is not a BJT model", model.name),
+        };
+        let mut q = Bjt {
+            ident: ident.to_string(),
+            c,
+            b,
+            e,
+            polarity,
+            i_sat: model.get_or("IS", 1e-16),
+            beta_f: model.get_or("BF", 100.0),
+            beta_r: model.get_or("BR", 1.0),
+            v_af: model.get_or("VAF", 0.0),
+            n_f: model.get_or("NF", 1.0),
+            c_je: model.get_or("CJE", 0.0),
+            c_jc: model.get_or("CJC", 0.0),
+            t_f: model.get_or("TF", 0.0),
+            tdegc: 27.0,
+            v_thermal: 0.0,
+            v_crit_be: 0.0,
+            v_crit_bc: 0.0,
+            v_be_prev: Cell::new(0.0),
+            v_bc_prev: Cell::new(0.0),
+        };
+        q.update_v_thermal();
+        q
+    }
+
+    /// +1.0 for NPN, -1.0 for PNP
+    fn sign(&self) -> f64 {
+        match self.polarity {
+            Polarity::Npn => 1.0,
+            Polarity::Pnp => -1.0,
+        }
+    }
+
+    /// Linearize around the junction voltages `v_be = v(b) - v(e)` and
+    /// `v_bc = v(b) - v(c)`.
+    pub fn linearize(&self, v_be_hat: f64, v_bc_hat: f64) -> BjtCompanion {
+        let s = self.sign();
+        let vt_f = self.n_f * self.v_thermal;
+
+        // work in NPN-land, limiting the excursions of both junctions
+        let v_be = limit_junction_voltage(s * v_be_hat, self.v_be_prev.get(),
+            vt_f, self.v_crit_be);
+        let v_bc = limit_junction_voltage(s * v_bc_hat, self.v_bc_prev.get(),
+            self.v_thermal, self.v_crit_bc);
+        trace!("{} v_be {} -> {}, v_bc {} -> {}",
+            self.ident, v_be_hat, v_be, v_bc_hat, v_bc);
+
+        // forward and reverse diode currents and their conductances
+        let exp_be = (v_be / vt_f).exp();
+        let exp_bc = (v_bc / self.v_thermal).exp();
+        let i_f = self.i_sat * (exp_be - 1.0);
+        let i_r = self.i_sat * (exp_bc - 1.0);
+        let g_f = self.i_sat * exp_be / vt_f;
+        let g_r = self.i_sat * exp_bc / self.v_thermal;
+
+        if !i_f.is_finite() || !i_r.is_finite() {
+            panic!("*FATAL* Possibly bad BJT currents {} {}", i_f, i_r);
+        }
+
+        // base-width modulation
+        let (k_early, dk_dvbc) = if self.v_af > 0.0 {
+            (1.0 - v_bc / self.v_af, -1.0 / self.v_af)
+        } else {
+            (1.0, 0.0)
+        };
+
+        // transport current
+        let i_ct = (i_f - i_r) * k_early;
+        let g_ct_be = g_f * k_early;
+        let g_ct_bc = -g_r * k_early + (i_f - i_r) * dk_dvbc;
+
+        // terminal currents, with a GMIN across each junction
+        let i_b = i_f / self.beta_f + i_r / self.beta_r + GMIN * (v_be + v_bc);
+        let i_c = i_ct - i_r / self.beta_r - GMIN * v_bc;
+
+        self.v_be_prev.set(v_be);
+        self.v_bc_prev.set(v_bc);
+
+        BjtCompanion {
+            i_c: s * i_c,
+            i_b: s * i_b,
+            g_c_be: g_ct_be,
+            g_c_bc: g_ct_bc - g_r / self.beta_r - GMIN,
+            g_b_be: g_f / self.beta_f + GMIN,
+            g_b_bc: g_r / self.beta_r + GMIN,
+            v_be: s * v_be,
+            v_bc: s * v_bc,
+        }
+    }
+
+    /// Check that the junction voltages from the solver are the ones the
+    /// device was last linearized at.
+    ///
+    /// If the junction limiting kicked in, the node voltages can settle while
+    /// the device is still a long way off its proper bias point.
+    pub fn is_converged(&self, v_be: f64, v_bc: f64, reltol: f64, vntol: f64) -> bool {
+        let s = self.sign();
+        let close = |v: f64, v_lin: f64| (v - v_lin).abs() <= v.abs() * reltol + vntol;
+        close(s * v_be, self.v_be_prev.get()) && close(s * v_bc, self.v_bc_prev.get())
+    }
+
+    /// Base-emitter and base-collector capacitances at a bias point
+    ///
+    /// The base-emitter one includes the diffusion capacitance `TF * g_f`.
+    pub fn capacitances(&self, v_be: f64) -> (f64, f64) {
+        let vt_f = self.n_f * self.v_thermal;
+        let v_be = self.sign() * v_be;
+        let g_f = self.i_sat * (v_be / vt_f).exp() / vt_f;
+        (self.c_je + self.t_f * g_f, self.c_jc)
+    }
+
+    /// Does this transistor store any charge?
+    pub fn has_capacitance(&self) -> bool {
+        self.c_je > 0.0 || self.c_jc > 0.0 || self.t_f > 0.0
+    }
+
+    /// thermal voltage, and the critical voltages that depend on it
+    fn update_v_thermal(&mut self) {
+        self.v_thermal = thermal_voltage(self.tdegc);
+        self.v_crit_be = critical_voltage(self.n_f * self.v_thermal, self.i_sat);
+        self.v_crit_bc = critical_voltage(self.v_thermal, self.i_sat);
+    }
+
+}
@@ -1,6 +1,7 @@
 //! Capacitor Implementation
 
 use crate::circuit::{NodeId};
+use crate::integration::Derivative;
 
 #[derive(Clone)]
 pub struct Capacitor {
@@ -8,6 +9,7 @@ pub struct Capacitor {
     pub a: NodeId,
     pub b: NodeId,
     pub value: f64, // Farads
+    pub ic: Option<f64>, // initial voltage for `uic`
 }
 
 impl Capacitor {
@@ -18,15 +20,16 @@ impl Capacitor {
             a,
             b,
             value,
+            ic: None,
         }
     }
 
-    pub fn linearize(&self, v_prev: f64, t_delta: f64) -> (f64, f64) {
-        // v_prev: voltage across cap at last solved timepoint
-        // t_delta: change in time between now and last solved timepoint
-        // implements backward-euler integration
-        let g_eq = self.value / t_delta;
-        let i_eq = g_eq * v_prev;
+    pub fn linearize(&self, v_hist: &[f64], i_prev: f64, d: &Derivative) -> (f64, f64) {
+        // v_hist: voltages across cap at the last solved timepoints, newest first
+        // i_prev: current through cap at last solved timepoint
+        // d: how the derivative is formed from them
+        let g_eq = self.value * d.a[0];
+        let i_eq = -(self.value * d.history(v_hist) + d.b * i_prev);
         (g_eq, i_eq)
     }
 
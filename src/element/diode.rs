@@ -1,7 +1,20 @@
 //! Diode Implementation
+//!
+//! `.model <name> D (...)` parameters:
+//!
+//! * `IS`  - saturation current (1e-14 A)
+//! * `N`   - emission coefficient (1)
+//! * `RS`  - series resistance (0 Ohms), on an internal anode node
+//! * `CJO` - zero-bias junction capacitance (0 F)
+//! * `VJ`  - junction potential (1 V)
+//! * `M`   - junction grading coefficient (0.5)
+//! * `TT`  - transit time (0 s)
+//! * `BV`  - reverse breakdown voltage (none)
+//! * `IBV` - current at the breakdown voltage (1e-3 A)
 
 use std::cell::Cell;
 use crate::circuit::{NodeId, BOLTZMANN, CHARGE, GMIN};
+use crate::model::{Model, ModelKind};
 
 /// Program exectution trace macro - prefix `<diode>`
 macro_rules! trace {
@@ -11,6 +24,9 @@ macro_rules! trace {
     };
 }
 
+/// Forward-bias depletion capacitance coefficient
+const FC: f64 = 0.5;
+
 #[derive(Clone)]
 pub struct Diode {
     pub ident: String,
@@ -18,6 +34,14 @@ pub struct Diode {
     pub n: NodeId,
     pub i_sat: f64,
     pub tdegc: f64,
+    pub n_emit: f64,
+    pub r_s: f64,
+    pub c_jo: f64,
+    pub v_j: f64,
+    pub m_j: f64,
+    pub t_t: f64,
+    pub bv: f64, // zero means no breakdown
+    pub ibv: f64,
     v_thermal: f64,
     v_crit: f64,
     v_d_prev: Cell<f64>,
@@ -34,6 +58,14 @@ impl Diode {
             n,
             i_sat,
             tdegc,
+            n_emit: 1.0,
+            r_s: 0.0,
+            c_jo: 0.0,
+            v_j: 1.0,
+            m_j: 0.5,
+            t_t: 0.0,
+            bv: 0.0,
+            ibv: 1e-3,
             v_thermal: 0.0,
             v_crit: 0.0,
             v_d_prev: Cell::new(0.0),
@@ -45,40 +77,58 @@ impl Diode {
         d
     }
 
+    /// Diode with its parameters from a `.model` card
+    ///
+    /// `p` is the junction's anode. If the model has an `RS`, this should
+    /// be an internal node, with the resistor to the real anode added
+    /// separately.
+    pub fn from_model(ident: &str, p: NodeId, n: NodeId, model: &Model) -> Diode {
+        if model.kind != ModelKind::Diode {
+            panic!("*FATAL* Model '{}' is not a diode model", model.name);
+        }
+        let mut d = Diode::new(ident, p, n, model.get_or("IS", 1e-14), 27.0);
+        d.n_emit = model.get_or("N", d.n_emit);
+        d.r_s = model.get_or("RS", d.r_s);
+        d.c_jo = model.get_or("CJO", d.c_jo);
+        d.v_j = model.get_or("VJ", d.v_j);
+        d.m_j = model.get_or("M", d.m_j);
+        d.t_t = model.get_or("TT", d.t_t);
+        d.bv = model.get_or("BV", d.bv);
+        d.ibv = model.get_or("IBV", d.ibv);
+        d.update_v_thermal();
+        d.update_v_crit();
+        d
+    }
+
 
     // http://dev.hypertriton.com/edacious/trunk/doc/lec.pdf
     // page 4 of 14
     pub fn linearize(&self, v_hat: f64, _: f64) -> (f64, f64) {
-       
-        // limit the excursion, following Colon via Nagel
+
+        // limit the excursion, following Colon via Nagel. In breakdown, the
+        // junction is limited as if it were forward biased the other way.
         let v_d_prev = self.v_d_prev.get();
-        let v_delta = v_hat - v_d_prev;
-
-        let v_d_i :f64;
-        #[allow(clippy::if_same_then_else)] // FIXME??
-        if v_hat < self.v_crit {
-            v_d_i = v_hat;
-        } else if v_delta.abs() <= 2.0 * self.v_thermal {
-            v_d_i = v_hat;
-        } else if v_d_prev <= 0.0 {
-            v_d_i = self.v_thermal * (v_hat / self.v_thermal).ln();
+        let vt_n = self.n_emit * self.v_thermal;
+        let v_d_i = if self.bv > 0.0 && v_hat < (10.0 * vt_n - self.bv).min(0.0) {
+            -(limit_junction_voltage(-(v_hat + self.bv), -(v_d_prev + self.bv),
+                vt_n, self.v_crit) + self.bv)
         } else {
-
-            let arg :f64 = 1.0 + (v_delta / self.v_thermal);
-            if arg <= 0.0 {
-                v_d_i  = self.v_crit;
-            } else {
-                v_d_i = v_d_prev + self.v_thermal * arg.ln();
-            }
-            trace!("v_d {}, v_d_prev {}", v_hat, v_d_prev);
-            trace!(" arg {}, v_d_i {}", arg, v_d_i);
-        }
+            limit_junction_voltage(v_hat, v_d_prev, vt_n, self.v_crit)
+        };
         trace!("V_d from {} V to {} V (v_crit={})", v_hat, v_d_i, self.v_crit);
 
         // current through the diode, given the bias voltage
-        let exp_vd_over_vt =(v_d_i / self.v_thermal).exp();
+        let exp_vd_over_vt =(v_d_i / vt_n).exp();
 
-        let i_d = self.i_sat * ( exp_vd_over_vt - 1.0 );
+        let mut i_d = self.i_sat * ( exp_vd_over_vt - 1.0 );
+        let mut g_d = (self.i_sat / vt_n) * exp_vd_over_vt;
+
+        // reverse breakdown
+        if self.bv > 0.0 {
+            let exp_bv = (-(v_d_i + self.bv) / vt_n).exp();
+            i_d -= self.ibv * exp_bv;
+            g_d += self.ibv * exp_bv / vt_n;
+        }
 
         // calculate the diode companion model parameters
         // companion model is a current source in parallel with a resistor
@@ -87,7 +137,7 @@ impl Diode {
         if i_d.is_finite() {
 
             // Equivalent conductance, limited to help convergence
-            g_eq = (self.i_sat / self.v_thermal) * exp_vd_over_vt;
+            g_eq = g_d;
             if g_eq < GMIN {
                 g_eq = GMIN;
             }
@@ -111,27 +161,100 @@ impl Diode {
         (g_eq, i_eq)
     }
 
+    /// Junction plus diffusion capacitance at a bias point
+    ///
+    /// The depletion capacitance is extended linearly above `FC * VJ`.
+    pub fn capacitance(&self, v_d: f64) -> f64 {
+        let c_j = if v_d < FC * self.v_j {
+            self.c_jo / (1.0 - v_d / self.v_j).powf(self.m_j)
+        } else {
+            self.c_jo / (1.0 - FC).powf(1.0 + self.m_j)
+                * (1.0 - FC * (1.0 + self.m_j) + self.m_j * v_d / self.v_j)
+        };
+
+        let vt_n = self.n_emit * self.v_thermal;
+        let g_d = (self.i_sat / vt_n) * (v_d / vt_n).exp();
+        c_j + self.t_t * g_d
+    }
+
+    /// Does this diode store any charge?
+    pub fn has_capacitance(&self) -> bool {
+        self.c_jo > 0.0 || self.t_t > 0.0
+    }
+
     /// thermal voltage. Should be ~26mV at room temperature
     fn update_v_thermal(&mut self) {
-        self.v_thermal = BOLTZMANN * (363.0 + self.tdegc) / CHARGE;
+        self.v_thermal = thermal_voltage(self.tdegc);
     }
 
     /// critical voltage. Colon limiting method
     /// See Nagel, section 5
     fn update_v_crit(&mut self) {
-        // critical voltage for Colon
-        self.v_crit = 
-            self.v_thermal 
-            * ( self.v_thermal / ( (2.0_f64).sqrt() * self.i_sat ) )
-            .ln();
+        self.v_crit = critical_voltage(self.n_emit * self.v_thermal, self.i_sat);
     }
 
 }
 
+
+/// Thermal voltage of a pn-junction at `tdegc`
+pub fn thermal_voltage(tdegc: f64) -> f64 {
+    BOLTZMANN * (273.15 + tdegc) / CHARGE
+}
+
+/// Critical voltage for Colon limiting
+pub fn critical_voltage(v_thermal: f64, i_sat: f64) -> f64 {
+    v_thermal * ( v_thermal / ( (2.0_f64).sqrt() * i_sat ) ).ln()
+}
+
+/// Limit the change in a pn-junction voltage between Newton iterations
+///
+/// Follows Colon via Nagel. `v_hat` is the candidate voltage from the
+/// solver and `v_prev` is the voltage the junction was last linearized at.
+pub fn limit_junction_voltage(v_hat: f64, v_prev: f64, v_thermal: f64, v_crit: f64) -> f64 {
+    let v_delta = v_hat - v_prev;
+
+    let v_i :f64;
+    #[allow(clippy::if_same_then_else)] // FIXME??
+    if v_hat < v_crit {
+        v_i = v_hat;
+    } else if v_delta.abs() <= 2.0 * v_thermal {
+        v_i = v_hat;
+    } else if v_prev <= 0.0 {
+        v_i = v_thermal * (v_hat / v_thermal).ln();
+    } else {
+
+        let arg :f64 = 1.0 + (v_delta / v_thermal);
+        if arg <= 0.0 {
+            v_i  = v_crit;
+        } else {
+            v_i = v_prev + v_thermal * arg.ln();
+        }
+        trace!("v_hat {}, v_prev {}", v_hat, v_prev);
+        trace!(" arg {}, v_i {}", arg, v_i);
+    }
+    v_i
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn junction_capacitance() {
+        let mut diode = Diode::new("Dcap", 0, 1, 1e-14, 27.0);
+        diode.c_jo = 4e-12;
+        diode.v_j = 0.8;
+        diode.m_j = 0.5;
+        assert!((diode.capacitance(0.0) - 4e-12).abs() < 1e-18);
+        assert!((diode.capacitance(-2.4) - 2e-12).abs() < 1e-18);
+
+        // diffusion capacitance follows the small-signal conductance
+        diode.c_jo = 0.0;
+        diode.t_t = 20e-9;
+        let (g_eq, _) = diode.linearize(0.6, 0.6);
+        assert!((diode.capacitance(0.6) - 20e-9 * g_eq).abs() < 1e-18);
+    }
+
     #[test]
     fn curve_trace() {
         const VMAX: f64 = 5.0;
@@ -0,0 +1,78 @@
+//! Exponential Waveform Implementation
+//!
+//! `EXP(v1 v2 td1 tau1 td2 tau2)`, for `V` and `I` sources. The waveform
+//! sits at `v1` until `td1`, heads towards `v2` with time constant `tau1`,
+//! then from `td2` heads back to `v1` with time constant `tau2`.
+//!
+//! `td2` and `tau2` are optional. Unlike ngspice, if `td2` is missing the
+//! waveform never heads back, and `tau2` defaults to `tau1`.
+
+/// Exponential rise and fall
+#[derive(Clone, Debug)]
+pub struct Exponential {
+    pub v1: f64,
+    pub v2: f64,
+    pub td1: f64,
+    pub tau1: f64,
+    pub td2: f64,
+    pub tau2: f64,
+}
+
+impl Exponential {
+
+    /// Build the waveform from the list of numbers in `EXP(...)`
+    pub fn from_values(vals: &[f64]) -> Option<Exponential> {
+        if vals.len() < 4 || vals.len() > 6 {
+            return None
+        }
+        let tau1 = vals[3];
+        let tau2 = vals.get(5).copied().unwrap_or(tau1);
+        if tau1 <= 0.0 || tau2 <= 0.0 {
+            return None
+        }
+        Some(Exponential {
+            v1: vals[0],
+            v2: vals[1],
+            td1: vals[2],
+            tau1,
+            td2: vals.get(4).copied().unwrap_or(f64::INFINITY),
+            tau2,
+        })
+    }
+
+    // calculate the value at a certain time
+    pub fn evaluate(&self, t: f64) -> f64 {
+        let mut v = self.v1;
+        if t > self.td1 {
+            v += (self.v2 - self.v1) * (1.0 - (-(t - self.td1) / self.tau1).exp());
+        }
+        if t > self.td2 {
+            v += (self.v1 - self.v2) * (1.0 - (-(t - self.td2) / self.tau2).exp());
+        }
+        v
+    }
+
+    /// Times of the corners of the waveform, up to `t_stop`
+    pub fn breakpoints(&self, t_stop: f64) -> Vec<f64> {
+        [self.td1, self.td2].iter().copied()
+            .filter(|&t| t <= t_stop)
+            .collect()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rise_and_fall() {
+        let wave = Exponential::from_values(&[0.0, 1.0, 1.0, 2.0, 3.0, 0.5]).unwrap();
+        assert_eq!(wave.evaluate(1.0), 0.0);
+        assert!((wave.evaluate(3.0) - (1.0 - (-1.0_f64).exp())).abs() < 1e-12);
+
+        // a long time after td2, it's back to v1
+        assert!(wave.evaluate(100.0).abs() < 1e-12);
+        assert_eq!(wave.breakpoints(2.0), vec![1.0]);
+    }
+}
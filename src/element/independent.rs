@@ -6,18 +6,37 @@ use crate::circuit::NodeId;
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct CurrentSource {
+    pub ident: String,
     pub p: NodeId,
     pub n: NodeId,
     pub value: f64, // Amperes
+    pub ac_mag: f64, // AC small-signal magnitude (A)
+    pub ac_phase: f64, // AC small-signal phase (degrees)
+}
+
+impl CurrentSource {
+    /// DC current source with no identifier and no AC spec
+    pub fn new(p: NodeId, n: NodeId, value: f64) -> CurrentSource {
+        CurrentSource { ident: String::new(), p, n, value, ac_mag: 0.0, ac_phase: 0.0 }
+    }
 }
 
 /// Voltage Source Implementation
 #[allow(dead_code)]
 #[derive(Clone)]
 pub struct VoltageSource {
+    pub ident: String,
     pub p: NodeId,
     pub n: NodeId,
     pub value: f64, // Volts
     pub idx: usize, // index of voltage source in "known" column
+    pub ac_mag: f64, // AC small-signal magnitude (V)
+    pub ac_phase: f64, // AC small-signal phase (degrees)
 }
 
+impl VoltageSource {
+    /// DC voltage source with no identifier and no AC spec
+    pub fn new(p: NodeId, n: NodeId, value: f64, idx: usize) -> VoltageSource {
+        VoltageSource { ident: String::new(), p, n, value, idx, ac_mag: 0.0, ac_phase: 0.0 }
+    }
+}
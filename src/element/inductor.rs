@@ -0,0 +1,49 @@
+//! Inductor Implementation
+//!
+//! The inductor current is an extra unknown in the MNA matrix, in the same
+//! way as the current through a voltage source. This gives:
+//!
+//! * a short circuit for DC: `v(a) - v(b) = 0`
+//! * a companion model for transient: `v(a) - v(b) - r_eq * i = -v_eq`
+//!
+//! The voltage across the inductor at the last timepoint, which the
+//! trapezoidal rule needs, is already in the solution vector, as are the
+//! previous currents for Gear.
+
+use crate::circuit::{NodeId};
+use crate::integration::Derivative;
+
+#[derive(Clone)]
+pub struct Inductor {
+    pub ident: String,
+    pub a: NodeId,
+    pub b: NodeId,
+    pub value: f64, // Henries
+    pub idx: usize, // index of branch current in "known" column
+    pub ic: Option<f64>, // initial current for `uic`
+}
+
+impl Inductor {
+
+    pub fn new(ident: &str, a: NodeId, b: NodeId, value: f64, idx: usize) -> Inductor {
+        Inductor {
+            ident: ident.to_string(),
+            a,
+            b,
+            value,
+            idx,
+            ic: None,
+        }
+    }
+
+    pub fn linearize(&self, i_hist: &[f64], v_prev: f64, d: &Derivative) -> (f64, f64) {
+        // i_hist: currents through the inductor at the last solved
+        //   timepoints, newest first
+        // v_prev: voltage across the inductor at last solved timepoint
+        // d: how the derivative is formed from them
+        let r_eq = self.value * d.a[0];
+        let v_eq = -(self.value * d.history(i_hist) + d.b * v_prev);
+        (r_eq, v_eq)
+    }
+
+}
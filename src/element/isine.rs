@@ -1,4 +1,7 @@
 //! Sinusoidal Current Source Implementation
+//!
+//! `SIN(io ia freq td theta phase)`, the same shape as the sinusoidal
+//! voltage source.
 
 use crate::circuit::{NodeId};
 use std::f64::consts::PI;
@@ -10,14 +13,21 @@ pub struct CurrentSourceSine {
     pub vo: f64, // offset (A)
     pub va: f64, // amplitude (A)
     pub freq: f64, // frequency (HZ)
+    pub td: f64, // delay (s)
+    pub theta: f64, // damping factor (1/s)
+    pub phase: f64, // phase (degrees)
 }
 
 impl CurrentSourceSine {
 
     // calculate the value at a certain time
     pub fn evaluate(&self, t: f64) -> f64 {
-        self.vo + self.va * (2.0 * PI * self.freq * t).sin()
+        let phase = self.phase.to_radians();
+        if t <= self.td {
+            return self.vo + self.va * phase.sin();
+        }
+        let t = t - self.td;
+        self.vo + self.va * (-self.theta * t).exp() * (2.0 * PI * self.freq * t + phase).sin()
     }
 
 }
-    
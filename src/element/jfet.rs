@@ -0,0 +1,217 @@
+//! JFET Implementation
+//!
+//! Shichman-Hodges drain current, with a diode from the gate to each of the
+//! source and drain. No series resistances or capacitances.
+//!
+//! `.model` parameters:
+//!
+//! * `VTO`    - threshold (pinch-off) voltage (-2 V)
+//! * `BETA`   - transconductance parameter (1e-4 A/V^2)
+//! * `LAMBDA` - channel-length modulation (0 1/V)
+//! * `IS`     - gate junction saturation current (1e-14 A)
+//!
+//! As in SPICE, `VTO` is negative for depletion devices of both polarities.
+
+use std::cell::Cell;
+use crate::circuit::{NodeId, GMIN};
+use crate::model::{Model, ModelKind};
+use crate::element::diode::{thermal_voltage, critical_voltage, limit_junction_voltage};
+
+/// Program exectution trace macro - prefix `<jfet>`
+macro_rules! trace {
+    ($fmt:expr $(, $($arg:tt)*)?) => {
+        // uncomment the line below for tracing prints
+        //println!(concat!("<jfet> ", $fmt), $($($arg)*)?);
+    };
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Polarity {
+    Njf,
+    Pjf,
+}
+
+/// Linearized JFET at a bias point
+///
+/// `i_d` is the channel current, flowing into the drain and out of the
+/// source, and `g_m` and `g_ds` are its derivatives with respect to `v_gs`
+/// and `v_ds`. `i_gs` and `i_gd` are the gate junction currents, flowing
+/// out of the gate. `v_gs` and `v_gd` are the (limited) junction voltages
+/// the device was linearized at.
+#[derive(Clone, Debug)]
+pub struct JfetCompanion {
+    pub i_d: f64,
+    pub g_m: f64,
+    pub g_ds: f64,
+    pub i_gs: f64,
+    pub g_gs: f64,
+    pub i_gd: f64,
+    pub g_gd: f64,
+    pub v_gs: f64,
+    pub v_gd: f64,
+}
+
+#[derive(Clone)]
+pub struct Jfet {
+    pub ident: String,
+    pub d: NodeId,
+    pub g: NodeId,
+    pub s: NodeId,
+    pub polarity: Polarity,
+    pub vto: f64,
+    pub beta: f64,
+    pub lambda: f64,
+    pub i_sat: f64,
+    pub tdegc: f64,
+    v_thermal: f64,
+    v_crit: f64,
+    v_gs_prev: Cell<f64>,
+    v_gd_prev: Cell<f64>,
+}
+
+impl Jfet {
+
+    pub fn new(ident: &str, d: NodeId, g: NodeId, s: NodeId, model: &Model) -> Jfet {
+        let polarity = match model.kind {
+            ModelKind::Njf => Polarity::Njf,
+            ModelKind::Pjf => Polarity::Pjf,
+            _ => panic!("*FATAL* Model '{}' is not a JFET model", model.name),
+        };
+        let mut j = Jfet {
+            ident: ident.to_string(),
+            d,
+            g,
+            s,
+            polarity,
+            vto: model.get_or("VTO", -2.0),
+            beta: model.get_or("BETA", 1e-4),
+            lambda: model.get_or("LAMBDA", 0.0),
+            i_sat: model.get_or("IS", 1e-14),
+            tdegc: 27.0,
+            v_thermal: 0.0,
+            v_crit: 0.0,
+            v_gs_prev: Cell::new(0.0),
+            v_gd_prev: Cell::new(0.0),
+        };
+        j.update_v_thermal();
+        j
+    }
+
+    /// +1.0 for NJF, -1.0 for PJF
+    fn sign(&self) -> f64 {
+        match self.polarity {
+            Polarity::Njf => 1.0,
+            Polarity::Pjf => -1.0,
+        }
+    }
+
+    /// Channel current and its derivatives `(i_d, g_m, g_ds)`, in NJF-land
+    /// with `v_ds >= 0`
+    fn ids(&self, v_gs: f64, v_ds: f64) -> (f64, f64, f64) {
+        let v_gst = v_gs - self.vto;
+        let clm = 1.0 + self.lambda * v_ds;
+
+        if v_gst <= 0.0 {
+            (0.0, 0.0, 0.0)
+        } else if v_ds < v_gst {
+            let i_d = self.beta * v_ds * (2.0 * v_gst - v_ds) * clm;
+            let g_m = 2.0 * self.beta * v_ds * clm;
+            let g_ds = 2.0 * self.beta * (v_gst - v_ds) * clm
+                + self.beta * self.lambda * v_ds * (2.0 * v_gst - v_ds);
+            (i_d, g_m, g_ds)
+        } else {
+            let i_d = self.beta * v_gst * v_gst * clm;
+            let g_m = 2.0 * self.beta * v_gst * clm;
+            let g_ds = self.beta * v_gst * v_gst * self.lambda;
+            (i_d, g_m, g_ds)
+        }
+    }
+
+    /// Linearize around the gate junction voltages `v_gs = v(g) - v(s)` and
+    /// `v_gd = v(g) - v(d)`.
+    pub fn linearize(&self, v_gs_hat: f64, v_gd_hat: f64) -> JfetCompanion {
+        let s = self.sign();
+
+        // work in NJF-land, limiting the excursions of both junctions
+        let v_gs = limit_junction_voltage(s * v_gs_hat, self.v_gs_prev.get(),
+            self.v_thermal, self.v_crit);
+        let v_gd = limit_junction_voltage(s * v_gd_hat, self.v_gd_prev.get(),
+            self.v_thermal, self.v_crit);
+        trace!("{} v_gs {} -> {}, v_gd {} -> {}",
+            self.ident, v_gs_hat, v_gs, v_gd_hat, v_gd);
+
+        // gate junctions, with a GMIN across each
+        let exp_gs = (v_gs / self.v_thermal).exp();
+        let exp_gd = (v_gd / self.v_thermal).exp();
+        let i_gs = self.i_sat * (exp_gs - 1.0) + GMIN * v_gs;
+        let i_gd = self.i_sat * (exp_gd - 1.0) + GMIN * v_gd;
+        let g_gs = self.i_sat * exp_gs / self.v_thermal + GMIN;
+        let g_gd = self.i_sat * exp_gd / self.v_thermal + GMIN;
+
+        if !i_gs.is_finite() || !i_gd.is_finite() {
+            panic!("*FATAL* Possibly bad JFET gate currents {} {}", i_gs, i_gd);
+        }
+
+        // channel
+        let v_ds = v_gs - v_gd;
+        let (i_d, g_m, g_ds) = if v_ds >= 0.0 {
+            self.ids(v_gs, v_ds)
+        } else {
+            // source and drain swap roles
+            let (i_d, g_m, g_ds) = self.ids(v_gd, -v_ds);
+            (-i_d, -g_m, g_m + g_ds)
+        };
+
+        self.v_gs_prev.set(v_gs);
+        self.v_gd_prev.set(v_gd);
+
+        JfetCompanion {
+            i_d: s * i_d,
+            g_m,
+            g_ds,
+            i_gs: s * i_gs,
+            g_gs,
+            i_gd: s * i_gd,
+            g_gd,
+            v_gs: s * v_gs,
+            v_gd: s * v_gd,
+        }
+    }
+
+    /// Check that the junction voltages from the solver are the ones the
+    /// device was last linearized at.
+    pub fn is_converged(&self, v_gs: f64, v_gd: f64, reltol: f64, vntol: f64) -> bool {
+        let s = self.sign();
+        let close = |v: f64, v_lin: f64| (v - v_lin).abs() <= v.abs() * reltol + vntol;
+        close(s * v_gs, self.v_gs_prev.get()) && close(s * v_gd, self.v_gd_prev.get())
+    }
+
+    /// thermal voltage, and the critical voltage that depends on it
+    fn update_v_thermal(&mut self) {
+        self.v_thermal = thermal_voltage(self.tdegc);
+        self.v_crit = critical_voltage(self.v_thermal, self.i_sat);
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn njf() -> Jfet {
+        let mut model = Model::new("nj", ModelKind::Njf);
+        model.params.push(("VTO".to_string(), -2.0));
+        model.params.push(("BETA".to_string(), 1e-3));
+        Jfet::new("J1", 1, 2, 0, &model)
+    }
+
+    #[test]
+    fn reversed_channel_is_antisymmetric() {
+        // with the gate at the same potential as both ends in turn, swapping
+        // drain and source should only flip the sign of the current
+        let fwd = njf().linearize(0.0, -1.0);
+        let rev = njf().linearize(-1.0, 0.0);
+        assert!((fwd.i_d + rev.i_d).abs() < 1e-15);
+        assert!((fwd.i_d - 3e-3).abs() < 1e-12);
+    }
+}
@@ -4,17 +4,32 @@ use std::fmt;
 
 pub mod resistor;
 pub mod capacitor;
+pub mod inductor;
+pub mod mutual;
 pub mod diode;
+pub mod bjt;
+pub mod mosfet;
+pub mod jfet;
+pub mod switch;
 
 pub mod isine;
 pub mod vsine;
 
-pub mod vpwl;
+
+pub mod pulse;
+pub mod pwl;
+pub mod wav;
+pub mod exponential;
+pub mod sffm;
+pub mod am;
+pub mod waveform;
 
 pub mod independent;
 
 pub mod vdepsrc;
 
+pub mod behavioural;
+
 /// Circuit Elements that this simulator supports
 #[allow(dead_code)]
 #[derive(Clone)]
@@ -23,12 +38,22 @@ pub enum Element {
     I(independent::CurrentSource),
     V(independent::VoltageSource),
     D(diode::Diode),
+    Q(bjt::Bjt),
+    M(mosfet::Mosfet),
+    J(jfet::Jfet),
+    S(switch::Switch),
     Isin(isine::CurrentSourceSine),
     Vsin(vsine::VoltageSourceSine),
-    Vpwl(vpwl::VoltageSourcePwl),
+    Iwave(waveform::CurrentSourceWaveform),
+    Vwave(waveform::VoltageSourceWaveform),
     C(capacitor::Capacitor),
+    L(inductor::Inductor),
+    K(mutual::MutualInductance),
     Vcvs(vdepsrc::Vcvs),
     Vccs(vdepsrc::Vccs),
+    Cccs(vdepsrc::Cccs),
+    Ccvs(vdepsrc::Ccvs),
+    B(behavioural::BehaviouralSource),
 }
 
 
@@ -36,35 +61,63 @@ impl fmt::Display for Element {
     fn fmt (&self, f:&mut fmt::Formatter) -> fmt::Result {
         match *self {
             Element::I(ref el) => {
-                write!(f, "I p:{} n:{} {} A", el.p, el.n, el.value)
+                write!(f, "I p:{} n:{} {} A ({})", el.p, el.n, el.value, el.ident)
             },
             Element::R(ref el) => {
                 write!(f, "R a:{} b:{} {} Ohms ({})",
                     el.a, el.b, el.value, el.ident)
             },
             Element::V(ref el) => {
-                write!(f, "V a:{} b:{} {} Volts", el.p, el.n, el.value)
+                write!(f, "V a:{} b:{} {} Volts ({})", el.p, el.n, el.value, el.ident)
             },
             Element::D(ref el) => {
                 write!(f, "D p:{} n:{} I_sat={} A ({})",
                     el.p, el.n, el.i_sat, el.ident)
             },
             Element::Isin(ref el) => {
-                write!(f, "Isin p:{} n:{} = {} + {} * sin(2pi {})",
-                    el.p, el.n, el.vo, el.va, el.freq)
+                write!(f, "Isin p:{} n:{} = {} + {} * sin(2pi {}) delay {} damping {} phase {}",
+                    el.p, el.n, el.vo, el.va, el.freq, el.td, el.theta, el.phase)
             },
             Element::Vsin(ref el) => {
-                write!(f, "Vsin p:{} n:{} = {} + {} * sin(2pi {})",
-                    el.p, el.n, el.vo, el.va, el.freq)
+                write!(f, "Vsin p:{} n:{} = {} + {} * sin(2pi {}) delay {} damping {} phase {} ({})",
+                    el.p, el.n, el.vo, el.va, el.freq, el.td, el.theta, el.phase, el.ident)
             },
-            Element::Vpwl(ref el) => {
-                write!(f, "Vpwl p:{} n:{}",
-                    el.p, el.n)
+            Element::Iwave(ref el) => {
+                write!(f, "I p:{} n:{} {} ({})",
+                    el.p, el.n, el.wave.name(), el.ident)
+            },
+            Element::Vwave(ref el) => {
+                write!(f, "V p:{} n:{} {} ({})",
+                    el.p, el.n, el.wave.name(), el.ident)
             },
             Element::C(ref el) => {
                 write!(f, "C a:{} b:{} {} Farads ({})",
                     el.a, el.b, el.value, el.ident)
             },
+            Element::L(ref el) => {
+                write!(f, "L a:{} b:{} {} Henries ({})",
+                    el.a, el.b, el.value, el.ident)
+            },
+            Element::K(ref el) => {
+                write!(f, "K {} {} k={} {} Henries ({})",
+                    el.l1, el.l2, el.k, el.value, el.ident)
+            },
+            Element::Q(ref el) => {
+                write!(f, "Q c:{} b:{} e:{} {:?} ({})",
+                    el.c, el.b, el.e, el.polarity, el.ident)
+            },
+            Element::M(ref el) => {
+                write!(f, "M d:{} g:{} s:{} b:{} {:?} W={} L={} ({})",
+                    el.d, el.g, el.s, el.b, el.polarity, el.w, el.l, el.ident)
+            },
+            Element::J(ref el) => {
+                write!(f, "J d:{} g:{} s:{} {:?} ({})",
+                    el.d, el.g, el.s, el.polarity, el.ident)
+            },
+            Element::S(ref el) => {
+                write!(f, "S p:{} n:{} ctl:{:?} Ron={} Roff={} ({})",
+                    el.p, el.n, el.control, el.r_on, el.r_off, el.ident)
+            },
             Element::Vcvs(ref el) => {
                 write!(f, "VCVS p:{} n:{} cp:{} cn:{} k={} ({})",
                     el.p, el.n, el.cp, el.cn, el.k, el.ident)
@@ -73,6 +126,18 @@ impl fmt::Display for Element {
                 write!(f, "VCCS p:{} n:{} cp:{} cn:{} k={} ({})",
                     el.p, el.n, el.cp, el.cn, el.k, el.ident)
             },
+            Element::Cccs(ref el) => {
+                write!(f, "CCCS p:{} n:{} ctl:{} k={} ({})",
+                    el.p, el.n, el.vsrc, el.k, el.ident)
+            },
+            Element::Ccvs(ref el) => {
+                write!(f, "CCVS p:{} n:{} ctl:{} k={} ({})",
+                    el.p, el.n, el.vsrc, el.k, el.ident)
+            },
+            Element::B(ref el) => {
+                write!(f, "B p:{} n:{} {:?} ({})",
+                    el.p, el.n, el.kind, el.ident)
+            },
         }
     }
 }
@@ -0,0 +1,274 @@
+//! MOSFET Implementation
+//!
+//! Level-1 (Shichman-Hodges) model, with Meyer gate capacitances.
+//!
+//! `.model` parameters:
+//!
+//! * `VTO`    - zero-bias threshold voltage (0 V)
+//! * `KP`     - transconductance parameter (2e-5 A/V^2)
+//! * `LAMBDA` - channel-length modulation (0 1/V)
+//! * `GAMMA`  - bulk threshold parameter (0 V^0.5)
+//! * `PHI`    - surface potential (0.6 V)
+//! * `TOX`    - oxide thickness (no gate capacitance if not given)
+//!
+//! Instance parameters `W` and `L` default to 100um. There are no bulk
+//! junction diodes.
+
+use crate::circuit::{NodeId, GMIN};
+use crate::model::{Model, ModelKind};
+
+/// Program exectution trace macro - prefix `<mosfet>`
+macro_rules! trace {
+    ($fmt:expr $(, $($arg:tt)*)?) => {
+        // uncomment the line below for tracing prints
+        //println!(concat!("<mosfet> ", $fmt), $($($arg)*)?);
+    };
+}
+
+/// Permittivity of silicon dioxide, F/m
+const EPS_OX: f64 = 3.9 * 8.854e-12;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Polarity {
+    Nmos,
+    Pmos,
+}
+
+/// Operating region of a MOSFET
+#[derive(Clone, Debug, PartialEq)]
+pub enum Region {
+    Cutoff,
+    Linear,
+    Saturation,
+}
+
+/// Linearized MOSFET at a bias point
+///
+/// `i_d` is the drain current, flowing into the drain and out of the
+/// source. The conductances are its partial derivatives with respect to
+/// `v_gs`, `v_ds` and `v_bs`.
+#[derive(Clone, Debug)]
+pub struct MosfetCompanion {
+    pub i_d: f64,
+    pub g_m: f64,
+    pub g_ds: f64,
+    pub g_mb: f64,
+    pub region: Region,
+}
+
+#[derive(Clone)]
+pub struct Mosfet {
+    pub ident: String,
+    pub d: NodeId,
+    pub g: NodeId,
+    pub s: NodeId,
+    pub b: NodeId,
+    pub polarity: Polarity,
+    pub vto: f64,
+    pub kp: f64,
+    pub lambda: f64,
+    pub gamma: f64,
+    pub phi: f64,
+    pub tox: f64, // zero means no gate capacitance
+    pub w: f64,
+    pub l: f64,
+}
+
+impl Mosfet {
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        ident: &str,
+        d: NodeId,
+        g: NodeId,
+        s: NodeId,
+        b: NodeId,
+        model: &Model,
+        w: f64,
+        l: f64,
+    ) -> Mosfet {
+        let polarity = match model.kind {
+            ModelKind::Nmos => Polarity::Nmos,
+            ModelKind::Pmos => Polarity::Pmos,
+            _ => panic!("*FATAL* Model '{}' is not a MOSFET model", model.name),
+        };
+        Mosfet {
+            ident: ident.to_string(),
+            d,
+            g,
+            s,
+            b,
+            polarity,
+            vto: model.get_or("VTO", 0.0),
+            kp: model.get_or("KP", 2e-5),
+            lambda: model.get_or("LAMBDA", 0.0),
+            gamma: model.get_or("GAMMA", 0.0),
+            phi: model.get_or("PHI", 0.6),
+            tox: model.get_or("TOX", 0.0),
+            w,
+            l,
+        }
+    }
+
+    /// +1.0 for NMOS, -1.0 for PMOS
+    fn sign(&self) -> f64 {
+        match self.polarity {
+            Polarity::Nmos => 1.0,
+            Polarity::Pmos => -1.0,
+        }
+    }
+
+    /// Threshold voltage, including the body effect, in NMOS-land
+    fn v_th(&self, v_bs: f64) -> (f64, f64) {
+        let vto = self.sign() * self.vto;
+        let sarg = (self.phi - v_bs).max(0.0).sqrt();
+        let v_th = vto + self.gamma * (sarg - self.phi.sqrt());
+
+        // d(v_th)/d(v_bs)
+        let dvth_dvbs = if sarg > 0.0 {
+            -self.gamma / (2.0 * sarg)
+        } else {
+            0.0
+        };
+        (v_th, dvth_dvbs)
+    }
+
+    /// Drain current and its derivatives, in NMOS-land with `v_ds >= 0`
+    fn ids(&self, v_gs: f64, v_ds: f64, v_bs: f64) -> MosfetCompanion {
+        let beta = self.kp * self.w / self.l;
+        let (v_th, dvth_dvbs) = self.v_th(v_bs);
+        let v_gst = v_gs - v_th;
+        let clm = 1.0 + self.lambda * v_ds;
+
+        let (i_d, g_m, g_ds, region) = if v_gst <= 0.0 {
+            (0.0, 0.0, 0.0, Region::Cutoff)
+        } else if v_ds < v_gst {
+            let i_d = beta * (v_gst - v_ds / 2.0) * v_ds * clm;
+            let g_m = beta * v_ds * clm;
+            let g_ds = beta * (v_gst - v_ds) * clm
+                + beta * self.lambda * (v_gst - v_ds / 2.0) * v_ds;
+            (i_d, g_m, g_ds, Region::Linear)
+        } else {
+            let i_d = beta / 2.0 * v_gst * v_gst * clm;
+            let g_m = beta * v_gst * clm;
+            let g_ds = beta / 2.0 * v_gst * v_gst * self.lambda;
+            (i_d, g_m, g_ds, Region::Saturation)
+        };
+
+        MosfetCompanion {
+            i_d,
+            g_m,
+            g_ds,
+            g_mb: -g_m * dvth_dvbs,
+            region,
+        }
+    }
+
+    /// Linearize around `v_gs`, `v_ds` and `v_bs`.
+    pub fn linearize(&self, v_gs: f64, v_ds: f64, v_bs: f64) -> MosfetCompanion {
+        let s = self.sign();
+        let (v_gs, v_ds, v_bs) = (s * v_gs, s * v_ds, s * v_bs);
+
+        let mut lin = if v_ds >= 0.0 {
+            self.ids(v_gs, v_ds, v_bs)
+        } else {
+            // source and drain swap roles
+            let rev = self.ids(v_gs - v_ds, -v_ds, v_bs - v_ds);
+            MosfetCompanion {
+                i_d: -rev.i_d,
+                g_m: -rev.g_m,
+                g_ds: rev.g_m + rev.g_ds + rev.g_mb,
+                g_mb: -rev.g_mb,
+                region: rev.region,
+            }
+        };
+        trace!("{} v_gs={} v_ds={} v_bs={} -> {:?}", self.ident, v_gs, v_ds, v_bs, lin);
+
+        // keep a path from drain to source, even when off
+        lin.i_d = s * (lin.i_d + GMIN * v_ds);
+        lin.g_ds += GMIN;
+        lin
+    }
+
+    /// Operating region at a bias point
+    pub fn region(&self, v_gs: f64, v_ds: f64, v_bs: f64) -> Region {
+        self.linearize(v_gs, v_ds, v_bs).region
+    }
+
+    /// Meyer gate capacitances `(c_gs, c_gd, c_gb)` at a bias point
+    pub fn capacitances(&self, v_gs: f64, v_ds: f64, v_bs: f64) -> (f64, f64, f64) {
+        if self.tox <= 0.0 {
+            return (0.0, 0.0, 0.0);
+        }
+        let c_ox = EPS_OX / self.tox * self.w * self.l;
+
+        let s = self.sign();
+        let (mut v_gs, mut v_ds, mut v_bs) = (s * v_gs, s * v_ds, s * v_bs);
+        let reversed = v_ds < 0.0;
+        if reversed {
+            v_gs -= v_ds;
+            v_bs -= v_ds;
+            v_ds = -v_ds;
+        }
+
+        let (v_th, _) = self.v_th(v_bs);
+        let v_gst = v_gs - v_th;
+
+        let (c_gs, c_gd, c_gb) = if v_gst <= 0.0 {
+            (0.0, 0.0, c_ox)
+        } else if v_ds < v_gst {
+            let den = (2.0 * v_gst - v_ds).powi(2);
+            let c_gs = 2.0 / 3.0 * c_ox * (1.0 - (v_gst - v_ds).powi(2) / den);
+            let c_gd = 2.0 / 3.0 * c_ox * (1.0 - v_gst.powi(2) / den);
+            (c_gs, c_gd, 0.0)
+        } else {
+            (2.0 / 3.0 * c_ox, 0.0, 0.0)
+        };
+
+        if reversed {
+            (c_gd, c_gs, c_gb)
+        } else {
+            (c_gs, c_gd, c_gb)
+        }
+    }
+
+    /// Does this transistor store any charge?
+    pub fn has_capacitance(&self) -> bool {
+        self.tox > 0.0
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nmos() -> Mosfet {
+        let mut model = Model::new("nm", ModelKind::Nmos);
+        model.params.push(("VTO".to_string(), 1.0));
+        model.params.push(("TOX".to_string(), 10e-9));
+        Mosfet::new("M1", 1, 2, 0, 0, &model, 10e-6, 1e-6)
+    }
+
+    #[test]
+    fn meyer_capacitances() {
+        let m = nmos();
+        let c_ox = EPS_OX / 10e-9 * 10e-6 * 1e-6;
+
+        // cutoff: all gate-bulk
+        let (c_gs, c_gd, c_gb) = m.capacitances(0.5, 1.0, 0.0);
+        assert_eq!((c_gs, c_gd), (0.0, 0.0));
+        assert!((c_gb - c_ox).abs() < 1e-20);
+
+        // linear, no v_ds: split evenly between source and drain
+        let (c_gs, c_gd, c_gb) = m.capacitances(3.0, 0.0, 0.0);
+        assert!((c_gs - c_ox / 2.0).abs() < 1e-20);
+        assert!((c_gd - c_ox / 2.0).abs() < 1e-20);
+        assert_eq!(c_gb, 0.0);
+
+        // saturation: all gate-source
+        let (c_gs, c_gd, c_gb) = m.capacitances(3.0, 5.0, 0.0);
+        assert!((c_gs - 2.0 / 3.0 * c_ox).abs() < 1e-20);
+        assert_eq!((c_gd, c_gb), (0.0, 0.0));
+    }
+}
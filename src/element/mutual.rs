@@ -0,0 +1,52 @@
+//! Mutual Inductance Implementation
+//!
+//! `K<ident> <L1> <L2> <k>` couples two inductors. The inductors are only
+//! known by name until the circuit is expanded, after which the branch
+//! current indices and inductances are filled in by
+//! `Circuit::resolve_mutual_inductances()`.
+//!
+//! The coupling adds a `M di/dt` term to each inductor's branch equation,
+//! where `M = k * sqrt(L1 * L2)`.
+
+use crate::integration::Derivative;
+
+#[derive(Clone)]
+pub struct MutualInductance {
+    pub ident: String,
+    pub l1: String, // full hierarchical name of the first inductor
+    pub l2: String, // full hierarchical name of the second inductor
+    pub k: f64, // coupling coefficient
+    pub idx1: usize, // branch current index of the first inductor
+    pub idx2: usize, // branch current index of the second inductor
+    pub value: f64, // mutual inductance in Henries
+}
+
+impl MutualInductance {
+
+    pub fn new(ident: &str, l1: &str, l2: &str, k: f64) -> MutualInductance {
+        MutualInductance {
+            ident: ident.to_string(),
+            l1: l1.to_string(),
+            l2: l2.to_string(),
+            k,
+            idx1: 0,
+            idx2: 0,
+            value: 0.0,
+        }
+    }
+
+    pub fn linearize(&self, i1_hist: &[f64], i2_hist: &[f64], d: &Derivative)
+        -> (f64, f64, f64)
+    {
+        // i1_hist, i2_hist: currents through the inductors at the last
+        //   solved timepoints, newest first
+        // d: how the derivative is formed from them
+        // The inductors' own companion models carry the voltage history
+        // for the trapezoidal rule, so there's no `d.b` term here.
+        let r_eq = self.value * d.a[0];
+        let v1_eq = -self.value * d.history(i2_hist); // goes in the branch equation of L1
+        let v2_eq = -self.value * d.history(i1_hist); // goes in the branch equation of L2
+        (r_eq, v1_eq, v2_eq)
+    }
+
+}
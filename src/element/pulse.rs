@@ -0,0 +1,121 @@
+//! Pulse Waveform Implementation
+//!
+//! `PULSE(v1 v2 td tr tf pw per)`, for `V` and `I` sources. Everything
+//! after `v2` is optional:
+//!
+//! * `td`  - delay before the first rising edge (0 s)
+//! * `tr`  - rise time (0 s)
+//! * `tf`  - fall time (0 s)
+//! * `pw`  - pulse width (forever)
+//! * `per` - period (no repeat)
+//!
+//! Unlike ngspice, a missing or zero `tr` or `tf` is an ideal edge rather
+//! than `TSTEP`. The engine lands on each corner of the waveform, so the
+//! edge happens straight after the timepoint at the corner.
+
+/// Trapezoidal pulse train
+#[derive(Clone, Debug)]
+pub struct Pulse {
+    pub v1: f64,
+    pub v2: f64,
+    pub td: f64,
+    pub tr: f64,
+    pub tf: f64,
+    pub pw: f64,
+    pub per: f64,
+}
+
+impl Pulse {
+
+    /// Build a pulse from the list of numbers in `PULSE(...)`
+    pub fn from_values(vals: &[f64]) -> Option<Pulse> {
+        if vals.len() < 2 || vals.len() > 7 {
+            return None
+        }
+        let get = |i: usize, default: f64| vals.get(i).copied().unwrap_or(default);
+        let per = get(6, f64::INFINITY);
+        Some(Pulse {
+            v1: vals[0],
+            v2: vals[1],
+            td: get(2, 0.0),
+            tr: get(3, 0.0),
+            tf: get(4, 0.0),
+            pw: get(5, f64::INFINITY),
+            per: if per > 0.0 { per } else { f64::INFINITY },
+        })
+    }
+
+    // calculate the value at a certain time
+    pub fn evaluate(&self, t: f64) -> f64 {
+        if t <= self.td {
+            return self.v1;
+        }
+
+        // where in the cycle are we?
+        let mut t_cycle = t - self.td;
+        if t_cycle >= self.per {
+            t_cycle %= self.per;
+        }
+
+        // ideal edges take the value from before the corner
+        if t_cycle == 0.0 {
+            self.v1
+        } else if t_cycle < self.tr {
+            self.v1 + (self.v2 - self.v1) * t_cycle / self.tr
+        } else if t_cycle <= self.tr + self.pw {
+            self.v2
+        } else if t_cycle < self.tr + self.pw + self.tf {
+            self.v2 + (self.v1 - self.v2) * (t_cycle - self.tr - self.pw) / self.tf
+        } else {
+            self.v1
+        }
+    }
+
+    /// Times of the corners of the waveform, up to `t_stop`
+    pub fn breakpoints(&self, t_stop: f64) -> Vec<f64> {
+        let corners = [0.0, self.tr, self.tr + self.pw, self.tr + self.pw + self.tf];
+        let mut bps = vec![];
+        let mut t_start = self.td;
+        while t_start <= t_stop {
+            for corner in corners {
+                let t = t_start + corner;
+                if t <= t_stop {
+                    bps.push(t);
+                }
+            }
+            t_start += self.per;
+        }
+        bps.dedup(); // ideal edges
+        bps
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn corners() {
+        let pulse = Pulse::from_values(&[0.0, 5.0, 1.0, 1.0, 2.0, 3.0, 10.0]).unwrap();
+        for (t, v) in [(0.5, 0.0), (1.5, 2.5), (3.0, 5.0), (4.0, 5.0), (6.0, 2.5), (7.0, 0.0),
+                (9.0, 0.0), (11.5, 2.5), (14.0, 5.0)] {
+            assert_eq!(pulse.evaluate(t), v, "at {}", t);
+        }
+        assert_eq!(pulse.breakpoints(13.0), vec![1.0, 2.0, 5.0, 7.0, 11.0, 12.0]);
+    }
+
+    #[test]
+    fn ideal_edges() {
+        let pulse = Pulse::from_values(&[1.0, -1.0, 1.0]).unwrap();
+        assert_eq!(pulse.evaluate(1.0), 1.0);
+        assert_eq!(pulse.evaluate(1.0 + 1e-12), -1.0);
+        assert_eq!(pulse.evaluate(1e6), -1.0);
+        assert_eq!(pulse.breakpoints(100.0), vec![1.0]);
+
+        let pulse = Pulse::from_values(&[0.0, 1.0, 0.0, 0.0, 0.0, 1.0, 2.0]).unwrap();
+        for (t, v) in [(0.0, 0.0), (0.5, 1.0), (1.0, 1.0), (1.5, 0.0), (2.0, 0.0), (2.5, 1.0)] {
+            assert_eq!(pulse.evaluate(t), v, "at {}", t);
+        }
+    }
+}
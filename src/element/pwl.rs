@@ -0,0 +1,132 @@
+//! Piecewise Linear Waveform Implementation
+//!
+//! `PWL(t1 v1 t2 v2 ...) [td=<delay>] [r=<time>]`, for `V` and `I` sources.
+//! The points can also come from a two-column file with
+//! `PWL FILE=<filename>`.
+//!
+//! * Before the first point, the waveform sits at the first value
+//! * After the last point, it holds the last value...
+//! * ... unless `r` is given, when the section of the pattern from the time
+//!   point `r` to the end repeats forever. `r=0` repeats the lot.
+//! * `td` delays the whole pattern
+
+/// Piecewise linear waveform
+#[derive(Clone, Debug)]
+pub struct Pwl {
+    pub points: Vec<(f64, f64)>, // (time, val)
+    pub td: f64,
+    pub repeat: Option<f64>,
+}
+
+impl Pwl {
+
+    /// Build a waveform from its time-value pairs, checking the times
+    /// don't go backwards and that `r` is one of them
+    pub fn new(points: Vec<(f64, f64)>, td: f64, repeat: Option<f64>) -> Result<Pwl, String> {
+        if points.is_empty() {
+            return Err("no time-value pairs".to_string());
+        }
+        if points.windows(2).any(|w| w[1].0 < w[0].0) {
+            return Err("time points go backwards".to_string());
+        }
+        if let Some(r) = repeat {
+            if !points.iter().any(|tv| tv.0 == r) {
+                return Err(format!("repeat time r={} isn't one of the time points", r));
+            }
+        }
+        Ok(Pwl { points, td, repeat })
+    }
+
+    /// Length of the repeating section of the pattern, if there is one
+    fn period(&self) -> Option<(f64, f64)> {
+        let t_last = self.points[self.points.len() - 1].0;
+        match self.repeat {
+            Some(r) if t_last > r => Some((r, t_last - r)),
+            _ => None,
+        }
+    }
+
+    // calculate the value at a certain time
+    pub fn evaluate(&self, t: f64) -> f64 {
+        let (t_first, v_first) = self.points[0];
+        let (t_last, v_last) = self.points[self.points.len() - 1];
+
+        let mut t_pat = t - self.td;
+        if t_pat <= t_first {
+            return v_first;
+        }
+
+        // where in the pattern are we?
+        if t_pat > t_last {
+            if let Some((r, t_period)) = self.period() {
+                let t_cycle = (t_pat - t_last) % t_period;
+                // the end of a cycle takes the value from before the corner
+                t_pat = if t_cycle == 0.0 { t_last } else { r + t_cycle };
+            } else {
+                return v_last;
+            }
+        }
+
+        // find out which points we're between, and interpolate. Steps take
+        // the value from before the corner.
+        let i = self.points.iter().position(|tv| tv.0 >= t_pat).unwrap();
+        let (t1, v1) = self.points[i - 1];
+        let (t2, v2) = self.points[i];
+        v1 + (v2 - v1) * (t_pat - t1) / (t2 - t1)
+    }
+
+    /// Times of the corners of the waveform, up to `t_stop`
+    pub fn breakpoints(&self, t_stop: f64) -> Vec<f64> {
+        let mut bps: Vec<f64> = self.points.iter()
+            .map(|tv| self.td + tv.0)
+            .take_while(|&t| t <= t_stop)
+            .collect();
+
+        if let Some((r, t_period)) = self.period() {
+            let mut t_offset = self.td + t_period;
+            while t_offset + r <= t_stop {
+                for tv in self.points.iter().filter(|tv| tv.0 > r) {
+                    let t = t_offset + tv.0;
+                    if t <= t_stop {
+                        bps.push(t);
+                    }
+                }
+                t_offset += t_period;
+            }
+        }
+        bps.dedup(); // steps
+        bps
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repeat_from_time_point() {
+        // ramps up, then a triangle from 1s repeats every 2s
+        let points = vec![(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 1.0)];
+        let pwl = Pwl::new(points, 1.0, Some(1.0)).unwrap();
+        for (t, v) in [(0.5, 0.0), (1.5, 0.5), (3.0, 2.0), (4.0, 1.0), (5.0, 2.0),
+                (5.5, 1.5), (6.0, 1.0), (6.5, 1.5)] {
+            assert_eq!(pwl.evaluate(t), v, "at {}", t);
+        }
+        assert_eq!(pwl.breakpoints(6.0), vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn steps_and_holds() {
+        // starts at 5V, steps down to 0V at 1s
+        let points = vec![(0.0, 5.0), (1.0, 5.0), (1.0, 0.0), (2.0, 0.0)];
+        let pwl = Pwl::new(points, 0.0, None).unwrap();
+        for (t, v) in [(0.0, 5.0), (1.0, 5.0), (1.0 + 1e-12, 0.0), (10.0, 0.0)] {
+            assert_eq!(pwl.evaluate(t), v, "at {}", t);
+        }
+        assert_eq!(pwl.breakpoints(10.0), vec![0.0, 1.0, 2.0]);
+
+        assert!(Pwl::new(vec![(1.0, 0.0), (0.5, 1.0)], 0.0, None).is_err());
+        assert!(Pwl::new(vec![(0.0, 0.0), (1.0, 1.0)], 0.0, Some(0.5)).is_err());
+    }
+}
@@ -0,0 +1,51 @@
+//! Single-Frequency FM Waveform Implementation
+//!
+//! `SFFM(vo va fc mdi fs [phasec phases])`, for `V` and `I` sources:
+//!
+//! ```text
+//! v(t) = vo + va * sin(2pi fc t + phasec + mdi * sin(2pi fs t + phases))
+//! ```
+//!
+//! `fc` is the carrier frequency, `fs` the modulating signal frequency and
+//! `mdi` the modulation index. The phases are in degrees, and default to 0.
+
+use std::f64::consts::PI;
+
+/// Sinewave carrier, frequency-modulated by another sinewave
+#[derive(Clone, Debug)]
+pub struct Sffm {
+    pub vo: f64,
+    pub va: f64,
+    pub fc: f64,
+    pub mdi: f64,
+    pub fs: f64,
+    pub phasec: f64, // degrees
+    pub phases: f64, // degrees
+}
+
+impl Sffm {
+
+    /// Build the waveform from the list of numbers in `SFFM(...)`
+    pub fn from_values(vals: &[f64]) -> Option<Sffm> {
+        if vals.len() < 5 || vals.len() > 7 {
+            return None
+        }
+        Some(Sffm {
+            vo: vals[0],
+            va: vals[1],
+            fc: vals[2],
+            mdi: vals[3],
+            fs: vals[4],
+            phasec: vals.get(5).copied().unwrap_or(0.0),
+            phases: vals.get(6).copied().unwrap_or(0.0),
+        })
+    }
+
+    // calculate the value at a certain time
+    pub fn evaluate(&self, t: f64) -> f64 {
+        let signal = (2.0 * PI * self.fs * t + self.phases.to_radians()).sin();
+        self.vo + self.va * (2.0 * PI * self.fc * t + self.phasec.to_radians()
+            + self.mdi * signal).sin()
+    }
+
+}
@@ -0,0 +1,154 @@
+//! Voltage- and Current-Controlled Switches
+//!
+//! `S` switches are controlled by the voltage between a pair of nodes, and
+//! `W` switches by the branch current of a voltage source. Either way, the
+//! switch is a resistance of `RON` or `ROFF` between its terminals.
+//!
+//! `.model` parameters, `SW` for `S` and `CSW` for `W`:
+//!
+//! * `VT` / `IT` - threshold (0 V or A)
+//! * `VH` / `IH` - hysteresis (0 V or A)
+//! * `RON`       - on resistance (1 Ohm)
+//! * `ROFF`      - off resistance (1/GMIN Ohms)
+//!
+//! The switch turns on when the control goes above `VT + VH`, and off when
+//! it drops below `VT - VH`. In between, it stays in the state it had at
+//! the last accepted timepoint.
+
+use std::cell::Cell;
+use crate::circuit::{NodeId, GMIN};
+use crate::model::{Model, ModelKind};
+
+/// Program exectution trace macro - prefix `<switch>`
+macro_rules! trace {
+    ($fmt:expr $(, $($arg:tt)*)?) => {
+        // uncomment the line below for tracing prints
+        //println!(concat!("<switch> ", $fmt), $($($arg)*)?);
+    };
+}
+
+/// What a switch is controlled by
+#[derive(Clone, Debug, PartialEq)]
+pub enum Control {
+    /// `v(cp) - v(cn)`
+    Voltage(NodeId, NodeId),
+    /// Branch current of the named voltage source, and its index
+    Current(String, usize),
+}
+
+#[derive(Clone)]
+pub struct Switch {
+    pub ident: String,
+    pub p: NodeId,
+    pub n: NodeId,
+    pub control: Control,
+    pub thresh: f64,
+    pub hyst: f64,
+    pub r_on: f64,
+    pub r_off: f64,
+    on: Cell<bool>,
+    on_accepted: Cell<bool>,
+}
+
+impl Switch {
+
+    /// `on` is the initial state of the switch
+    pub fn new(ident: &str, p: NodeId, n: NodeId, control: Control, model: &Model,
+        on: bool) -> Switch
+    {
+        let (vt, vh) = match (&control, &model.kind) {
+            (Control::Voltage(..), ModelKind::Switch) => ("VT", "VH"),
+            (Control::Current(..), ModelKind::CurrentSwitch) => ("IT", "IH"),
+            _ => panic!("*FATAL* Model '{}' is the wrong kind of switch model for '{}'",
+                model.name, ident),
+        };
+        Switch {
+            ident: ident.to_string(),
+            p,
+            n,
+            control,
+            thresh: model.get_or(vt, 0.0),
+            hyst: model.get_or(vh, 0.0).abs(),
+            r_on: model.get_or("RON", 1.0),
+            r_off: model.get_or("ROFF", 1.0 / GMIN),
+            on: Cell::new(on),
+            on_accepted: Cell::new(on),
+        }
+    }
+
+    /// Control voltage or current from the vector of unknowns
+    pub fn control_value(&self, x: &[f64], c_nodes: usize) -> f64 {
+        match self.control {
+            Control::Voltage(cp, cn) => x[cp] - x[cn],
+            Control::Current(_, ctl_idx) => x[c_nodes + ctl_idx],
+        }
+    }
+
+    /// State the switch would be in with control value `ctl`
+    fn next_state(&self, ctl: f64) -> bool {
+        if ctl > self.thresh + self.hyst {
+            true
+        } else if ctl < self.thresh - self.hyst {
+            false
+        } else {
+            self.on_accepted.get()
+        }
+    }
+
+    /// Conductance of the switch for the control value `ctl`
+    pub fn conductance(&self, ctl: f64) -> f64 {
+        let on = self.next_state(ctl);
+        if on != self.on.get() {
+            trace!("{} {} at {}", self.ident, if on { "on" } else { "off" }, ctl);
+        }
+        self.on.set(on);
+        if on { 1.0 / self.r_on } else { 1.0 / self.r_off }
+    }
+
+    /// Check the switch is in the state the solution asks for
+    pub fn is_converged(&self, ctl: f64) -> bool {
+        self.next_state(ctl) == self.on.get()
+    }
+
+    pub fn is_on(&self) -> bool {
+        self.on.get()
+    }
+
+    /// Has the switch changed state since the last accepted timepoint?
+    pub fn has_switched(&self) -> bool {
+        self.on.get() != self.on_accepted.get()
+    }
+
+    /// The solution has been accepted, so the present state is the one the
+    /// hysteresis is measured from from now on.
+    pub fn accept(&self) {
+        self.on_accepted.set(self.on.get());
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hysteresis() {
+        let mut model = Model::new("sw", ModelKind::Switch);
+        model.params.push(("VT".to_string(), 1.0));
+        model.params.push(("VH".to_string(), 0.5));
+        let sw = Switch::new("S1", 1, 0, Control::Voltage(2, 0), &model, false);
+
+        // stays off until the control is above VT + VH ...
+        for (ctl, on) in [(1.2, false), (1.6, true), (1.2, true), (0.4, false)] {
+            sw.conductance(ctl);
+            assert_eq!(sw.is_on(), on, "at {}", ctl);
+            sw.accept();
+        }
+
+        // ... but the state only sticks once it's accepted
+        sw.conductance(2.0);
+        assert!(sw.has_switched());
+        sw.conductance(1.2);
+        assert!(!sw.is_on());
+    }
+}
@@ -1,7 +1,13 @@
-//! Voltage-Controlled Dependent Source Implementations
+//! Dependent Source Implementations
 //!
 //! * `E` - Voltage-Controlled Voltage Source (VCVS)
 //! * `G` - Voltage-Controlled Current Source (VCCS)
+//! * `F` - Current-Controlled Current Source (CCCS)
+//! * `H` - Current-Controlled Voltage Source (CCVS)
+//!
+//! The current-controlled sources are controlled by the branch current of a
+//! named voltage source. Its index is only known once the circuit has been
+//! expanded.
 
 use crate::circuit::{NodeId};
 
@@ -49,4 +55,54 @@ impl Vccs {
 
 }
 
+/// `F` - CCCS
+#[derive(Clone)]
+pub struct Cccs {
+    pub ident: String,
+    pub p: NodeId,
+    pub n: NodeId,
+    pub vsrc: String, // name of the controlling voltage source
+    pub k: f64,
+    pub ctl_idx: usize, // branch current index of the controlling source
+}
+
+impl Cccs {
+
+    pub fn new(ident: &str, p: NodeId, n: NodeId, vsrc: &str, k: f64) -> Self {
+        Cccs {
+            ident: ident.to_string(),
+            p, n,
+            vsrc: vsrc.to_string(),
+            k,
+            ctl_idx: 0,
+        }
+    }
+
+}
+
+/// `H` - CCVS
+#[derive(Clone)]
+pub struct Ccvs {
+    pub ident: String,
+    pub p: NodeId,
+    pub n: NodeId,
+    pub vsrc: String, // name of the controlling voltage source
+    pub k: f64,
+    pub idx: usize, // index of voltage source in "known" column
+    pub ctl_idx: usize, // branch current index of the controlling source
+}
+
+impl Ccvs {
+
+    pub fn new(ident: &str, p: NodeId, n: NodeId, vsrc: &str, k: f64, idx: usize) -> Self {
+        Ccvs {
+            ident: ident.to_string(),
+            p, n,
+            vsrc: vsrc.to_string(),
+            k,
+            idx,
+            ctl_idx: 0,
+        }
+    }
 
+}
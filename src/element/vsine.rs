@@ -1,15 +1,23 @@
 //! Sinusoidal Voltage Source Implementation
+//!
+//! `SIN(vo va freq td theta phase)`. Before the delay `td`, the source sits
+//! at its value at the start of the sinewave. After it, the sinewave is
+//! damped by `exp(-theta * (t - td))`. `phase` is in degrees.
 
 use crate::circuit::{NodeId};
 use std::f64::consts::PI;
 
 #[derive(Clone)]
 pub struct VoltageSourceSine {
+    pub ident: String,
     pub p: NodeId,
     pub n: NodeId,
-    pub vo: f64, // offset (A)
-    pub va: f64, // amplitude (A)
+    pub vo: f64, // offset (V)
+    pub va: f64, // amplitude (V)
     pub freq: f64, // frequency (HZ)
+    pub td: f64, // delay (s)
+    pub theta: f64, // damping factor (1/s)
+    pub phase: f64, // phase (degrees)
     pub idx: usize,
 }
 
@@ -17,8 +25,12 @@ impl VoltageSourceSine {
 
     // calculate the value at a certain time
     pub fn evaluate(&self, t: f64) -> f64 {
-        self.vo + self.va * (2.0 * PI * self.freq * t).sin()
+        let phase = self.phase.to_radians();
+        if t <= self.td {
+            return self.vo + self.va * phase.sin();
+        }
+        let t = t - self.td;
+        self.vo + self.va * (-self.theta * t).exp() * (2.0 * PI * self.freq * t + phase).sin()
     }
 
 }
-    
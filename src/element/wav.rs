@@ -0,0 +1,53 @@
+//! WAV File Waveform Implementation
+//!
+//! `WAV(file=<filename> [chan=<n>] [gain=<value>] [td=<delay>])`, for `V`
+//! and `I` sources. One channel of a WAV file, counting from 0, is scaled
+//! by `gain` and delayed by `td`. Full-scale samples are +/-1.0 before the
+//! gain.
+//!
+//! Between samples, the waveform is linearly interpolated. Before the
+//! first sample it sits at the first sample's value, and after the last it
+//! holds the last one.
+
+/// Samples from one channel of a WAV file
+#[derive(Clone, Debug)]
+pub struct Wav {
+    pub filename: String,
+    pub samples: Vec<f64>,
+    pub sample_rate: f64,
+    pub gain: f64,
+    pub td: f64,
+}
+
+impl Wav {
+
+    // calculate the value at a certain time
+    pub fn evaluate(&self, t: f64) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+
+        // which samples are we between?
+        let x = (t - self.td) * self.sample_rate;
+        let idx_last = self.samples.len() - 1;
+        let v = if x <= 0.0 {
+            self.samples[0]
+        } else if x >= idx_last as f64 {
+            self.samples[idx_last]
+        } else {
+            let i = x.floor() as usize;
+            let frac = x - i as f64;
+            self.samples[i] + (self.samples[i + 1] - self.samples[i]) * frac
+        };
+        self.gain * v
+    }
+
+    /// Times of each sample, up to `t_stop`
+    pub fn breakpoints(&self, t_stop: f64) -> Vec<f64> {
+        (0..self.samples.len())
+            .map(|i| self.td + i as f64 / self.sample_rate)
+            .take_while(|&t| t <= t_stop)
+            .collect()
+    }
+
+}
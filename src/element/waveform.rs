@@ -0,0 +1,100 @@
+//! Independent Sources with Time-Varying Waveforms
+//!
+//! The waveform shapes are shared between `V` and `I` sources. Each shape
+//! can be evaluated at any time, and can list its breakpoints - the times
+//! where its slope changes abruptly - so the transient analysis can land
+//! on them rather than step over them.
+
+use crate::circuit::{NodeId};
+use crate::element::pulse::Pulse;
+use crate::element::exponential::Exponential;
+use crate::element::sffm::Sffm;
+use crate::element::am::Am;
+use crate::element::pwl::Pwl;
+use crate::element::wav::Wav;
+
+/// Waveform shapes
+#[derive(Clone, Debug)]
+pub enum Waveform {
+    Pulse(Pulse),
+    Exp(Exponential),
+    Sffm(Sffm),
+    Am(Am),
+    Pwl(Pwl),
+    Wav(Wav),
+}
+
+impl Waveform {
+
+    // calculate the value at a certain time
+    pub fn evaluate(&self, t: f64) -> f64 {
+        match *self {
+            Waveform::Pulse(ref pulse) => pulse.evaluate(t),
+            Waveform::Exp(ref exp) => exp.evaluate(t),
+            Waveform::Sffm(ref sffm) => sffm.evaluate(t),
+            Waveform::Am(ref am) => am.evaluate(t),
+            Waveform::Pwl(ref pwl) => pwl.evaluate(t),
+            Waveform::Wav(ref wav) => wav.evaluate(t),
+        }
+    }
+
+    /// Times of the corners of the waveform, up to `t_stop`
+    pub fn breakpoints(&self, t_stop: f64) -> Vec<f64> {
+        match *self {
+            Waveform::Pulse(ref pulse) => pulse.breakpoints(t_stop),
+            Waveform::Exp(ref exp) => exp.breakpoints(t_stop),
+            Waveform::Sffm(_) => vec![],
+            Waveform::Am(ref am) => am.breakpoints(t_stop),
+            Waveform::Pwl(ref pwl) => pwl.breakpoints(t_stop),
+            Waveform::Wav(ref wav) => wav.breakpoints(t_stop),
+        }
+    }
+
+    /// SPICE name of the waveform
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Waveform::Pulse(_) => "PULSE",
+            Waveform::Exp(_) => "EXP",
+            Waveform::Sffm(_) => "SFFM",
+            Waveform::Am(_) => "AM",
+            Waveform::Pwl(_) => "PWL",
+            Waveform::Wav(_) => "WAV",
+        }
+    }
+
+}
+
+#[derive(Clone)]
+pub struct VoltageSourceWaveform {
+    pub ident: String,
+    pub p: NodeId,
+    pub n: NodeId,
+    pub wave: Waveform,
+    pub idx: usize,
+}
+
+impl VoltageSourceWaveform {
+
+    // calculate the value at a certain time
+    pub fn evaluate(&self, t: f64) -> f64 {
+        self.wave.evaluate(t)
+    }
+
+}
+
+#[derive(Clone)]
+pub struct CurrentSourceWaveform {
+    pub ident: String,
+    pub p: NodeId,
+    pub n: NodeId,
+    pub wave: Waveform,
+}
+
+impl CurrentSourceWaveform {
+
+    // calculate the value at a certain time
+    pub fn evaluate(&self, t: f64) -> f64 {
+        self.wave.evaluate(t)
+    }
+
+}
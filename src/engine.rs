@@ -2,9 +2,16 @@
 //!
 //! Contains the stamper, solver and convergence checkers
 
+use std::f64::consts::PI;
+use std::path::Path;
+
 use crate::analysis;
+use crate::analysis::Method;
+use crate::integration::Derivative;
 use crate::circuit;
+use crate::complex::Complex;
 use crate::wavewriter::WaveWriter;
+use crate::wavfile;
 
 /// Program execution trace macro - prefix `<engine>`
 macro_rules! trace {
@@ -14,6 +21,14 @@ macro_rules! trace {
     };
 }
 
+/// How finely switching events are pinned down in time, as a fraction of
+/// `TSTEP`
+const EVENT_RESOLUTION: f64 = 1e-3;
+
+/// Conductance that holds `.ic` nodes at their voltage during the DC
+/// operating point
+const IC_CONDUCTANCE: f64 = 1e9;
+
 fn banner() {
 
     println!("**********************************************");
@@ -58,9 +73,32 @@ pub struct Engine {
     // list of elements with energy storage (caps & inductors)
     storage_elements: Vec<circuit::Element>,
 
+    // list of sources with an AC small-signal spec
+    ac_sources: Vec<circuit::Element>,
+
     // DC operating point
     dc_op: Vec<f64>,
 
+    // AC solutions: (frequency, unknowns)
+    ac_sol: Vec<(f64, Vec<Complex>)>,
+
+    // DC sweep solutions: (source value, unknowns)
+    sweep_sol: Vec<(f64, Vec<f64>)>,
+
+    // Transient solutions: (time, unknowns), only kept for `wav` commands
+    tran_sol: Vec<(f64, Vec<f64>)>,
+
+    // Solutions at the last accepted timepoints, newest first: (time,
+    // unknowns). Three for backward Euler and trapezoidal, `maxord + 1` for
+    // Gear, but only back to the last breakpoint or switching event.
+    tran_history: Vec<(f64, Vec<f64>)>,
+
+    // Capacitor currents at the last timepoint, for the trapezoidal rule,
+    // and at the one before that, to spot ringing.
+    // Lines up with `capacitors()`.
+    cap_currents: Vec<f64>,
+    cap_currents_prev: Vec<f64>,
+
 }
 
 impl Engine {
@@ -75,7 +113,14 @@ impl Engine {
             independent_sources: vec![],
             v_dependent_sources: vec![],
             storage_elements: vec![],
+            ac_sources: vec![],
             dc_op: vec![],
+            ac_sol: vec![],
+            sweep_sol: vec![],
+            tran_sol: vec![],
+            tran_history: vec![],
+            cap_currents: vec![],
+            cap_currents_prev: vec![],
         }
     }
 
@@ -92,10 +137,8 @@ impl Engine {
             match *a {
                 analysis::Kind::DcOperatingPoint => Some(self.dc_operating_point(ckt, cfg)),
                 analysis::Kind::Transient => Some(self.transient_analysis(ckt, cfg)),
-                _ => {
-                    println!("*ERROR* unsupported circuit analysis type");
-                    None
-                }
+                analysis::Kind::DcSweep => self.dc_sweep(ckt, cfg),
+                analysis::Kind::Ac => Some(self.ac_analysis(ckt, cfg)),
             }
         } else {
             println!("*ERROR* analysis type is not set");
@@ -112,63 +155,156 @@ impl Engine {
         }
     }
 
-/*
-    // need to know which element to sweep
+    // Grab the AC small-signal solutions
+    pub fn ac(self) -> Option<Vec<(f64, Vec<Complex>)>> {
+        if self.ac_sol.is_empty() {
+            None
+        } else {
+            Some(self.ac_sol.clone())
+        }
+    }
+
+    // Grab the DC sweep solutions
+    pub fn sweep(self) -> Option<Vec<(f64, Vec<f64>)>> {
+        if self.sweep_sol.is_empty() {
+            None
+        } else {
+            Some(self.sweep_sol.clone())
+        }
+    }
+
+    /// DC sweep of an independent `V` or `I` source
+    ///
+    /// The source is found by its identifier. Each point in the sweep is
+    /// warm-started from the solution of the previous point.
+    ///
+    /// If there's an outer sweep too, the inner sweep is run for each outer
+    /// source value, and each inner sweep is written to its own waveform
+    /// file with an extra column holding the outer value. The solutions
+    /// from each inner sweep are listed back-to-back.
     pub fn dc_sweep(
         &mut self,
         ckt: &circuit::Circuit,
         cfg: &analysis::Configuration,
-    ) {
-        const VSTART: f64 = -3.0;
-        const VSTOP: f64 = 5.0;
-        const VSTEPS: usize = 100;
+    )
+    -> Option<analysis::Statistics>
+    {
+        let sweep = if let Some(ref sweep) = cfg.dc_sweep {
+            sweep
+        } else {
+            println!("*ERROR* DC sweep settings are missing");
+            return None;
+        };
 
-        let v_step = (VSTOP - VSTART) / VSTEPS as f64;
+        // find the source(s) we're sweeping
+        let src = find_dc_source(ckt, &sweep.src)?;
+        let outer = if let Some(ref outer) = cfg.dc_sweep_outer {
+            Some((outer, find_dc_source(ckt, &outer.src)?))
+        } else {
+            None
+        };
 
-        self.elaborate(&ckt);
+        self.elaborate(ckt);
 
         // announce
         println!("*************************************************************");
-        println!("DC Sweep: {} to {} by {}", VSTART, VSTOP, v_step);
+        println!("*CONFIG* DC SWEEP");
+        println!("*CONFIG* {} {} to {} by {}",
+                 sweep.src, sweep.start, sweep.stop, sweep.step);
+        if let Some((outer_sweep, _)) = outer {
+            println!("*CONFIG* {} {} to {} by {} (outer)",
+                     outer_sweep.src, outer_sweep.start, outer_sweep.stop, outer_sweep.step);
+        }
+        println!("*************************************************************");
+
+        // without an outer sweep, just go round the outer loop once
+        let outer_values = if let Some((outer_sweep, _)) = outer {
+            outer_sweep.values()
+        } else {
+            vec![0.0]
+        };
+
+        self.sweep_sol.clear();
+        let mut unknowns_init = self.initial_guess(ckt, cfg);
+        let mut c_iteration: usize = 0;
+        let mut value_now = 0.0;
 
         // open waveform database
-        let mut wavedb = WaveWriter::new(&cfg.wavefile).unwrap();
-        wavedb.header(self.c_nodes, self.c_vsrcs);
+        let mut cols = vec![(sweep.src.as_str(), dc_source_unit(&src))];
+        if let Some((outer_sweep, ref outer_src)) = outer {
+            cols.push((outer_sweep.src.as_str(), dc_source_unit(outer_src)));
+        }
+        let mut wavedb = WaveWriter::new(&cfg.wavefile, &ckt.node_id_lut).unwrap();
+        wavedb.set_branch_lut(&ckt.branch_id_lut);
+        wavedb.header_cols(&cols, self.c_nodes, self.c_vsrcs);
 
-        // FUCK!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!
-        // have to stamp this all the time
-        // FIXCasdfkj disf=
-        //0]
-        //
-
-        // FIXME very fragile - what if there's more than one voltage source in
-        // the design?
-        let idx_vsrc : usize = self.c_nodes; // index, not amperage...
-
-        // tweak the thing we're sweeping
-        for s in 0..VSTEPS {
-            let v_sweep = VSTART + (v_step * s as f64);
-            //let self.
-
-            let mut mna = self.base_matrix.clone();
-
-            // unstamp the voltage source
-            // FIXME - assume it's 0V
-            let v_src = circuit::VoltageSource{
-                p: 1,
-                n: 0,
-                value: v_sweep,
-            };
+        for (i_outer, outer_value) in outer_values.iter().enumerate() {
+
+            // a block of rows for each outer value
+            if i_outer > 0 {
+                wavedb.new_block();
+            }
+
+            let mut mna_outer = self.base_matrix.clone();
+            if let Some((_, ref outer_src)) = outer {
+                self.restamp_dc_source(&mut mna_outer, outer_src, *outer_value);
+            }
+
+            for value in sweep.values() {
+                value_now = value;
+                let mut mna = mna_outer.clone();
+                self.restamp_dc_source(&mut mna, &src, value);
 
-            self.stamp_voltage_source(&mut mna, &v_src, idx_vsrc);
-            
-            let _stats = self.dc_solve(&mna, &cfg);
-            wavedb.dump_vector(v_sweep, &self.dc_op);
+                let stats = self.dc_solve(&mna, cfg, &unknowns_init);
+                c_iteration += stats.iterations;
+
+                if outer.is_some() {
+                    let mut vars = vec![*outer_value];
+                    vars.extend_from_slice(&self.dc_op);
+                    wavedb.dump_vector(value, &vars);
+                } else {
+                    wavedb.dump_vector(value, &self.dc_op);
+                }
+                self.sweep_sol.push((value, self.dc_op.clone()));
+                unknowns_init = self.dc_op.clone();
+            }
+        }
 
+        // show how to plot the non-ground node voltages
+        let first_col = if outer.is_some() { 3 } else { 2 };
+        let exprs: Vec<String> = (first_col..first_col + self.c_nodes - 1)
+            .map(|c| c.to_string())
+            .collect();
+        if let Some(wavedir) = std::path::Path::new(&cfg.wavefile).parent() {
+            println!("*INFO* Plot with: python3 bin/r8n -expr \"{}\" {}",
+                     exprs.join(","), wavedir.display());
         }
 
+        println!("*INFO* Finished at {} = {}", sweep.src, value_now);
+        Some(analysis::Statistics {
+            kind: analysis::Kind::DcSweep,
+            end: value_now,
+            iterations: c_iteration,
+        })
+    }
+
+    // Replace the value of a DC source already stamped into the base matrix
+    fn restamp_dc_source(&self, m: &mut [Vec<f64>], src: &circuit::Element, value: f64) {
+        match *src {
+            circuit::Element::V(ref vsrc) => {
+                let mut vsrc_upd = vsrc.clone();
+                vsrc_upd.value = value;
+                self.stamp_voltage_source(m, &vsrc_upd);
+            },
+            circuit::Element::I(ref isrc) => {
+                // current sources accumulate, so stamp the difference
+                let mut isrc_upd = isrc.clone();
+                isrc_upd.value = value - isrc.value;
+                self.stamp_current_source(m, &isrc_upd);
+            },
+            _ => { println!("*ERROR* - can only sweep DC V and I sources"); }
+        }
     }
-*/
 
     pub fn transient_analysis(
         &mut self,
@@ -180,37 +316,91 @@ impl Engine {
 
         // Find the DC operating point
         // used as the initial values in the transient simulation
-        // this will also build the circuit
-        let dc_op_stats = self.dc_operating_point(ckt, cfg);
-        let mut unknowns = self.dc_op.clone();
+        // this will also build the circuit.
+        // With `uic`, skip it and start off from the initial conditions.
+        let ics = find_nodes(ckt, &cfg.initial_conditions, ".ic");
+        let (dc_iterations, mut unknowns) = if cfg.uic {
+            println!("*INFO* UIC - skipping the DC operating point");
+            self.elaborate(ckt);
+            (0, self.uic_unknowns(&ics))
+        } else if ics.is_empty() {
+            let dc_op_stats = self.dc_operating_point(ckt, cfg);
+            (dc_op_stats.iterations, self.dc_op.clone())
+        } else {
+            let dc_op_stats = self.dc_operating_point_ic(ckt, cfg, &ics);
+            (dc_op_stats.iterations, self.dc_op.clone())
+        };
+        if !cfg.uic {
+            for el in &self.storage_elements {
+                match *el {
+                    circuit::Element::C(ref c) if c.ic.is_some() => {
+                        println!("*WARN* ignoring IC= on '{}' without uic", c.ident);
+                    },
+                    circuit::Element::L(ref l) if l.ic.is_some() => {
+                        println!("*WARN* ignoring IC= on '{}' without uic", l.ident);
+                    },
+                    _ => {}
+                }
+            }
+        }
 
         trace!(" [TRANSIENT] : DC : {:?}", &unknowns);
 
         // prep values
         let c_mna = self.c_nodes + self.c_vsrcs;
-        let mut unknowns_prev : Vec<f64> = vec![0.0; c_mna];
+        // storage elements start from the DC solution, not from zero
+        let mut unknowns_prev : Vec<f64> = unknowns.clone();
+        self.cap_currents = vec![0.0; self.capacitors(&unknowns).len()];
+        self.cap_currents_prev = self.cap_currents.clone();
+        self.tran_history = vec![(0.0, unknowns.clone())];
+
+        // The trapezoidal rule rings after sudden changes, so the first step,
+        // the steps off breakpoints and switching events, and the step after
+        // any ringing is spotted with trap, are backward Euler. Gear starts
+        // again from first order after them too.
+        let mut method = Method::Euler;
+
+        // Timepoints to remember: enough for Gear, and one more than the
+        // order of the method to estimate its truncation error
+        let c_history = if cfg.method == Method::Gear { cfg.maxord + 1 } else { 3 };
 
         // transient loop
-        let mut t_delta = cfg.TSTEP * cfg.FS;
+        let t_delta_max = cfg.t_delta_max();
+        let mut t_delta = (cfg.TSTEP * cfg.FS).min(t_delta_max);
         let t_delta_min = cfg.TSTEP * cfg.RMIN; // not mimimum resistance...
+        let t_delta_event = cfg.TSTEP * EVENT_RESOLUTION;
         let mut t_now = 0.0;
 
+        // the corners of the source waveforms, and the end, are landed on
+        // exactly rather than stepped over
+        let mut breakpoints = self.breakpoints(cfg.TSTOP);
+
         // announce
         println!("*************************************************************");
         println!("*CONFIG* TRANSIENT ANALYSIS");
         println!("*CONFIG* TIME {} to {} by {:0.12}",
                  cfg.TSTART, cfg.TSTOP, cfg.TSTEP);
+        println!("*CONFIG* TMAX {:0.12}; UIC = {}; METHOD = {}",
+                 t_delta_max, cfg.uic, cfg.method);
         println!("*************************************************************");
 
         // open waveform database
         let mut wavedb = WaveWriter::new(&cfg.wavefile, &ckt.node_id_lut).unwrap();
+        wavedb.set_branch_lut(&ckt.branch_id_lut);
         wavedb.header(self.c_nodes, self.c_vsrcs);
+        if cfg.interp {
+            wavedb.set_grid(cfg.TSTART, cfg.TSTEP);
+        }
         wavedb.dump_vector(t_now, &unknowns); // DC solution
+        let keep_solutions = !cfg.wav_outputs.is_empty();
+        self.tran_sol.clear();
+        if keep_solutions {
+            self.tran_sol.push((t_now, unknowns.clone()));
+        }
 
         // timestep loop
         let mut is_final_timestep = false;
-        #[allow(unused_variables)]
-        let mut c_step = 0; // goes away with empty trace!
+        let mut c_step = 0;
         let mut c_iteration: usize = 0;
         loop {
 
@@ -219,15 +409,29 @@ impl Engine {
             // * the initial calculation after DC on the initial iteration
             // * the prevous go round the loop for other iterations
 
-            if t_now >= cfg.TSTART && t_now != 0.0 {
+            // on the grid, the timepoint before `TSTART` is interpolated from
+            if (t_now >= cfg.TSTART || cfg.interp) && t_now != 0.0 {
                 trace!("*DATA*: [{}] t={} : {:?}", c_step, t_now, unknowns);
                 wavedb.dump_vector(t_now, &unknowns);
+                if keep_solutions {
+                    self.tran_sol.push((t_now, unknowns.clone()));
+                }
             }
 
             if is_final_timestep {
                 break;
             }
 
+            // don't step over the next breakpoint
+            while breakpoints.last().is_some_and(|&t_bp| t_bp <= t_now) {
+                breakpoints.pop();
+            }
+            let t_bp = *breakpoints.last().unwrap_or(&cfg.TSTOP);
+            let t_delta_free = t_delta;
+            if t_now + t_delta + t_delta_event > t_bp {
+                t_delta = t_bp - t_now;
+                trace!(" [TIMESTEP] Breakpoint at {} -> new t_delta = {}", t_bp, t_delta);
+            }
 
             // solver loop
             // breaks when solved, or time-step too small
@@ -240,6 +444,9 @@ impl Engine {
             let mut unknowns_solve : Vec<f64> = vec![0.0; c_mna];
             let mut unknowns_solve_prev : Vec<f64> = vec![0.0; c_mna];
             let mut geared = false;
+            let mut switched = false;
+            let mut deriv = self.derivative(method, cfg.maxord, t_now + t_delta);
+            let mut h_lte = None;
 
             let mut _mse :f64 = 0.0; // not used if trace! is empty
 
@@ -260,7 +467,7 @@ impl Engine {
                 self.v_dependent_source_stamp(&mut m);
 
                 // stamp elements that store energy
-                self.storage_stamp(&mut m, &unknowns_prev, t_delta);
+                self.storage_stamp(&mut m, &deriv);
 
                 // stamp companion models of nonlinear devices
                 self.nonlinear_stamp(&mut m, &unknowns, &unknowns_solve_prev);
@@ -279,14 +486,41 @@ impl Engine {
                 // Convergence check
                 match self.convergence_check(&unknowns, &unknowns_solve, cfg) {
                     Ok(cnvg) => {
-                        if cnvg {
-                            trace!(" [TIMESTEP] Timestep converged after {} iterations", c_itl);
-                            converged = true;
-                            break;
+                        if cnvg && self.nonlinear_converged(&unknowns, cfg) {
+                            if self.switch_event() && t_delta > t_delta_event {
+                                // a switch flipped somewhere in this step, so go
+                                // back and close in on when with smaller ones
+                                t_delta = (t_delta * cfg.FT).max(t_delta_event);
+                                deriv = self.derivative(method, cfg.maxord, t_now + t_delta);
+                                trace!(" [TIMESTEP] Switching event -> new t_delta = {}", t_delta);
+                                unknowns = unknowns_prev.to_vec();
+                                geared = true;
+                                switched = true;
+                                c_itl = 0;
+                            } else {
+                                // is the truncation error small enough?
+                                h_lte = self.truncation_step(&unknowns, t_now + t_delta, &deriv, cfg);
+                                match h_lte {
+                                    Some(h) if h < 0.9 * t_delta && t_delta > t_delta_event => {
+                                        t_delta = h.max(t_delta * cfg.FT).max(t_delta_event);
+                                        deriv = self.derivative(method, cfg.maxord, t_now + t_delta);
+                                        trace!(" [TIMESTEP] Truncation error -> new t_delta = {}", t_delta);
+                                        unknowns = unknowns_prev.to_vec();
+                                        geared = true;
+                                        c_itl = 0;
+                                    },
+                                    _ => {
+                                        trace!(" [TIMESTEP] Timestep converged after {} iterations", c_itl);
+                                        converged = true;
+                                        break;
+                                    },
+                                }
+                            }
                         } else {
                             // adjust timestep if we can
                             if c_itl >= cfg.ITL4 {
                                 t_delta *= cfg.FT;
+                                deriv = self.derivative(method, cfg.maxord, t_now + t_delta);
                                 // check if we're ok to continue iterating
                                 if t_delta < t_delta_min {
                                     println!("*ERROR* Internal timestep too small");
@@ -313,13 +547,29 @@ impl Engine {
 
             if converged {
                 // update things for next loop
+                let ringing = self.update_cap_currents(&unknowns, &deriv, cfg.ABSTOL);
                 unknowns_prev = unknowns.to_vec();
-
-                // solver found it too easy, maybe there's not a lot going on
-                // reduce the t_delta
-                if !geared & (c_itl < cfg.ITL3) {
+                self.accept_switches();
+                t_now += t_delta;
+                let ringing = ringing && cfg.method == Method::Trapezoidal;
+                method = if switched || ringing { Method::Euler } else { cfg.method };
+                let mut restart = switched;
+
+                if t_now >= t_bp - t_delta_event * 1e-6 {
+                    // on the breakpoint, so start off gently from the corner
+                    t_now = t_bp;
+                    t_delta = t_delta_free.min(cfg.TSTEP * cfg.FS).min(t_delta_max);
+                    method = Method::Euler;
+                    restart = true;
+                } else if let Some(h) = h_lte {
+                    // as big as the truncation error allows, but don't grow
+                    // too quickly
+                    t_delta = h.min(2.0 * t_delta).min(t_delta_max);
+                    trace!(" [TIMESTEP] Truncation error -> new t_delta = {}", t_delta);
+                } else if !geared & (c_itl < cfg.ITL3) {
+                    // solver found it too easy, maybe there's not a lot going on
+                    // reduce the t_delta
                     t_delta *= 2.0;
-                    let t_delta_max = cfg.TSTEP * cfg.RMAX;
                     if t_delta > t_delta_max {
                         trace!(" [TIMESTEP] Downshifting maxed out");
                         t_delta = t_delta_max;
@@ -327,11 +577,15 @@ impl Engine {
                         trace!(" [TIMESTEP] Downshifting -> new t_delta = {}", t_delta);
                     }
                 }
+
+                // remember the solutions for the multistep methods and the
+                // truncation error, forgetting what came before a corner
+                self.tran_history.insert(0, (t_now, unknowns.clone()));
+                self.tran_history.truncate(if restart { 1 } else { c_history });
             }
 
             c_step += 1;
-            t_now += t_delta;
-            if t_now > cfg.TSTOP {
+            if t_now >= cfg.TSTOP {
                 t_now = cfg.TSTOP;
                 is_final_timestep = true;
 
@@ -344,28 +598,187 @@ impl Engine {
             }
         } // time
 
-        println!("*INFO* Finished at time {}", t_now);
+        println!("*INFO* Finished at time {} after {} timesteps", t_now, c_step);
+        for wav in &cfg.wav_outputs {
+            self.write_wav(ckt, cfg, wav);
+        }
         analysis::Statistics {
             kind: analysis::Kind::Transient,
             end: t_now,
-            iterations: dc_op_stats.iterations + c_iteration,
+            iterations: dc_iterations + c_iteration,
+        }
+    }
+
+
+    /// Write the results of a transient analysis to a WAV file
+    ///
+    /// The solutions are linearly interpolated onto the audio sample grid,
+    /// from `TSTART`. The file goes in the same directory as the waveform
+    /// data.
+    fn write_wav(
+        &self,
+        ckt: &circuit::Circuit,
+        cfg: &analysis::Configuration,
+        wav: &analysis::WavOutput,
+    ) {
+        // find the columns to subtract for each channel
+        let mut cols = vec![];
+        for signal in &wav.signals {
+            let col = match *signal {
+                analysis::WavSignal::Voltage(ref p, ref n) => {
+                    let p = ckt.get_node_id(p);
+                    let n = n.as_ref().map_or(Some(0), |n| ckt.get_node_id(n));
+                    p.zip(n).map(|(p, n)| (p, Some(n)))
+                },
+                analysis::WavSignal::Current(ref ident) => ckt.branch_id_lut.iter()
+                    .find(|(_, name)| *name == ident)
+                    .map(|(idx, _)| (self.c_nodes + idx, None)),
+            };
+            if let Some(col) = col {
+                cols.push(col);
+            } else {
+                println!("*ERROR* can't find {:?} for '{}'", signal, wav.filename);
+                return;
+            }
+        }
+        let value = |x: &[f64], (p, n): (usize, Option<usize>)| match n {
+            Some(n) => x[p] - x[n],
+            None => x[p],
+        };
+
+        // resample onto the audio grid
+        let rate = wav.rate as f64;
+        let c_samples = ((cfg.TSTOP - cfg.TSTART) * rate).floor() as usize + 1;
+        let mut channels = vec![Vec::with_capacity(c_samples); cols.len()];
+        let mut i = 0;
+        for k in 0..c_samples {
+            let t = cfg.TSTART + k as f64 / rate;
+            while i + 2 < self.tran_sol.len() && self.tran_sol[i + 1].0 < t {
+                i += 1;
+            }
+            let (t1, ref x1) = self.tran_sol[i];
+            let (t2, ref x2) = self.tran_sol[(i + 1).min(self.tran_sol.len() - 1)];
+            let frac = if t2 > t1 { ((t - t1) / (t2 - t1)).clamp(0.0, 1.0) } else { 0.0 };
+            for (ch, &col) in channels.iter_mut().zip(&cols) {
+                let (v1, v2) = (value(x1, col), value(x2, col));
+                ch.push(v1 + (v2 - v1) * frac);
+            }
+        }
+
+        if wav.normalise {
+            let peak = channels.iter().flatten().fold(0.0_f64, |m, x| m.max(x.abs()));
+            if peak > 0.0 {
+                channels.iter_mut().flatten().for_each(|x| *x /= peak);
+            }
+        }
+
+        let dir = Path::new(&cfg.wavefile).parent().unwrap_or(Path::new(""));
+        let path = dir.join(&wav.filename);
+        match wavfile::write(&path, wav.rate, wav.bits, &channels) {
+            Ok(c_clipped) => {
+                println!("*INFO* Wrote {} samples at {} Hz to {}", c_samples, wav.rate, path.display());
+                if c_clipped > 0 {
+                    println!("*WARN* {} samples clipped in {} - try 'norm'", c_clipped, path.display());
+                }
+            },
+            Err(msg) => println!("*ERROR* {}", msg),
+        }
+    }
+
+
+    /// AC small-signal analysis
+    ///
+    /// Nonlinear elements are linearised around the DC operating point, then
+    /// the complex MNA system is solved at each frequency in the sweep. Only
+    /// sources with an `AC` spec drive the circuit; all other independent
+    /// sources are zeroed.
+    pub fn ac_analysis(
+        &mut self,
+        ckt: &circuit::Circuit,
+        cfg: &analysis::Configuration,
+    )
+    -> analysis::Statistics
+    {
+
+        // Find the DC operating point to linearise around
+        // this will also build the circuit
+        let dc_op_stats = self.dc_operating_point(ckt, cfg);
+
+        let c_mna = self.c_nodes + self.c_vsrcs;
+        let ia = c_mna; // index for ampere vector
+
+        // Real part of the matrix: linear elements, source topology and
+        // companion model conductances at the operating point.
+        let mut g = self.base_matrix.clone();
+        self.independent_source_stamp(&mut g, 0.0);
+        self.v_dependent_source_stamp(&mut g);
+        self.nonlinear_stamp(&mut g, &self.dc_op, &self.dc_op);
+
+        // DC values and companion currents play no part in the small-signal
+        // solve, so wipe the 'known' vector.
+        for row in g.iter_mut() {
+            row[ia] = 0.0;
+        }
+
+        let freqs = cfg.ac_frequencies();
+
+        // announce
+        println!("*************************************************************");
+        println!("*CONFIG* AC ANALYSIS");
+        println!("*CONFIG* FREQ {} to {} Hz ({:?}, {} points)",
+                 cfg.FSTART, cfg.FSTOP, cfg.ac_sweep, cfg.NPOINTS);
+        println!("*************************************************************");
+
+        // open waveform database
+        let mut wavedb = WaveWriter::new(&cfg.wavefile, &ckt.node_id_lut).unwrap();
+        wavedb.set_branch_lut(&ckt.branch_id_lut);
+        wavedb.header_ac(self.c_nodes, self.c_vsrcs);
+
+        self.ac_sol.clear();
+        for freq in &freqs {
+            let omega = 2.0 * PI * freq;
+
+            let mut m: Vec<Vec<Complex>> = g.iter()
+                .map(|row| row.iter().map(|x| Complex::from(*x)).collect())
+                .collect();
+
+            self.ac_storage_stamp(&mut m, omega);
+            self.ac_source_stamp(&mut m);
+
+            let unknowns = self.solve_complex(m);
+
+            let mut vars: Vec<f64> = vec![];
+            for x in &unknowns {
+                vars.push(x.norm());
+                vars.push(x.arg_deg());
+            }
+            wavedb.dump_vector(*freq, &vars);
+            self.ac_sol.push((*freq, unknowns));
+        }
+
+        println!("*INFO* Finished at frequency {}", freqs.last().unwrap_or(&0.0));
+        analysis::Statistics {
+            kind: analysis::Kind::Ac,
+            end: *freqs.last().unwrap_or(&0.0),
+            iterations: dc_op_stats.iterations + freqs.len(),
         }
     }
 
 
     // assume circuit has been elaborated
+    // `unknowns_init` is the starting guess for the Newton-Raphson loop
     fn dc_solve(
         &mut self,
         mna: &[Vec<f64>],
         cfg: &analysis::Configuration,
+        unknowns_init: &[f64],
     )
         -> analysis::Statistics
     {
 
         // prep values for convergence checks
-        let c_mna = self.c_nodes + self.c_vsrcs;
-        let mut unknowns_prev : Vec<f64> = vec![0.0; c_mna];
-        let mut unknowns_prev_prev : Vec<f64> = vec![0.0; c_mna];
+        let mut unknowns_prev : Vec<f64> = unknowns_init.to_vec();
+        let mut unknowns_prev_prev : Vec<f64> = unknowns_init.to_vec();
         let mut unknowns : Vec<f64> = vec![];
 
         let mut converged = false;
@@ -403,7 +816,7 @@ impl Engine {
             if c_iteration > 0 {
                 match self.convergence_check(&unknowns, &unknowns_prev, cfg) {
                     Ok(cnvd) => {
-                        if cnvd {
+                        if cnvd && self.nonlinear_converged(&unknowns, cfg) {
                             converged = true;
                             break;
                         }
@@ -423,6 +836,7 @@ impl Engine {
 
         if converged {
             trace!(" [CONVERGE] Converged after {} iterations", c_iteration);
+            self.accept_switches();
         } else {
             println!("*ERROR* Divergent");
         }
@@ -452,7 +866,86 @@ impl Engine {
 
         // cos borrowck
         let mna = self.base_matrix.clone();
-        self.dc_solve(&mna, cfg)
+        let unknowns_init = self.initial_guess(ckt, cfg);
+        self.dc_solve(&mna, cfg, &unknowns_init)
+    }
+
+
+    /// DC operating point with the `.ic` nodes held at their voltages
+    fn dc_operating_point_ic(
+        &mut self,
+        ckt: &circuit::Circuit,
+        cfg: &analysis::Configuration,
+        ics: &[(circuit::NodeId, f64)],
+    )
+        -> analysis::Statistics
+    {
+        self.elaborate(ckt);
+
+        // a big conductance to ground, with a current source to match
+        let mut mna = self.base_matrix.clone();
+        for &(node, value) in ics {
+            self.stamp_resistor(&mut mna, &circuit::Resistor{
+                ident: format!("ic({})", node),
+                a: node,
+                b: 0,
+                value: 1.0 / IC_CONDUCTANCE,
+            });
+            self.stamp_current_source(&mut mna, &circuit::CurrentSource{
+                ident: format!("ic({})", node),
+                p: 0,
+                n: node,
+                value: value * IC_CONDUCTANCE,
+                ac_mag: 0.0,
+                ac_phase: 0.0,
+            });
+        }
+        let unknowns_init = self.initial_guess(ckt, cfg);
+        self.dc_solve(&mna, cfg, &unknowns_init)
+    }
+
+
+    /// Starting guess for a DC solve: zero, apart from the `.nodeset`
+    /// nodes. Newton-Raphson is free to move them after the first iteration.
+    fn initial_guess(&self, ckt: &circuit::Circuit, cfg: &analysis::Configuration) -> Vec<f64> {
+        let mut unknowns = vec![0.0; self.c_nodes + self.c_vsrcs];
+        for (node, value) in find_nodes(ckt, &cfg.nodesets, ".nodeset") {
+            unknowns[node] = value;
+        }
+        unknowns
+    }
+
+
+    /// Starting point for `uic`: zero, apart from the `.ic` nodes and the
+    /// `IC=` on capacitors and inductors
+    ///
+    /// A capacitor's `IC=` sets the voltage on its `a` node relative to its
+    /// `b` node, unless `a` is ground.
+    fn uic_unknowns(&self, ics: &[(circuit::NodeId, f64)]) -> Vec<f64> {
+        let mut unknowns = vec![0.0; self.c_nodes + self.c_vsrcs];
+        for &(node, value) in ics {
+            unknowns[node] = value;
+        }
+        for el in &self.storage_elements {
+            match *el {
+                circuit::Element::C(ref c) => {
+                    if let Some(ic) = c.ic {
+                        if c.a != 0 {
+                            unknowns[c.a] = unknowns[c.b] + ic;
+                        } else {
+                            unknowns[c.b] = -ic;
+                        }
+                    }
+                },
+                circuit::Element::L(ref l) => {
+                    if let Some(ic) = l.ic {
+                        unknowns[self.c_nodes + l.idx] = ic;
+                    }
+                },
+                _ => {}
+            }
+        }
+        unknowns
     }
 
 
@@ -492,6 +985,11 @@ impl Engine {
                 // out of the n+ node, through the source, and into the n- node.
                 circuit::Element::I(ref isrc) => {
                     self.stamp_current_source(&mut m, isrc);
+                    if isrc.ac_mag != 0.0 {
+                        self.ac_sources.push(
+                            circuit::Element::I(isrc.clone())
+                        );
+                    }
                 }
 
                 circuit::Element::R(ref r) => {
@@ -500,6 +998,11 @@ impl Engine {
 
                 circuit::Element::V(ref vsrc) => {
                     self.stamp_voltage_source(&mut m, vsrc);
+                    if vsrc.ac_mag != 0.0 {
+                        self.ac_sources.push(
+                            circuit::Element::V(vsrc.clone())
+                        );
+                    }
                 }
 
                 circuit::Element::D(ref d) => {
@@ -507,6 +1010,57 @@ impl Engine {
                     self.nonlinear_elements.push(
                         circuit::Element::D(d.clone())
                     );
+                    if d.has_capacitance() {
+                        self.storage_elements.push(
+                            circuit::Element::D(d.clone())
+                        );
+                    }
+                }
+
+                circuit::Element::M(ref mos) => {
+                    trace!("  [ELEMENT] MOSFET:");
+                    self.nonlinear_elements.push(
+                        circuit::Element::M(mos.clone())
+                    );
+                    if mos.has_capacitance() {
+                        self.storage_elements.push(
+                            circuit::Element::M(mos.clone())
+                        );
+                    }
+                }
+
+                circuit::Element::B(ref bsrc) => {
+                    trace!("  [ELEMENT] Behavioural Source:");
+                    self.nonlinear_elements.push(
+                        circuit::Element::B(bsrc.clone())
+                    );
+                }
+
+                circuit::Element::J(ref j) => {
+                    trace!("  [ELEMENT] JFET:");
+                    self.nonlinear_elements.push(
+                        circuit::Element::J(j.clone())
+                    );
+                }
+
+                // a resistor, but which one depends on the solution
+                circuit::Element::S(ref sw) => {
+                    trace!("  [ELEMENT] Switch:");
+                    self.nonlinear_elements.push(
+                        circuit::Element::S(sw.clone())
+                    );
+                }
+
+                circuit::Element::Q(ref q) => {
+                    trace!("  [ELEMENT] BJT:");
+                    self.nonlinear_elements.push(
+                        circuit::Element::Q(q.clone())
+                    );
+                    if q.has_capacitance() {
+                        self.storage_elements.push(
+                            circuit::Element::Q(q.clone())
+                        );
+                    }
                 }
 
                 circuit::Element::Isin(ref isrcsine) => {
@@ -530,10 +1084,42 @@ impl Engine {
                     );
                 }
 
-                circuit::Element::Vpwl(ref vpwl) => {
-                    trace!("  [ELEMENT] PWL Voltage Source:");
+                // DC: short circuit, the branch current is an unknown
+                // Transient: companion model stamped in `storage_stamp()`
+                circuit::Element::L(ref l) => {
+                    trace!("  [ELEMENT] Inductor:");
+                    self.stamp_voltage_source(&mut m, &circuit::VoltageSource{
+                        ident: l.ident.clone(),
+                        p: l.a,
+                        n: l.b,
+                        value: 0.0,
+                        idx: l.idx,
+                        ac_mag: 0.0,
+                        ac_phase: 0.0,
+                    });
+                    self.storage_elements.push(
+                        circuit::Element::L(l.clone())
+                    );
+                }
+
+                circuit::Element::K(ref k) => {
+                    trace!("  [ELEMENT] Mutual Inductance:");
+                    self.storage_elements.push(
+                        circuit::Element::K(k.clone())
+                    );
+                }
+
+                circuit::Element::Iwave(ref iwave) => {
+                    trace!("  [ELEMENT] Current Source ({}):", iwave.wave.name());
+                    self.independent_sources.push(
+                        circuit::Element::Iwave(iwave.clone())
+                    );
+                }
+
+                circuit::Element::Vwave(ref vwave) => {
+                    trace!("  [ELEMENT] Voltage Source ({}):", vwave.wave.name());
                     self.independent_sources.push(
-                        circuit::Element::Vpwl(vpwl.clone())
+                        circuit::Element::Vwave(vwave.clone())
                     );
                 }
 
@@ -550,6 +1136,20 @@ impl Engine {
                         circuit::Element::Vccs(vccs.clone())
                     );
                 }
+
+                circuit::Element::Cccs(ref cccs) => {
+                    trace!("  [ELEMENT] CCCS:");
+                    self.v_dependent_sources.push(
+                        circuit::Element::Cccs(cccs.clone())
+                    );
+                }
+
+                circuit::Element::Ccvs(ref ccvs) => {
+                    trace!("  [ELEMENT] CCVS:");
+                    self.v_dependent_sources.push(
+                        circuit::Element::Ccvs(ccvs.clone())
+                    );
+                }
                 
             }
         }
@@ -592,6 +1192,7 @@ impl Engine {
                 }
                 let ratio = v[r_mod][r_ref] / v[r_ref][r_ref];
 
+                #[allow(clippy::needless_range_loop)]
                 for c_mod in r_ref..=c_mna { // column we're scaling
                     let val = v[r_mod][c_mod];
                     let wiggle = v[r_ref][c_mod];
@@ -658,6 +1259,71 @@ impl Engine {
 
     }
 
+    // Solve the complex system of linear equations
+    //
+    // Same approach as `solve()`: Gaussian elimination with partial pivoting,
+    // ignoring the ground row & column.
+    fn solve_complex(&self, mut v: Vec<Vec<Complex>>) -> Vec<Complex> {
+
+        let c_mna = self.c_nodes + self.c_vsrcs;
+        let ia = c_mna; // index for ampere vector
+
+        trace!("*INFO* Complex Gaussian Elimination");
+        for r_ref in 1..c_mna-1 {
+
+            // find the pivot
+            let mut biggest: f64 = 0.0;
+            let mut r_max = r_ref;
+            #[allow(clippy::needless_range_loop)]
+            for r in r_ref..c_mna {
+                let this = v[r][r_ref].norm();
+                if this > biggest {
+                    biggest = this;
+                    r_max = r;
+                }
+            }
+
+            if v[r_max][r_ref].is_zero() {
+                println!("*ERROR* Matrix is singular! (AC)");
+                break;
+            }
+            v.swap(r_max, r_ref);
+
+            for r_mod in r_ref+1..c_mna {
+                if v[r_mod][r_ref].is_zero() {
+                    continue;
+                }
+                let ratio = v[r_mod][r_ref] / v[r_ref][r_ref];
+
+                #[allow(clippy::needless_range_loop)]
+                for c_mod in r_ref..=c_mna {
+                    let wiggle = v[r_ref][c_mod];
+                    v[r_mod][c_mod] -= wiggle * ratio;
+                }
+            }
+        }
+
+        trace!("*INFO* Complex Back-substitution");
+        let mut n = vec![Complex::default(); c_mna];
+
+        let i_last = c_mna - 1;
+        n[i_last] = v[i_last][ia] / v[i_last][i_last];
+        if !n[i_last].is_finite() {
+            n[i_last] = Complex::default();
+        }
+
+        for i_solve in (1..c_mna-1).rev() {
+            let mut sum = Complex::default();
+            #[allow(clippy::needless_range_loop)]
+            for i_term in i_solve+1..c_mna {
+                sum += v[i_solve][i_term] * n[i_term];
+            }
+            n[i_solve] = ( v[i_solve][ia] - sum ) / v[i_solve][i_solve];
+        }
+
+        n
+    }
+
     fn index_of_next_abs( &self, m: &[Vec<f64>], k: usize ) -> usize {
         let mut biggest: f64 = 0.0;
         let mut r_biggest: usize = k;
@@ -761,32 +1427,120 @@ impl Engine {
     }
 
 
-    fn storage_stamp(&self, m: &mut [Vec<f64>], n: &[f64], t: f64) {
+    /// The capacitors among the storage elements, including the junction and
+    /// gate capacitances of devices at the bias `n`
+    ///
+    /// The list is always the same length and in the same order, so it lines
+    /// up with `cap_currents`. Device capacitances can be zero.
+    fn capacitors(&self, n: &[f64]) -> Vec<circuit::Capacitor> {
+        let mut caps = vec![];
+        for el in &self.storage_elements {
+            match *el {
+                circuit::Element::C(ref c) => {
+                    caps.push(c.clone());
+                },
+                circuit::Element::M(ref mos) => {
+                    let (c_gs, c_gd, c_gb) = mos.capacitances(
+                        n[mos.g] - n[mos.s], n[mos.d] - n[mos.s], n[mos.b] - n[mos.s]);
+                    for (c_value, other) in [(c_gs, mos.s), (c_gd, mos.d), (c_gb, mos.b)] {
+                        caps.push(circuit::Capacitor::new(&mos.ident, mos.g, other, c_value));
+                    }
+                },
+                circuit::Element::D(ref d) => {
+                    caps.push(circuit::Capacitor::new(&d.ident, d.p, d.n,
+                        d.capacitance(n[d.p] - n[d.n])));
+                },
+                circuit::Element::Q(ref q) => {
+                    let (c_be, c_bc) = q.capacitances(n[q.b] - n[q.e]);
+                    caps.push(circuit::Capacitor::new(&q.ident, q.b, q.e, c_be));
+                    caps.push(circuit::Capacitor::new(&q.ident, q.b, q.c, c_bc));
+                },
+                _ => {}
+            }
+        }
+        caps
+    }
+
+
+    /// How to form the derivative for a step to `t_new` from the history
+    fn derivative(&self, method: Method, maxord: usize, t_new: f64) -> Derivative {
+        let t_prev = self.tran_history[0].0;
+        match method {
+            Method::Euler => Derivative::euler(t_new - t_prev),
+            Method::Trapezoidal => Derivative::trapezoidal(t_new - t_prev),
+            Method::Gear => {
+                // above second order, only reach back over timepoints
+                // spaced within 20% of the new step, as variable-step BDF is
+                // unstable when the step keeps growing quickly
+                let h = t_new - t_prev;
+                let mut times = vec![t_new];
+                for (t, _) in &self.tran_history {
+                    let t_last = times[times.len() - 1];
+                    if times.len() > maxord ||
+                            (times.len() > 2 && ((t_last - t) - h).abs() > 0.2 * h) {
+                        break;
+                    }
+                    times.push(*t);
+                }
+                Derivative::gear(&times)
+            },
+        }
+    }
+
+
+    /// Values of one of the unknowns at the previous timepoints, newest first
+    fn history(&self, f: impl Fn(&[f64]) -> f64) -> Vec<f64> {
+        self.tran_history.iter().map(|(_, x)| f(x)).collect()
+    }
+
+
+    fn storage_stamp(&self, m: &mut [Vec<f64>], d: &Derivative) {
 
         if !&self.storage_elements.is_empty() {
             trace!("  [STAMP] storage elements");
         }
 
+        // capacitances at the last timepoint's bias
+        let n = &self.tran_history[0].1;
+        for (c, &i_prev) in self.capacitors(n).iter().zip(&self.cap_currents) {
+            if c.value > 0.0 {
+                let v_hist = self.history(|x| x[c.a] - x[c.b]);
+                self.stamp_capacitor(m, c, &v_hist, i_prev, d);
+            }
+        }
+
         for el in &self.storage_elements {
             match *el {
-                circuit::Element::C(ref c) => {
+                circuit::Element::C(_) | circuit::Element::M(_) |
+                circuit::Element::D(_) | circuit::Element::Q(_) => {},
+                circuit::Element::L(ref l) => {
 
                     // linearize
-                    let v_c = n[c.a] - n[c.b];
-                    let (g_eq, i_eq) = c.linearize(v_c, t);
+                    let idx_l = self.c_nodes + l.idx;
+                    let i_hist = self.history(|x| x[idx_l]);
+                    let (r_eq, v_eq) = l.linearize(&i_hist, n[l.a] - n[l.b], d);
 
-                    // stamp
-                    self.stamp_current_source(m, &circuit::CurrentSource{
-                        p: c.b,
-                        n: c.a,
-                        value: i_eq
-                    });
-                    self.stamp_resistor(m, &circuit::Resistor{
-                        ident: "asdfa".to_string(),
-                        a: c.a,
-                        b: c.b,
-                        value: 1.0/g_eq
-                    });
+                    // stamp - incidence is already in the base matrix
+                    let ia = self.c_nodes + self.c_vsrcs;
+                    m[idx_l][idx_l] -= r_eq;
+                    m[idx_l][ia] -= v_eq;
+
+                },
+                circuit::Element::K(ref k) => {
+
+                    // linearize
+                    let idx_l1 = self.c_nodes + k.idx1;
+                    let idx_l2 = self.c_nodes + k.idx2;
+                    let i1_hist = self.history(|x| x[idx_l1]);
+                    let i2_hist = self.history(|x| x[idx_l2]);
+                    let (r_eq, v1_eq, v2_eq) = k.linearize(&i1_hist, &i2_hist, d);
+
+                    // stamp the coupling terms into both branch equations
+                    let ia = self.c_nodes + self.c_vsrcs;
+                    m[idx_l1][idx_l2] -= r_eq;
+                    m[idx_l1][ia] -= v1_eq;
+                    m[idx_l2][idx_l1] -= r_eq;
+                    m[idx_l2][ia] -= v2_eq;
 
                 },
                 _ => { println!("*ERROR* - unrecognised storage element"); }
@@ -797,6 +1551,206 @@ impl Engine {
     }
 
 
+    /// Work out the capacitor currents at an accepted timepoint, for the
+    /// trapezoidal rule on the next step
+    ///
+    /// Returns true if any of them are ringing: flipping direction on every
+    /// step, which the trapezoidal rule doesn't damp.
+    fn update_cap_currents(&mut self, n: &[f64], d: &Derivative, abstol: f64) -> bool {
+        let caps = self.capacitors(&self.tran_history[0].1);
+        let mut ringing = false;
+        for (k, c) in caps.iter().enumerate() {
+            let i_prev = self.cap_currents[k];
+            let i_now = if c.value > 0.0 {
+                let v_hist = self.history(|x| x[c.a] - x[c.b]);
+                let (g_eq, i_eq) = c.linearize(&v_hist, i_prev, d);
+                g_eq * (n[c.a] - n[c.b]) - i_eq
+            } else {
+                0.0
+            };
+            if i_now.abs() > abstol && i_now * i_prev < 0.0
+                    && i_prev * self.cap_currents_prev[k] < 0.0 {
+                trace!(" [TIMESTEP] {} is ringing", c.ident);
+                ringing = true;
+            }
+            self.cap_currents_prev[k] = i_prev;
+            self.cap_currents[k] = i_now;
+        }
+        ringing
+    }
+
+
+    /// Largest step the local truncation error allows, going by the charge
+    /// on the capacitors and the flux in the inductors at the new timepoint
+    ///
+    /// `None` if there's nothing to estimate it from.
+    fn truncation_step(
+        &self,
+        n: &[f64],
+        t_new: f64,
+        d: &Derivative,
+        cfg: &analysis::Configuration,
+    ) -> Option<f64> {
+        let (t_prev, ref n_prev) = self.tran_history[0];
+        let h = t_new - t_prev;
+        let mut times = vec![t_new];
+        times.extend(self.tran_history.iter().map(|(t, _)| *t));
+
+        let mut h_lte: Option<f64> = None;
+        let mut limit = |h_el: Option<f64>| {
+            if let Some(h_el) = h_el {
+                h_lte = Some(h_lte.map_or(h_el, |h| h.min(h_el)));
+            }
+        };
+
+        for (c, &i_prev) in self.capacitors(n_prev).iter().zip(&self.cap_currents) {
+            if c.value > 0.0 {
+                let v_hist = self.history(|x| x[c.a] - x[c.b]);
+                let (g_eq, i_eq) = c.linearize(&v_hist, i_prev, d);
+                let v_now = n[c.a] - n[c.b];
+                let i_now = g_eq * v_now - i_eq;
+
+                let mut qs = vec![c.value * v_now];
+                qs.extend(v_hist.iter().map(|v| c.value * v));
+                let tol = (cfg.RELTOL * i_now.abs().max(i_prev.abs()) + cfg.ABSTOL)
+                    .max(cfg.RELTOL * qs[0].abs().max(qs[1].abs()).max(cfg.CHGTOL) / h);
+                limit(d.truncation_step(&times, &qs, cfg.TRTOL * tol));
+            }
+        }
+
+        for el in &self.storage_elements {
+            if let circuit::Element::L(ref l) = *el {
+                let idx_l = self.c_nodes + l.idx;
+                let mut fluxes = vec![l.value * n[idx_l]];
+                fluxes.extend(self.history(|x| l.value * x[idx_l]));
+                let v_now = n[l.a] - n[l.b];
+                let v_prev = n_prev[l.a] - n_prev[l.b];
+                let tol = (cfg.RELTOL * v_now.abs().max(v_prev.abs()) + cfg.VNTOL)
+                    .max(cfg.RELTOL * fluxes[0].abs().max(fluxes[1].abs()) / h);
+                limit(d.truncation_step(&times, &fluxes, cfg.TRTOL * tol));
+            }
+        }
+        h_lte
+    }
+
+
+    // stamp the companion model of a capacitor
+    fn stamp_capacitor(
+        &self,
+        m: &mut [Vec<f64>],
+        c: &circuit::Capacitor,
+        v_hist: &[f64],
+        i_prev: f64,
+        d: &Derivative,
+    ) {
+
+        // linearize
+        let (g_eq, i_eq) = c.linearize(v_hist, i_prev, d);
+
+        // stamp
+        self.stamp_current_source(m, &circuit::CurrentSource{
+            ident: c.ident.clone(),
+            p: c.b,
+            n: c.a,
+            value: i_eq,
+            ac_mag: 0.0,
+            ac_phase: 0.0,
+        });
+        self.stamp_resistor(m, &circuit::Resistor{
+            ident: "asdfa".to_string(),
+            a: c.a,
+            b: c.b,
+            value: 1.0/g_eq
+        });
+    }
+
+
+    // stamp the admittances of the energy storage elements for AC analysis
+    fn ac_storage_stamp(&self, m: &mut [Vec<Complex>], omega: f64) {
+
+        for el in &self.storage_elements {
+            match *el {
+                circuit::Element::C(ref c) => {
+                    let y = Complex::new(0.0, omega * c.value);
+                    trace!("  [STAMP] AC {} : {}", el, y);
+                    self.stamp_admittance(m, c.a, c.b, y);
+                },
+                circuit::Element::L(ref l) => {
+                    // branch equation: v(a) - v(b) - jwL * i = 0
+                    let idx_l = self.c_nodes + l.idx;
+                    let z = Complex::new(0.0, omega * l.value);
+                    trace!("  [STAMP] AC {} : {}", el, z);
+                    m[idx_l][idx_l] -= z;
+                },
+                circuit::Element::D(ref d) => {
+                    let c_d = d.capacitance(self.dc_op[d.p] - self.dc_op[d.n]);
+                    trace!("  [STAMP] AC {} : {}", el, c_d);
+                    self.stamp_admittance(m, d.p, d.n, Complex::new(0.0, omega * c_d));
+                },
+                circuit::Element::Q(ref q) => {
+                    let (c_be, c_bc) = q.capacitances(self.dc_op[q.b] - self.dc_op[q.e]);
+                    trace!("  [STAMP] AC {} : {} {}", el, c_be, c_bc);
+                    self.stamp_admittance(m, q.b, q.e, Complex::new(0.0, omega * c_be));
+                    self.stamp_admittance(m, q.b, q.c, Complex::new(0.0, omega * c_bc));
+                },
+                circuit::Element::M(ref mos) => {
+                    let n = &self.dc_op;
+                    let (c_gs, c_gd, c_gb) = mos.capacitances(
+                        n[mos.g] - n[mos.s], n[mos.d] - n[mos.s], n[mos.b] - n[mos.s]);
+                    trace!("  [STAMP] AC {} : {} {} {}", el, c_gs, c_gd, c_gb);
+                    self.stamp_admittance(m, mos.g, mos.s, Complex::new(0.0, omega * c_gs));
+                    self.stamp_admittance(m, mos.g, mos.d, Complex::new(0.0, omega * c_gd));
+                    self.stamp_admittance(m, mos.g, mos.b, Complex::new(0.0, omega * c_gb));
+                },
+                circuit::Element::K(ref k) => {
+                    let idx_l1 = self.c_nodes + k.idx1;
+                    let idx_l2 = self.c_nodes + k.idx2;
+                    let z = Complex::new(0.0, omega * k.value);
+                    trace!("  [STAMP] AC {} : {}", el, z);
+                    m[idx_l1][idx_l2] -= z;
+                    m[idx_l2][idx_l1] -= z;
+                },
+                _ => { println!("*ERROR* - unrecognised storage element"); }
+            }
+        }
+    }
+
+    // stamp the AC stimulus of sources with an AC spec
+    fn ac_source_stamp(&self, m: &mut [Vec<Complex>]) {
+        let ia = self.c_nodes + self.c_vsrcs; // index for ampere vector
+
+        for el in &self.ac_sources {
+            match *el {
+                circuit::Element::I(ref isrc) => {
+                    let i_ac = Complex::from_polar_deg(isrc.ac_mag, isrc.ac_phase);
+                    if isrc.p != 0 { m[isrc.p][ia] -= i_ac }
+                    if isrc.n != 0 { m[isrc.n][ia] += i_ac }
+                },
+                circuit::Element::V(ref vsrc) => {
+                    let idx_vsrc = self.c_nodes + vsrc.idx;
+                    m[idx_vsrc][ia] = Complex::from_polar_deg(vsrc.ac_mag, vsrc.ac_phase);
+                },
+                _ => { println!("*ERROR* - unrecognised AC source"); }
+            }
+        }
+    }
+
+    // stamp a complex admittance between two nodes
+    fn stamp_admittance(&self, m: &mut [Vec<Complex>], a: usize, b: usize, y: Complex) {
+        if a != 0 {
+            m[a][a] += y;
+            if b != 0 {
+                m[a][b] -= y;
+            }
+        }
+        if b != 0 {
+            m[b][b] += y;
+            if a != 0 {
+                m[b][a] -= y;
+            }
+        }
+    }
+
     // stamp a matrix with linearized companion models of all the nonlinear
     // devices listed in the SPICE netlist
     fn nonlinear_stamp(&self, m: &mut [Vec<f64>], n: &[f64], n_prev: &[f64] ) {
@@ -818,9 +1772,12 @@ impl Engine {
 
                     // stamp
                     self.stamp_current_source(m, &circuit::CurrentSource{
+                        ident: d.ident.clone(),
                         p: d.p,
                         n: d.n,
-                        value: i_eq
+                        value: i_eq,
+                        ac_mag: 0.0,
+                        ac_phase: 0.0,
                     });
                     self.stamp_resistor(m, &circuit::Resistor{
                         ident: "nl_something".to_string(),
@@ -830,6 +1787,135 @@ impl Engine {
                     });
                 }
 
+                circuit::Element::Q(ref q) => {
+
+                    // linearize
+                    let v_be = n[q.b] - n[q.e];
+                    let v_bc = n[q.b] - n[q.c];
+                    let lin = q.linearize(v_be, v_bc);
+
+                    trace!(" [STAMP] {} {} {} {:?}", el, v_be, v_bc, lin);
+
+                    // stamp - each terminal current is linear in v_be & v_bc
+                    let i_c_eq = lin.i_c - lin.g_c_be * lin.v_be - lin.g_c_bc * lin.v_bc;
+                    let i_b_eq = lin.i_b - lin.g_b_be * lin.v_be - lin.g_b_bc * lin.v_bc;
+                    self.stamp_terminal(m, q.c, i_c_eq, &[
+                        (q.b, q.e, lin.g_c_be),
+                        (q.b, q.c, lin.g_c_bc),
+                    ]);
+                    self.stamp_terminal(m, q.b, i_b_eq, &[
+                        (q.b, q.e, lin.g_b_be),
+                        (q.b, q.c, lin.g_b_bc),
+                    ]);
+                    self.stamp_terminal(m, q.e, -(i_c_eq + i_b_eq), &[
+                        (q.b, q.e, -(lin.g_c_be + lin.g_b_be)),
+                        (q.b, q.c, -(lin.g_c_bc + lin.g_b_bc)),
+                    ]);
+                }
+
+                circuit::Element::M(ref mos) => {
+
+                    // linearize
+                    let v_gs = n[mos.g] - n[mos.s];
+                    let v_ds = n[mos.d] - n[mos.s];
+                    let v_bs = n[mos.b] - n[mos.s];
+                    let lin = mos.linearize(v_gs, v_ds, v_bs);
+
+                    trace!(" [STAMP] {} {} {} {} {:?}", el, v_gs, v_ds, v_bs, lin);
+
+                    // stamp - drain current flows in the drain, out the source
+                    let i_eq = lin.i_d - lin.g_m * v_gs - lin.g_ds * v_ds - lin.g_mb * v_bs;
+                    let terms = [
+                        (mos.g, mos.s, lin.g_m),
+                        (mos.d, mos.s, lin.g_ds),
+                        (mos.b, mos.s, lin.g_mb),
+                    ];
+                    self.stamp_terminal(m, mos.d, i_eq, &terms);
+                    let terms = terms.map(|(p, n, g)| (p, n, -g));
+                    self.stamp_terminal(m, mos.s, -i_eq, &terms);
+                }
+
+                circuit::Element::J(ref j) => {
+
+                    // linearize
+                    let v_gs = n[j.g] - n[j.s];
+                    let v_gd = n[j.g] - n[j.d];
+                    let lin = j.linearize(v_gs, v_gd);
+
+                    trace!(" [STAMP] {} {} {} {:?}", el, v_gs, v_gd, lin);
+
+                    // stamp - channel current in the drain and out the source,
+                    // gate junction currents out of the gate
+                    let v_ds = lin.v_gs - lin.v_gd;
+                    let i_d_eq = lin.i_d - lin.g_m * lin.v_gs - lin.g_ds * v_ds;
+                    let i_gs_eq = lin.i_gs - lin.g_gs * lin.v_gs;
+                    let i_gd_eq = lin.i_gd - lin.g_gd * lin.v_gd;
+                    self.stamp_terminal(m, j.d, i_d_eq - i_gd_eq, &[
+                        (j.g, j.s, lin.g_m),
+                        (j.d, j.s, lin.g_ds),
+                        (j.g, j.d, -lin.g_gd),
+                    ]);
+                    self.stamp_terminal(m, j.g, i_gs_eq + i_gd_eq, &[
+                        (j.g, j.s, lin.g_gs),
+                        (j.g, j.d, lin.g_gd),
+                    ]);
+                    self.stamp_terminal(m, j.s, -(i_d_eq + i_gs_eq), &[
+                        (j.g, j.s, -(lin.g_m + lin.g_gs)),
+                        (j.d, j.s, -lin.g_ds),
+                    ]);
+                }
+
+                circuit::Element::S(ref sw) => {
+
+                    // pick the on or off resistance
+                    let ctl = sw.control_value(n, self.c_nodes);
+                    let g = sw.conductance(ctl);
+
+                    trace!(" [STAMP] {} {} {}", el, ctl, g);
+
+                    self.stamp_resistor(m, &circuit::Resistor{
+                        ident: sw.ident.clone(),
+                        a: sw.p,
+                        b: sw.n,
+                        value: 1.0/g
+                    });
+                }
+
+                circuit::Element::B(ref bsrc) => {
+
+                    // linearize: f(x) ~ f(x0) + sum(df/dx_k * (x_k - x0_k))
+                    let (f0, derivs) = bsrc.evaluate(n, self.c_nodes);
+                    let f_eq = f0 - derivs.iter().map(|&(k, g)| g * n[k]).sum::<f64>();
+
+                    trace!(" [STAMP] {} {} {:?}", el, f0, derivs);
+
+                    match bsrc.kind {
+                        circuit::BehaviouralKind::Current => {
+                            // in through p, out through n, like the VCCS
+                            let terms: Vec<_> = derivs.iter().map(|&(k, g)| (k, 0, g)).collect();
+                            self.stamp_terminal(m, bsrc.p, f_eq, &terms);
+                            let terms: Vec<_> = derivs.iter().map(|&(k, g)| (k, 0, -g)).collect();
+                            self.stamp_terminal(m, bsrc.n, -f_eq, &terms);
+                        },
+                        circuit::BehaviouralKind::Voltage => {
+                            let idx = self.c_nodes + bsrc.idx; // index in ampere vector
+                            let ia = self.c_nodes + self.c_vsrcs;
+
+                            // branch current of output source
+                            if bsrc.p != 0 { m[bsrc.p][idx] += 1.0 }
+                            if bsrc.n != 0 { m[bsrc.n][idx] -= 1.0 }
+
+                            // v(p) - v(n) = f_eq + sum(df/dx_k * x_k)
+                            if bsrc.p != 0 { m[idx][bsrc.p] -= 1.0 }
+                            if bsrc.n != 0 { m[idx][bsrc.n] += 1.0 }
+                            for &(k, g) in &derivs {
+                                m[idx][k] += g;
+                            }
+                            m[idx][ia] -= f_eq;
+                        },
+                    }
+                }
+
                 _ => { println!("*ERROR* - unrecognised nonlinear element"); }
             }
         }
@@ -837,6 +1923,27 @@ impl Engine {
         //self.pp_matrix(&m);
     }
 
+    // stamp the current flowing into a transistor terminal `x`, which is
+    // `i_eq + sum(g * (v(p) - v(n)))` over the `(p, n, g)` terms
+    fn stamp_terminal(
+        &self,
+        m: &mut [Vec<f64>],
+        x: usize,
+        i_eq: f64,
+        terms: &[(usize, usize, f64)],
+    ) {
+        if x == 0 {
+            return;
+        }
+        let ia = self.c_nodes + self.c_vsrcs; // index for ampere vector
+
+        for &(p, n, g) in terms {
+            if p != 0 { m[x][p] += g; }
+            if n != 0 { m[x][n] -= g; }
+        }
+        m[x][ia] -= i_eq;
+    }
+
     fn v_dependent_source_stamp(&self, m: &mut [Vec<f64>]) {
 
         if !&self.v_dependent_sources.is_empty() {
@@ -880,6 +1987,32 @@ impl Engine {
                     }
                 }
 
+                circuit::Element::Cccs(ref src) => {
+                    // same direction as the VCCS, but controlled by the
+                    // branch current of a voltage source
+                    let ctl = self.c_nodes + src.ctl_idx;
+                    trace!(" [STAMP] CCCS (ctl:{} ({}))", src.ctl_idx, ctl);
+                    if src.p != 0 { m[src.p][ctl] += src.k }
+                    if src.n != 0 { m[src.n][ctl] -= src.k }
+                }
+
+                circuit::Element::Ccvs(ref src) => {
+                    let idx = self.c_nodes + src.idx; // index in ampere vector
+                    let ctl = self.c_nodes + src.ctl_idx;
+                    trace!(" [STAMP] CCVS (idx:{} ({}), ctl:{} ({}))",
+                        src.idx, idx, src.ctl_idx, ctl);
+
+                    // branch current of output source
+                    if src.p != 0 { m[src.p][idx] += 1.0 }
+                    if src.n != 0 { m[src.n][idx] -= 1.0 }
+
+                    // make sure controls and outputs are related
+                    m[idx][ctl] += src.k;
+
+                    if src.p != 0 { m[idx][src.p] -= 1.0 }
+                    if src.n != 0 { m[idx][src.n] += 1.0 }
+                }
+
                 _ => { println!("*ERROR* - unrecognised voltage-dependent source"); }
             }
         }
@@ -905,9 +2038,12 @@ impl Engine {
 
                     // stamp
                     self.stamp_current_source(m, &circuit::CurrentSource{
+                        ident: "isrc_something".to_string(),
                         p: isrc.p,
                         n: isrc.n,
                         value: i_now,
+                        ac_mag: 0.0,
+                        ac_phase: 0.0,
                     });
                 },
 
@@ -919,14 +2055,34 @@ impl Engine {
 
                     // stamp
                     self.stamp_voltage_source(m, &circuit::VoltageSource{
+                        ident: "vsrc_something".to_string(),
                         p: vsrc.p,
                         n: vsrc.n,
                         value: v_now,
                         idx: vsrc.idx,
+                        ac_mag: 0.0,
+                        ac_phase: 0.0,
+                    });
+                },
+
+                circuit::Element::Iwave(ref isrc) => {
+                    trace!(" [STAMP] {}", el);
+
+                    // evaluate at the present sim time
+                    let i_now = isrc.evaluate(t_now);
+
+                    // stamp
+                    self.stamp_current_source(m, &circuit::CurrentSource{
+                        ident: isrc.ident.clone(),
+                        p: isrc.p,
+                        n: isrc.n,
+                        value: i_now,
+                        ac_mag: 0.0,
+                        ac_phase: 0.0,
                     });
                 },
 
-                circuit::Element::Vpwl(ref vsrc) => {
+                circuit::Element::Vwave(ref vsrc) => {
                     trace!("  [STAMP] {}", el);
 
                     // evaluate at the present sim time
@@ -934,10 +2090,13 @@ impl Engine {
 
                     // stamp
                     self.stamp_voltage_source(m, &circuit::VoltageSource{
+                        ident: vsrc.ident.clone(),
                         p: vsrc.p,
                         n: vsrc.n,
                         value: v_now,
                         idx: vsrc.idx,
+                        ac_mag: 0.0,
+                        ac_phase: 0.0,
                     });
                 },
 
@@ -981,5 +2140,111 @@ impl Engine {
         res
     }
 
+    /// Breakpoints of all the independent sources up to `t_stop`, and
+    /// `t_stop` itself, latest first
+    fn breakpoints(&self, t_stop: f64) -> Vec<f64> {
+        let mut bps = vec![t_stop];
+        for el in &self.independent_sources {
+            match *el {
+                circuit::Element::Vwave(ref src) => bps.extend(src.wave.breakpoints(t_stop)),
+                circuit::Element::Iwave(ref src) => bps.extend(src.wave.breakpoints(t_stop)),
+                circuit::Element::Vsin(ref src) => bps.push(src.td),
+                circuit::Element::Isin(ref src) => bps.push(src.td),
+                _ => {}
+            }
+        }
+        bps.retain(|&t| t > 0.0 && t <= t_stop);
+        bps.sort_by(|a, b| b.total_cmp(a));
+        bps.dedup();
+        bps
+    }
+
+    /// Did any switch change state in the step just solved?
+    fn switch_event(&self) -> bool {
+        self.nonlinear_elements.iter().any(|el| match *el {
+            circuit::Element::S(ref sw) => sw.has_switched(),
+            _ => false,
+        })
+    }
+
+    /// The solution has been accepted, so the switches keep their state
+    fn accept_switches(&self) {
+        for el in &self.nonlinear_elements {
+            if let circuit::Element::S(ref sw) = *el {
+                sw.accept();
+            }
+        }
+    }
+
+    /// Check the nonlinear devices agree with the solution
+    ///
+    /// The node voltages can look converged while a device is still
+    /// limiting its junction voltages.
+    fn nonlinear_converged(&self, n: &[f64], cfg: &analysis::Configuration) -> bool {
+        for el in &self.nonlinear_elements {
+            match *el {
+                circuit::Element::Q(ref q) => {
+                    let v_be = n[q.b] - n[q.e];
+                    let v_bc = n[q.b] - n[q.c];
+                    if !q.is_converged(v_be, v_bc, cfg.RELTOL, cfg.VNTOL) {
+                        trace!("  [CONVERGE] {} not converged", q.ident);
+                        return false;
+                    }
+                }
+                circuit::Element::J(ref j) => {
+                    let v_gs = n[j.g] - n[j.s];
+                    let v_gd = n[j.g] - n[j.d];
+                    if !j.is_converged(v_gs, v_gd, cfg.RELTOL, cfg.VNTOL) {
+                        trace!("  [CONVERGE] {} not converged", j.ident);
+                        return false;
+                    }
+                }
+                circuit::Element::S(ref sw) => {
+                    let ctl = sw.control_value(n, self.c_nodes);
+                    if !sw.is_converged(ctl) {
+                        trace!("  [CONVERGE] {} not converged", sw.ident);
+                        return false;
+                    }
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+
+}
+
+
+/// Look up the nodes named in `.ic` or `.nodeset`, after expansion
+fn find_nodes(ckt: &circuit::Circuit, nodes: &[(String, f64)], card: &str)
+    -> Vec<(circuit::NodeId, f64)>
+{
+    let mut found = vec![];
+    for (name, value) in nodes {
+        match ckt.get_node_id(name) {
+            Some(0) => println!("*WARN* ignoring {} on ground node '{}'", card, name),
+            Some(node) => found.push((node, *value)),
+            None => println!("*ERROR* can't find node '{}' for {}", name, card),
+        }
+    }
+    found
 }
 
+
+/// Find a DC `V` or `I` source by its identifier
+fn find_dc_source(ckt: &circuit::Circuit, ident: &str) -> Option<circuit::Element> {
+    let src = ckt.elements.iter().find(|el| match el {
+        circuit::Element::V(ref vsrc) => vsrc.ident == ident,
+        circuit::Element::I(ref isrc) => isrc.ident == ident,
+        _ => false,
+    });
+    if src.is_none() {
+        println!("*ERROR* Can't find a DC source called '{}' to sweep", ident);
+    }
+    src.cloned()
+}
+
+/// Units of the value of a DC source
+fn dc_source_unit(src: &circuit::Element) -> &'static str {
+    if let circuit::Element::V(_) = src { "V" } else { "A" }
+}
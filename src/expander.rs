@@ -40,12 +40,20 @@
 use crate::element::{Element};
 use crate::circuit::{Circuit, Instance};
 use crate::circuit::{NodeId};
+use crate::circuit::{Model, ModelKind};
 
 use crate::element::resistor::Resistor;
 use crate::element::capacitor::Capacitor;
+use crate::element::inductor::Inductor;
+use crate::element::mutual::MutualInductance;
+use crate::element::bjt::Bjt;
+use crate::element::mosfet::Mosfet;
+use crate::element::jfet::Jfet;
 use crate::element::diode::Diode;
+use crate::element::switch::{Switch, Control};
 
-use crate::element::vdepsrc::{Vcvs, Vccs};
+use crate::element::vdepsrc::{Vcvs, Vccs, Cccs, Ccvs};
+use crate::element::behavioural::{BehaviouralSource, BehaviouralKind, Var, parse_expression};
 
 use crate::parameter::Parameter;
 use crate::bracket_expression::{Expression};
@@ -77,6 +85,21 @@ pub fn expand(ckts: &[Circuit]) -> Circuit {
     expand_instances(ckts, &mut ckt, 0, &hier);
     trace!("------------------------------------------------");
 
+    // `K` elements can only find their inductors once everything's expanded
+    if ckt.resolve_mutual_inductances() {
+        panic!("*FATAL* Mutual inductances couple inductors that don't exist");
+    }
+
+    // ... and the same for `F`, `H`, `W` & `B` and their controlling sources
+    if ckt.resolve_current_controls() {
+        panic!("*FATAL* Current-controlled sources refer to missing voltage sources");
+    }
+
+    // ... and `B` sources looking at nodes inside subcircuits
+    if ckt.resolve_behavioural_nodes() {
+        panic!("*FATAL* Behavioural sources refer to missing nodes");
+    }
+
     ckt.build_node_id_lut();
     ckt
 
@@ -88,8 +111,8 @@ pub fn expand(ckts: &[Circuit]) -> Circuit {
 /// Foreach instance in at this level
 /// 1. Resolve parameter values
 /// 2. either:
-///   2a. `expand_primitive()` or
-///   2b. `expand_subckt()`
+///    2a. `expand_primitive()` or
+///    2b. `expand_subckt()`
 ///
 /// Instances include both primitive circuit element and subcircuits
 fn expand_instances(
@@ -277,6 +300,22 @@ fn expand_instances(
     } // insts
 }
 
+/// Look up the optional `IC=` of a capacitor or inductor
+fn initial_condition(ckt: &Circuit, inst: &Instance, hier: &mut Vec<String>) -> Option<f64> {
+    if !inst.params.iter().any(|p| p.name == "/IC") {
+        return None;
+    }
+    hier.push("/IC".to_string());
+    let param_full_name = hier.join(".");
+    hier.pop();
+    if let Some(value) = ckt.get_param_value(&param_full_name) {
+        Some(value)
+    } else {
+        println!("Can't find {}", param_full_name);
+        panic!("*FATAL* IC for {} was not resolved", inst.name);
+    }
+}
+
 /// Expand a subcircuit instantiation
 /// Use this recursively
 fn expand_subckt(
@@ -337,11 +376,94 @@ fn expand_subckt(
     }
 
     hier.push(inst.name.to_string()); // inst-name
+    expand_sources(ckts, ckt, subckt_id, &hier);
     expand_instances(ckts, ckt, subckt_id, &hier);
     hier.pop(); // inst-name
 }
 
 
+/// Copy the DC, `SIN()` and waveform sources in a subcircuit into the
+/// toplevel clone
+///
+/// Sources aren't instances, so they need to be handled separately. This is
+/// mostly so that `F` and `H` can be controlled by a `V` inside a
+/// subcircuit.
+fn expand_sources(
+    ckts: &[Circuit],
+    ckt: &mut Circuit,
+    host_ckt_id: usize,
+    inhier: &[String]
+) {
+    let mut hier = inhier.to_owned();
+
+    for el in &ckts[host_ckt_id].elements {
+        match *el {
+            Element::V(ref vsrc) => {
+                let mut v = vsrc.clone();
+                v.p = local_connect(ckts, ckt, host_ckt_id, &hier, vsrc.p);
+                v.n = local_connect(ckts, ckt, host_ckt_id, &hier, vsrc.n);
+                hier.push(vsrc.ident.to_string());
+                v.ident = hier.join(".");
+                hier.pop();
+                trace!("Source: {}", v.ident);
+                ckt.add_v_src(v);
+            },
+            Element::I(ref isrc) => {
+                let mut i = isrc.clone();
+                i.p = local_connect(ckts, ckt, host_ckt_id, &hier, isrc.p);
+                i.n = local_connect(ckts, ckt, host_ckt_id, &hier, isrc.n);
+                hier.push(isrc.ident.to_string());
+                i.ident = hier.join(".");
+                hier.pop();
+                trace!("Source: {}", i.ident);
+                ckt.add_i_src(i);
+            },
+            Element::Vwave(ref vsrc) => {
+                let mut v = vsrc.clone();
+                v.p = local_connect(ckts, ckt, host_ckt_id, &hier, vsrc.p);
+                v.n = local_connect(ckts, ckt, host_ckt_id, &hier, vsrc.n);
+                hier.push(vsrc.ident.to_string());
+                v.ident = hier.join(".");
+                hier.pop();
+                trace!("Source: {}", v.ident);
+                ckt.add_v_wave(v);
+            },
+            Element::Iwave(ref isrc) => {
+                let mut i = isrc.clone();
+                i.p = local_connect(ckts, ckt, host_ckt_id, &hier, isrc.p);
+                i.n = local_connect(ckts, ckt, host_ckt_id, &hier, isrc.n);
+                hier.push(isrc.ident.to_string());
+                i.ident = hier.join(".");
+                hier.pop();
+                trace!("Source: {}", i.ident);
+                ckt.add_i_wave(i);
+            },
+            Element::Vsin(ref vsrc) => {
+                let mut v = vsrc.clone();
+                v.p = local_connect(ckts, ckt, host_ckt_id, &hier, vsrc.p);
+                v.n = local_connect(ckts, ckt, host_ckt_id, &hier, vsrc.n);
+                hier.push(vsrc.ident.to_string());
+                v.ident = hier.join(".");
+                hier.pop();
+                trace!("Source: {}", v.ident);
+                ckt.add_v_sin(v);
+            },
+            Element::Isin(ref isrc) => {
+                let mut i = isrc.clone();
+                i.p = local_connect(ckts, ckt, host_ckt_id, &hier, isrc.p);
+                i.n = local_connect(ckts, ckt, host_ckt_id, &hier, isrc.n);
+                trace!("Source: SIN in {}", hier.join("."));
+                ckt.add_i_sin(i);
+            },
+            _ => {
+                println!("*WARN* Ignoring '{}' in subcircuit '{}'",
+                    el, ckts[host_ckt_id].name);
+            }
+        }
+    }
+}
+
+
 /// Expand a primitive instantiation
 /// (not recursive)
 //
@@ -417,14 +539,149 @@ fn expand_primitive(
             panic!("*FATAL* Value for C was not resolved");
         };
 
-        let cap = Capacitor {ident, a: n[0], b: n[1], value };
+        let ic = initial_condition(ckt, inst, &mut hier);
+        let cap = Capacitor {ident, a: n[0], b: n[1], value, ic };
         ckt.elements.push(Element::C(cap));
+    } else if inst.name.starts_with('L') {
+        trace!("Found an inductor primitive");
+        assert!(!inst.params.is_empty());
+
+        hier.push("/param0".to_string());
+        let param_full_name = hier.join(".");
+        hier.pop();
+
+        let param_lut = ckt.get_param_value(&param_full_name);
+        let value = if let Some(lval) = param_lut {
+            lval
+        } else {
+            println!("Can't find {}", param_full_name);
+            panic!("*FATAL* Value for L was not resolved");
+        };
+
+        // Inductor currents are solved for like voltage source currents
+        let l_idx = ckt.v_idx_next;
+        ckt.v_idx_next += 1;
+
+        let mut ind = Inductor::new(&ident, n[0], n[1], value, l_idx);
+        ind.ic = initial_condition(ckt, inst, &mut hier);
+        ckt.elements.push(Element::L(ind));
+    } else if inst.name.starts_with('K') {
+        trace!("Found a mutual inductance primitive");
+        assert!(!inst.params.is_empty());
+        assert_eq!(inst.refs.len(), 2);
+
+        hier.push("/param0".to_string());
+        let param_full_name = hier.join(".");
+        hier.pop();
+
+        let param_lut = ckt.get_param_value(&param_full_name);
+        let k = if let Some(kval) = param_lut {
+            kval
+        } else {
+            println!("Can't find {}", param_full_name);
+            panic!("*FATAL* Coupling factor for K was not resolved");
+        };
+
+        // the coupled inductors are in the same scope as the `K`
+        let mut scope = inhier.to_owned();
+        scope.push(inst.refs[0].to_string());
+        let l1 = scope.join(".");
+        scope.pop();
+        scope.push(inst.refs[1].to_string());
+        let l2 = scope.join(".");
+
+        let mutual = MutualInductance::new(&ident, &l1, &l2, k);
+        ckt.elements.push(Element::K(mutual));
     } else if inst.name.starts_with('D') {
         trace!("Found a diode primitive");
-        let i_sat = 1e-9;
-        let tdegc = 27.0;
-        let diode = Diode::new(&ident, n[0], n[1], i_sat, tdegc);
-        ckt.elements.push(Element::D(diode));
+        if inst.model.is_some() {
+            let model = lookup_model(ckts, host_ckt_id, inst);
+            if model.kind != ModelKind::Diode {
+                panic!("*FATAL* '{}' uses model '{}', which isn't a diode model",
+                    ident, model.name);
+            }
+
+            // series resistance goes to an internal anode node
+            let mut diode = Diode::from_model(&ident, n[0], n[1], model);
+            if diode.r_s > 0.0 {
+                let a_int = ckt.add_node(&format!("{}#a", ident));
+                let res = Resistor {ident: format!("{}#rs", ident), a: n[0], b: a_int,
+                    value: diode.r_s};
+                ckt.elements.push(Element::R(res));
+                diode.p = a_int;
+            }
+            ckt.elements.push(Element::D(diode));
+        } else {
+            let i_sat = 1e-9;
+            let tdegc = 27.0;
+            let diode = Diode::new(&ident, n[0], n[1], i_sat, tdegc);
+            ckt.elements.push(Element::D(diode));
+        }
+    } else if inst.name.starts_with('Q') {
+        trace!("Found a bjt primitive");
+        let model = lookup_model(ckts, host_ckt_id, inst);
+        if model.kind != ModelKind::Npn && model.kind != ModelKind::Pnp {
+            panic!("*FATAL* '{}' uses model '{}', which isn't NPN or PNP",
+                ident, model.name);
+        }
+        let bjt = Bjt::new(&ident, n[0], n[1], n[2], model);
+        ckt.elements.push(Element::Q(bjt));
+    } else if inst.name.starts_with('M') {
+        trace!("Found a mosfet primitive");
+        let model = lookup_model(ckts, host_ckt_id, inst);
+        if model.kind != ModelKind::Nmos && model.kind != ModelKind::Pmos {
+            panic!("*FATAL* '{}' uses model '{}', which isn't NMOS or PMOS",
+                ident, model.name);
+        }
+
+        // optional instance parameters
+        let mut size = |name: &str| -> f64 {
+            let pname = format!("/{}", name);
+            if !inst.params.iter().any(|p| p.name == pname) {
+                return 100e-6;
+            }
+            hier.push(pname);
+            let param_full_name = hier.join(".");
+            hier.pop();
+            if let Some(value) = ckt.get_param_value(&param_full_name) {
+                value
+            } else {
+                println!("Can't find {}", param_full_name);
+                panic!("*FATAL* {} for M was not resolved", name);
+            }
+        };
+        let w = size("W");
+        let l = size("L");
+
+        let mosfet = Mosfet::new(&ident, n[0], n[1], n[2], n[3], model, w, l);
+        ckt.elements.push(Element::M(mosfet));
+    } else if inst.name.starts_with('J') {
+        trace!("Found a jfet primitive");
+        let model = lookup_model(ckts, host_ckt_id, inst);
+        if model.kind != ModelKind::Njf && model.kind != ModelKind::Pjf {
+            panic!("*FATAL* '{}' uses model '{}', which isn't NJF or PJF",
+                ident, model.name);
+        }
+        let jfet = Jfet::new(&ident, n[0], n[1], n[2], model);
+        ckt.elements.push(Element::J(jfet));
+    } else if inst.name.starts_with('S') || inst.name.starts_with('W') {
+        trace!("Found a switch primitive");
+        let model = lookup_model(ckts, host_ckt_id, inst);
+
+        // voltage-controlled by the last two nodes, or current-controlled
+        // by a voltage source in the same scope
+        let control = if inst.name.starts_with('S') {
+            Control::Voltage(n[2], n[3])
+        } else {
+            assert_eq!(inst.refs.len(), 1);
+            let mut scope = inhier.to_owned();
+            scope.push(inst.refs[0].to_string());
+            Control::Current(scope.join("."), 0)
+        };
+        let on = inst.params.iter().any(|p| p.name == "/ON");
+
+        let switch = Switch::new(&ident, n[0], n[1], control, model, on);
+        ckt.elements.push(Element::S(switch));
     } else if inst.name.starts_with('E') {
         trace!("Found a vcvs primitive");
         assert!(!inst.params.is_empty());
@@ -465,6 +722,81 @@ fn expand_primitive(
 
         let vccs = Vccs::new(&ident, n[0], n[1], n[2], n[3], k);
         ckt.elements.push(Element::Vccs(vccs));
+    } else if inst.name.starts_with('F') || inst.name.starts_with('H') {
+        trace!("Found a current-controlled source primitive");
+        assert!(!inst.params.is_empty());
+        assert_eq!(inst.refs.len(), 1);
+
+        hier.push("/param0".to_string());
+        let param_full_name = hier.join(".");
+        hier.pop();
+
+        let param_lut = ckt.get_param_value(&param_full_name);
+        let k = if let Some(cval) = param_lut {
+            cval
+        } else {
+            println!("Can't find {}", param_full_name);
+            panic!("*FATAL* Gain for {} was not resolved", inst.name);
+        };
+
+        // the controlling source is in the same scope as the `F` or `H`
+        let mut scope = inhier.to_owned();
+        scope.push(inst.refs[0].to_string());
+        let vsrc = scope.join(".");
+
+        if inst.name.starts_with('F') {
+            let cccs = Cccs::new(&ident, n[0], n[1], &vsrc, k);
+            ckt.elements.push(Element::Cccs(cccs));
+        } else {
+            // Need to update the voltage source ids for CCVS
+            let v_idx = ckt.v_idx_next;
+            ckt.v_idx_next += 1;
+
+            let ccvs = Ccvs::new(&ident, n[0], n[1], &vsrc, k, v_idx);
+            ckt.elements.push(Element::Ccvs(ccvs));
+        }
+    } else if inst.name.starts_with('B') {
+        trace!("Found a behavioural source primitive");
+        let text = inst.behaviour.as_ref()
+            .unwrap_or_else(|| panic!("*FATAL* '{}' has no expression", inst.name));
+        let (kind, expr_text) = text.split_once('=').unwrap();
+        let (expr, mut vars) = parse_expression(expr_text)
+            .unwrap_or_else(|| panic!("*FATAL* Bad expression for '{}'", ident));
+
+        // nodes and sources are in the same scope as the `B`
+        for var in &mut vars {
+            match *var {
+                Var::Node(ref mut name, ref mut nid) => {
+                    if let Some(lnid) = ckts[host_ckt_id].get_node_id(name) {
+                        *nid = local_connect(ckts, ckt, host_ckt_id, inhier, lnid);
+                    } else {
+                        // `v(X1.out)` reaches into a subcircuit that may
+                        // not be expanded yet, so look it up at the end
+                        let mut scope = inhier.to_owned();
+                        scope.push(name.to_string());
+                        *name = scope.join(".");
+                        *nid = NodeId::MAX;
+                    }
+                },
+                Var::Branch(ref mut name, _) => {
+                    let mut scope = inhier.to_owned();
+                    scope.push(name.to_string());
+                    *name = scope.join(".");
+                },
+            }
+        }
+
+        let bsrc = if kind == "V" {
+            // Need to update the voltage source ids for `V=`
+            let v_idx = ckt.v_idx_next;
+            ckt.v_idx_next += 1;
+            BehaviouralSource::new(&ident, n[0], n[1], BehaviouralKind::Voltage,
+                expr, vars, v_idx)
+        } else {
+            BehaviouralSource::new(&ident, n[0], n[1], BehaviouralKind::Current,
+                expr, vars, 0)
+        };
+        ckt.elements.push(Element::B(bsrc));
     } else {
         println!("*ERROR* Unrecognised primitive '{}'", inst.name);
         panic!("*FATAL*");
@@ -491,6 +823,24 @@ fn find_subckt_index(ckts: &[Circuit], name: &str) -> Option<usize> {
 }
 
 
+/// Find the `.model` a device instance uses.
+///
+/// Models defined in the subcircuit the device is in are used first, then
+/// the ones defined at the toplevel.
+fn lookup_model<'a>(ckts: &'a [Circuit], host_ckt_id: usize, inst: &Instance) -> &'a Model {
+    let name = inst.model.as_ref()
+        .unwrap_or_else(|| panic!("*FATAL* '{}' has no model", inst.name));
+
+    if let Some(model) = ckts[host_ckt_id].find_model(name) {
+        model
+    } else if let Some(model) = ckts[0].find_model(name) {
+        model
+    } else {
+        panic!("*FATAL* Can't find model '{}' for '{}'", name, inst.name);
+    }
+}
+
+
 /// Connect a primitive
 fn local_connect(
     ckts: &[Circuit],
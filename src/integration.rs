@@ -0,0 +1,173 @@
+//! Numerical Integration
+//!
+//! Storage elements are turned into companion models by replacing the
+//! derivative at the new timepoint with a weighted sum of the solutions at
+//! the new and previous timepoints, and maybe the derivative at the last
+//! one:
+//!
+//! ```text
+//! dx/dt(n) = a[0] x(n) + a[1] x(n-1) + ... + a[k] x(n-k) + b dx/dt(n-1)
+//! ```
+//!
+//! * Backward Euler: `a = [1/h, -1/h]`
+//! * Trapezoidal: `a = [2/h, -2/h]`, `b = -1`
+//! * Gear (BDF) of order `k`: `a` comes from differentiating the polynomial
+//!   through the last `k+1` timepoints, which needn't be evenly spaced.
+//!   Order 1 is backward Euler.
+//!
+//! A method of order `k` gets the derivative wrong by about
+//! `E h^k x^(k+1)`, where `E` is its error constant. `x^(k+1)` is estimated
+//! from a divided difference over the new timepoint and the last `k+1`, which
+//! gives the step the local truncation error allows.
+
+/// Error constants of Gear, by order
+const GEAR_ERROR: [f64; 7] = [
+    0.0, 1.0 / 2.0, 2.0 / 9.0, 3.0 / 22.0, 12.0 / 125.0, 10.0 / 137.0, 20.0 / 343.0
+];
+
+/// Weights for the derivative at the new timepoint
+#[derive(Clone, Debug)]
+pub struct Derivative {
+    pub a: Vec<f64>,
+    pub b: f64,
+    order: usize,
+    error: f64,
+}
+
+impl Derivative {
+
+    pub fn euler(t_delta: f64) -> Derivative {
+        Derivative { a: vec![1.0 / t_delta, -1.0 / t_delta], b: 0.0, order: 1, error: 0.5 }
+    }
+
+    pub fn trapezoidal(t_delta: f64) -> Derivative {
+        Derivative {
+            a: vec![2.0 / t_delta, -2.0 / t_delta], b: -1.0, order: 2, error: 1.0 / 12.0
+        }
+    }
+
+    /// Gear, from the new time then the previous timepoints, newest first.
+    /// The order is one less than the number of times.
+    pub fn gear(times: &[f64]) -> Derivative {
+        let t_new = times[0];
+        let mut a = vec![0.0; times.len()];
+
+        // derivative of each Lagrange basis polynomial at the new time
+        a[0] = times[1..].iter().map(|t| 1.0 / (t_new - t)).sum();
+        for j in 1..times.len() {
+            let mut num = 1.0;
+            let mut den = 1.0;
+            for (m, t) in times.iter().enumerate() {
+                if m != j {
+                    den *= times[j] - t;
+                    if m != 0 {
+                        num *= t_new - t;
+                    }
+                }
+            }
+            a[j] = num / den;
+        }
+        let order = times.len() - 1;
+        Derivative { a, b: 0.0, order, error: GEAR_ERROR[order] }
+    }
+
+    /// Order of accuracy
+    pub fn order(&self) -> usize {
+        self.order
+    }
+
+    /// The part of the derivative from the previous timepoints,
+    /// `a[1] x(n-1) + ... + a[k] x(n-k)`, with `x_hist` newest first
+    pub fn history(&self, x_hist: &[f64]) -> f64 {
+        self.a[1..].iter().zip(x_hist).map(|(a, x)| a * x).sum()
+    }
+
+    /// Largest step that keeps the error in the derivative of `x` inside
+    /// `tol`, from `x` at the new and previous timepoints, newest first
+    ///
+    /// `None` if there aren't enough previous timepoints to estimate it.
+    pub fn truncation_step(&self, times: &[f64], xs: &[f64], tol: f64) -> Option<f64> {
+        let c_points = self.order + 2;
+        if times.len() < c_points || xs.len() < c_points {
+            return None;
+        }
+        let x_diff = nth_derivative(&times[..c_points], &xs[..c_points]).abs();
+        if x_diff == 0.0 {
+            return Some(f64::INFINITY);
+        }
+        Some((tol / (self.error * x_diff)).powf(1.0 / self.order as f64))
+    }
+
+}
+
+/// Estimate the `n`th derivative of `x` from it at `n+1` timepoints,
+/// with divided differences
+pub fn nth_derivative(times: &[f64], xs: &[f64]) -> f64 {
+    let mut diffs = xs.to_vec();
+    let mut factorial = 1.0;
+    for n in 1..times.len() {
+        for i in 0..times.len() - n {
+            diffs[i] = (diffs[i] - diffs[i + 1]) / (times[i] - times[i + n]);
+        }
+        factorial *= n as f64;
+    }
+    diffs[0] * factorial
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_nearly(x: f64, expected: f64) {
+        assert!((x - expected).abs() < 1e-9, "{} isn't approximately {}", x, expected);
+    }
+
+    #[test]
+    fn gear_even_steps() {
+        // the textbook BDF coefficients, with h = 1
+        let bdf2 = Derivative::gear(&[0.0, -1.0, -2.0]);
+        for (a, expected) in bdf2.a.iter().zip([1.5, -2.0, 0.5]) {
+            assert_nearly(*a, expected);
+        }
+        let bdf3 = Derivative::gear(&[0.0, -1.0, -2.0, -3.0]);
+        for (a, expected) in bdf3.a.iter().zip([11.0 / 6.0, -3.0, 1.5, -1.0 / 3.0]) {
+            assert_nearly(*a, expected);
+        }
+
+        // order 1 is backward Euler
+        let be = Derivative::gear(&[3.0, 2.5]);
+        assert_eq!(be.order(), 1);
+        assert_nearly(be.a[0], Derivative::euler(0.5).a[0]);
+        assert_nearly(be.a[1], Derivative::euler(0.5).a[1]);
+    }
+
+    #[test]
+    fn gear_uneven_steps() {
+        // exact for polynomials up to the order, however the steps fall
+        let times = [1.0, 0.7, 0.2, 0.1, -0.5, -0.6, -2.0];
+        let x = |t: f64| 2.0 - t + 3.0 * t.powi(2) - t.powi(6);
+        let dxdt = -1.0 + 6.0 - 6.0;
+        let d = Derivative::gear(&times);
+        let x_hist: Vec<f64> = times[1..].iter().map(|&t| x(t)).collect();
+        assert_nearly(d.a[0] * x(1.0) + d.history(&x_hist), dxdt);
+    }
+
+    #[test]
+    fn truncation_error() {
+        // third derivative of t^3 is 6, wherever the points are
+        let times = [1.0, 0.7, 0.2, 0.1];
+        let xs: Vec<f64> = times.iter().map(|t| t * t * t).collect();
+        assert_nearly(nth_derivative(&times, &xs), 6.0);
+
+        // trapezoidal is second order, so the step goes as the square root
+        let d = Derivative::trapezoidal(0.3);
+        assert_eq!(d.order(), 2);
+        assert_nearly(d.truncation_step(&times, &xs, 0.5).unwrap(), 1.0);
+        assert_nearly(d.truncation_step(&times, &xs, 2.0).unwrap(), 2.0);
+        assert!(d.truncation_step(&times[..3], &xs, 0.5).is_none());
+
+        // nothing changing, so no limit
+        let d = Derivative::euler(0.3);
+        assert_eq!(d.truncation_step(&times, &[1.0; 4], 0.5), Some(f64::INFINITY));
+    }
+}
@@ -2,12 +2,15 @@
 
 // Circuit and Analysis Datastructures
 pub mod parameter;
+pub mod model;
 pub mod bracket_expression;
 pub mod circuit;
 pub mod analysis;
 
 // Simulation Engine
 pub mod engine;
+pub mod complex;
+pub mod integration;
 
 // Device Models
 pub mod element;
@@ -15,6 +18,9 @@ pub mod element;
 // Waveform dumper
 pub mod wavewriter;
 
+// WAV audio files
+pub mod wavfile;
+
 // Read and elaborate SPICE circuit descriptions
 pub mod spice;
 pub mod expander;
@@ -0,0 +1,81 @@
+//! Device Models
+//!
+//! `.model <name> <kind> (<PARAM>=<value> ...)` cards. The parameters are
+//! kept as a simple list of name/value pairs, and the devices pick out the
+//! ones they know about when the circuit is expanded. Anything not given
+//! in the card falls back to the device's default.
+
+use std::fmt;
+
+/// What sort of device a model describes
+#[derive(Clone, Debug, PartialEq)]
+pub enum ModelKind {
+    Npn,
+    Pnp,
+    Nmos,
+    Pmos,
+    Njf,
+    Pjf,
+    Diode,
+    Switch,
+    CurrentSwitch,
+}
+
+impl ModelKind {
+
+    /// Map the SPICE model type name to a `ModelKind`
+    pub fn from_spice(text: &str) -> Option<ModelKind> {
+        match text.to_uppercase().as_str() {
+            "NPN" => Some(ModelKind::Npn),
+            "PNP" => Some(ModelKind::Pnp),
+            "NMOS" => Some(ModelKind::Nmos),
+            "PMOS" => Some(ModelKind::Pmos),
+            "NJF" => Some(ModelKind::Njf),
+            "PJF" => Some(ModelKind::Pjf),
+            "D" => Some(ModelKind::Diode),
+            "SW" => Some(ModelKind::Switch),
+            "CSW" => Some(ModelKind::CurrentSwitch),
+            _ => None,
+        }
+    }
+
+}
+
+/// Device Model
+#[derive(Clone, Debug)]
+pub struct Model {
+    pub name: String,
+    pub kind: ModelKind,
+    pub params: Vec<(String, f64)>,
+}
+
+impl Model {
+
+    pub fn new(name: &str, kind: ModelKind) -> Self {
+        Model {
+            name: name.to_string(),
+            kind,
+            params: vec![],
+        }
+    }
+
+    /// Value of a model parameter, if it was given. Names are not case
+    /// sensitive.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        self.params.iter()
+            .find(|(pname, _)| pname.eq_ignore_ascii_case(name))
+            .map(|(_, value)| *value)
+    }
+
+    /// Value of a model parameter, or `default` if it wasn't given
+    pub fn get_or(&self, name: &str, default: f64) -> f64 {
+        self.get(name).unwrap_or(default)
+    }
+
+}
+
+impl fmt::Display for Model {
+    fn fmt (&self, f:&mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Model '{}' ({:?}) {:?}", self.name, self.kind, self.params)
+    }
+}
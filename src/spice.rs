@@ -7,7 +7,37 @@
 //! * Components:
 //!   * Voltage source : `V<ident> <n+> <n-> <value>`
 //!   * Current source : `I<ident> <n+> <n-> <value>`
+//!   * Source waveforms :
+//!     * `SIN(<vo> <va> <freq> [<td> <theta> <phase>])`
+//!     * `PWL(<t1> <v1> <t2> <v2> ...) [td=<delay>] [r=<time>]`
+//!     * `PWL FILE=<filename> [td=<delay>] [r=<time>]`
+//!     * `WAV(file=<filename> [chan=<n>] [gain=<value>] [td=<delay>])`
+//!     * `PULSE(<v1> <v2> [<td> <tr> <tf> <pw> <per>])`
+//!     * `EXP(<v1> <v2> <td1> <tau1> [<td2> <tau2>])`
+//!     * `SFFM(<vo> <va> <fc> <mdi> <fs> [<phasec> <phases>])`
+//!     * `AM(<va> <vo> <mf> <fc> [<td> <phasem> <phasec>])`
 //!   * Resistor : `R<ident> <n1> <n2> <value>`
+//!   * Inductor : `L<ident> <n1> <n2> <value>`
+//!   * Mutual inductance : `K<ident> <L1> <L2> <k>`
+//!   * Diode : `D<ident> <n+> <n-> [<model>]`
+//!   * CCCS : `F<ident> <n+> <n-> <Vsrc> <gain>`
+//!   * CCVS : `H<ident> <n+> <n-> <Vsrc> <transresistance>`
+//!   * Behavioural source : `B<ident> <n+> <n-> V={<expr>}` or `I={<expr>}`
+//!   * BJT : `Q<ident> <c> <b> <e> <model>`
+//!   * MOSFET : `M<ident> <d> <g> <s> <b> <model> [W=<value>] [L=<value>]`
+//!   * JFET : `J<ident> <d> <g> <s> <model>`
+//!   * Switch : `S<ident> <n+> <n-> <nc+> <nc-> <model> [ON|OFF]`
+//!   * Current-controlled switch : `W<ident> <n+> <n-> <Vsrc> <model> [ON|OFF]`
+//!   * Initial conditions : `IC=<value>` on `C` and `L`, used with `uic`
+//! * Initial node voltages: `.ic v(<node>)=<value> ...`
+//!   * nodes can be hierarchical, e.g. `v(Xsystem1.soutp)`
+//! * Starting guesses for DC: `.nodeset v(<node>)=<value> ...`
+//! * Device models: `.model <name> <kind> (<PARAM>=<value> ...)`
+//!   * `NPN` and `PNP`
+//!   * `NMOS` and `PMOS`
+//!   * `NJF` and `PJF`
+//!   * `D`
+//!   * `SW` and `CSW`
 //! * Node names:
 //!   * Integers (e.g. `23`) or text (e.g. `node_3452`)
 //!   * As usual, `0` , `gnd` and `GND` are aliases
@@ -17,13 +47,27 @@
 //! * Control Blocks:
 //!   Only one operation for now - no sequences
 //!   * DC Operating Point `op`
-//!   * Transient : `tran <t_step> <t_stop> [t_start]`
-//!     * for now, `t_start` is ignored
+//!   * Transient : `tran <t_step> <t_stop> [<t_start> [<t_max>]] [uic]`
+//!     * `t_max` caps the timestep, `uic` skips the DC operating point
+//!     * also as a `.tran` dot-card outside the control block
+//!   * DC Sweep : `dc <src> <start> <stop> <incr>`
+//!     * with an optional outer sweep `<src2> <start2> <stop2> <incr2>`
+//!   * AC small-signal : `ac dec|oct|lin <points> <f_start> <f_stop>`
+//!     * sources take an `AC <mag> [<phase>]` spec
+//!   * WAV output : `wav <file> <signal> [<signal2>] [rate=<sps>] [bits=<n>] [norm]`
+//!     * signals are `v(<node>)`, `v(<node1>,<node2>)` or `i(<ident>)`
 //! * Options (in Control Blocks)
-//!   * Options: `option <OPTION_NAME> = <value>`
+//!   * Options: `option <OPTION_NAME> = <value>`, in any case
 //!     * `ABSTOL`
 //!     * `RELTOL`
 //!     * `RMAX`
+//!     * `method` - `trap` (the default), `euler` or `gear`
+//!     * `maxord` - highest order for `gear`, 2 (the default) to 6
+//!     * `trtol` - how much the truncation error is overestimated by, 7 by
+//!       default. Smaller takes shorter timesteps.
+//!   * Flags: `option <FLAG>`
+//!     * `interp` - write transient results on the `t_step` grid
+//!     * `nointerp` - write every timepoint (the default)
 //! * Subcircuits
 //!   * Subcircuit definitions with `.subckt` and `.ends`
 //!   * Instantiations with `X...`
@@ -33,14 +77,23 @@ use std::fs::File;
 use std::io::{BufReader, BufRead};
 
 use crate::circuit::{Circuit, CurrentSourceSine, VoltageSourceSine};
-use crate::circuit::{VoltageSourcePwl};
-use crate::circuit::{Instance};
+use crate::circuit::{VoltageSource, CurrentSource};
+use crate::circuit::{Waveform, VoltageSourceWaveform, CurrentSourceWaveform};
+use crate::element::pulse::Pulse;
+use crate::element::exponential::Exponential;
+use crate::element::sffm::Sffm;
+use crate::element::am::Am;
+use crate::element::pwl::Pwl;
+use crate::element::wav::Wav;
+use crate::wavfile;
+use crate::circuit::{Instance, Model, ModelKind};
 use crate::parameter::{Parameter};
 use crate::bracket_expression::{extract_expression, extract_value};
 use crate::bracket_expression::Expression::{Literal};
 
-use crate::analysis::{Configuration, Kind};
+use crate::analysis::{Configuration, Kind, AcSweep, WavOutput, WavSignal, Method};
 use crate::expander;
+use crate::element::behavioural::parse_expression;
 
 /// Program execution trace macro - prefix `<spice>`
 macro_rules! trace {
@@ -177,22 +230,21 @@ impl Reader {
                     self.cfg.set_wavefile(wavefile.to_str().expect("CasdfasDF"));
 
                 } else if bits[0] == "tran" {
-                    self.cfg.kind = Some(Kind::Transient);
+                    self.extract_tran(&bits);
+                } else if bits[0] == "dc" {
                     let wavefile = Path::new("waves")
                         .join(&self.cfg.ckt_name)
-                        .join("tran.dat");
+                        .join("dc.dat");
                     self.cfg.set_wavefile(wavefile.to_str().expect("CasdfasDF"));
-
-                    // step stop <start>
-                    if bits.len() < 3 {
-                        println!("*ERROR* not enough trans info");
-                        self.there_are_errors = true;
-                    }
-                    self.cfg.TSTEP = extract_value(bits[1]).unwrap();
-                    self.cfg.TSTOP = extract_value(bits[2]).unwrap();
-                    if bits.len() > 3 {
-                        self.cfg.TSTART = extract_value(bits[3]).unwrap();
-                    }
+                    self.extract_dc_sweep(&bits);
+                } else if bits[0] == "ac" {
+                    let wavefile = Path::new("waves")
+                        .join(&self.cfg.ckt_name)
+                        .join("ac.dat");
+                    self.cfg.set_wavefile(wavefile.to_str().expect("CasdfasDF"));
+                    self.extract_ac(&bits);
+                } else if bits[0] == "wav" {
+                    self.extract_wav_output(&bits);
                 } else if bits[0] == "option" {
                         self.extract_option(&bits);
                 } else if bits[0] == ".endc" {
@@ -212,37 +264,66 @@ impl Reader {
                     in_subckt = false;
                     self.c = 0; // point back to toplevel
                 } else if bits[0].starts_with('I') {
-                    let _ = extract_identifier(bits[0]);
+                    let ident = extract_identifier(bits[0]);
                     let node1 = self.extract_node(bits[1]);
                     let node2 = self.extract_node(bits[2]);
-                    if bits.len() == 4 {
-                        trace!("*INFO* Idc");
-                        let value = extract_value(bits[3]);
-                        self.ckts[self.c].add_i(node1, node2, value.unwrap());
-                    } else if bits[3].starts_with("SIN") {
+                    if bits[3].starts_with("SIN") {
                         trace!("*INFO* Isin");
                         let src = self.extract_i_sine(&bits);
                         self.ckts[self.c].add_i_sin(src);
+                    } else if is_waveform(bits[3]) {
+                        trace!("*INFO* Iwave");
+                        if let Some(wave) = self.extract_waveform(&bits, filepath) {
+                            self.ckts[self.c].add_i_wave(CurrentSourceWaveform {
+                                ident,
+                                p: node1,
+                                n: node2,
+                                wave,
+                            });
+                        }
+                    } else {
+                        trace!("*INFO* Idc");
+                        let (value, ac_mag, ac_phase) = self.extract_dc_ac(&bits[3..]);
+                        self.ckts[self.c].add_i_src(CurrentSource {
+                            ident,
+                            p: node1,
+                            n: node2,
+                            value,
+                            ac_mag,
+                            ac_phase,
+                        });
                     }
                 } else if bits[0].starts_with('V') {
-                    let _ = extract_identifier(bits[0]);
+                    let ident = extract_identifier(bits[0]);
                     let node1 = self.extract_node(bits[1]);
                     let node2 = self.extract_node(bits[2]);
-                    if bits.len() == 4 {
-                        trace!("*INFO* Vdc");
-                        let value = extract_value(bits[3]);
-                        self.ckts[self.c].add_v(node1, node2, value.unwrap());
-                    } else if bits[3].starts_with("SIN(") {
+                    if bits[3].starts_with("SIN(") {
                         trace!("*INFO* Vsin");
                         let src = self.extract_v_sine(&bits);
                         self.ckts[self.c].add_v_sin(src);
-                    } else if bits[3].starts_with("PWL(") {
-                        trace!("*INFO* Vpwl");
-                        let src = self.extract_v_pwl(&bits);
-                        self.ckts[self.c].add_v_pwl(src);
+                    } else if is_waveform(bits[3]) {
+                        trace!("*INFO* Vwave");
+                        if let Some(wave) = self.extract_waveform(&bits, filepath) {
+                            self.ckts[self.c].add_v_wave(VoltageSourceWaveform {
+                                ident,
+                                p: node1,
+                                n: node2,
+                                wave,
+                                idx: 0,
+                            });
+                        }
                     } else {
-                        println!("Some weird V thing you've got going here");
-                        println!("bits: {:?}", bits);
+                        trace!("*INFO* Vdc");
+                        let (value, ac_mag, ac_phase) = self.extract_dc_ac(&bits[3..]);
+                        self.ckts[self.c].add_v_src(VoltageSource {
+                            ident,
+                            p: node1,
+                            n: node2,
+                            value,
+                            idx: 0,
+                            ac_mag,
+                            ac_phase,
+                        });
                     }
                 } else if bits[0].starts_with('R') {
                     if let Some(r) = self.extract_primitive(&bits, 2, 1) {
@@ -251,13 +332,35 @@ impl Reader {
                         println!("*ERROR* can't extract resistance");
                     }
                 } else if bits[0].starts_with('C') {
-                    if let Some(c) = self.extract_primitive(&bits, 2, 1) {
+                    let (bits, ic) = self.extract_element_ic(&bits);
+                    if let Some(mut c) = self.extract_primitive(&bits, 2, 1) {
+                        c.params.extend(ic);
                         self.ckts[self.c].add_instance(c);
                     } else {
                         println!("*ERROR* can't extract capacitor");
                     }
+                } else if bits[0].starts_with('L') {
+                    let (bits, ic) = self.extract_element_ic(&bits);
+                    if let Some(mut l) = self.extract_primitive(&bits, 2, 1) {
+                        l.params.extend(ic);
+                        self.ckts[self.c].add_instance(l);
+                    } else {
+                        println!("*ERROR* can't extract inductor");
+                    }
+                } else if bits[0].starts_with('K') {
+                    if let Some(k) = self.extract_mutual(&bits) {
+                        self.ckts[self.c].add_instance(k);
+                    } else {
+                        println!("*ERROR* can't extract mutual inductance");
+                        self.there_are_errors = true;
+                    }
                 } else if bits[0].starts_with('D') {
-                    if let Some(d) = self.extract_primitive(&bits, 2, 0) {
+                    let d = if bits.len() > 3 {
+                        self.extract_device(&bits, 2)
+                    } else {
+                        self.extract_primitive(&bits, 2, 0)
+                    };
+                    if let Some(d) = d {
                         self.ckts[self.c].add_instance(d);
                     } else {
                         println!("*ERROR* can't extract diode");
@@ -274,6 +377,55 @@ impl Reader {
                     } else {
                         println!("*ERROR* can't extract VCCS");
                     }
+                } else if bits[0].starts_with('F') {
+                    if let Some(f) = self.extract_current_controlled(&bits) {
+                        self.ckts[self.c].add_instance(f);
+                    } else {
+                        println!("*ERROR* can't extract CCCS");
+                        self.there_are_errors = true;
+                    }
+                } else if bits[0].starts_with('H') {
+                    if let Some(h) = self.extract_current_controlled(&bits) {
+                        self.ckts[self.c].add_instance(h);
+                    } else {
+                        println!("*ERROR* can't extract CCVS");
+                        self.there_are_errors = true;
+                    }
+                } else if bits[0].starts_with('B') {
+                    if let Some(b) = self.extract_behavioural(&bits) {
+                        self.ckts[self.c].add_instance(b);
+                    } else {
+                        println!("*ERROR* can't extract behavioural source");
+                        self.there_are_errors = true;
+                    }
+                } else if bits[0].starts_with('S') || bits[0].starts_with('W') {
+                    if let Some(sw) = self.extract_switch(&bits) {
+                        self.ckts[self.c].add_instance(sw);
+                    } else {
+                        println!("*ERROR* can't extract switch");
+                        self.there_are_errors = true;
+                    }
+                } else if bits[0].starts_with('Q') {
+                    if let Some(q) = self.extract_device(&bits, 3) {
+                        self.ckts[self.c].add_instance(q);
+                    } else {
+                        println!("*ERROR* can't extract BJT");
+                        self.there_are_errors = true;
+                    }
+                } else if bits[0].starts_with('M') {
+                    if let Some(m) = self.extract_device(&bits, 4) {
+                        self.ckts[self.c].add_instance(m);
+                    } else {
+                        println!("*ERROR* can't extract MOSFET");
+                        self.there_are_errors = true;
+                    }
+                } else if bits[0].starts_with('J') {
+                    if let Some(j) = self.extract_device(&bits, 3) {
+                        self.ckts[self.c].add_instance(j);
+                    } else {
+                        println!("*ERROR* can't extract JFET");
+                        self.there_are_errors = true;
+                    }
                 } else if bits[0].starts_with('X') {
                     trace!("Found instantiation");
                     let inst = self.extract_instance(&bits);
@@ -281,6 +433,20 @@ impl Reader {
                 } else if bits[0].starts_with('.') {
                     if bits[0] == ".control" {
                         in_control_block = true;
+                    } else if bits[0] == ".tran" {
+                        self.extract_tran(&bits);
+                    } else if bits[0] == ".ic" || bits[0] == ".nodeset" {
+                        if self.c != 0 {
+                            println!("*ERROR* {} inside a subcircuit", bits[0]);
+                            self.there_are_errors = true;
+                        }
+                        for (node, value) in self.extract_node_voltages(&bits) {
+                            if bits[0] == ".ic" {
+                                self.cfg.add_initial_condition(&node, value);
+                            } else {
+                                self.cfg.add_nodeset(&node, value);
+                            }
+                        }
                     } else if bits[0] == ".subckt" {
                         trace!("In subcircuit definition");
 
@@ -317,6 +483,13 @@ impl Reader {
 
                         //self.ckts[0].add_subckt(subckt);
                         in_subckt = true;
+                    } else if bits[0] == ".model" {
+                        if let Some(model) = self.extract_model(&bits) {
+                            self.ckts[self.c].models.push(model);
+                        } else {
+                            println!("*ERROR* can't extract model");
+                            self.there_are_errors = true;
+                        }
                     } else if bits[0] == ".lib" {
 
                         match &readmode {
@@ -379,6 +552,7 @@ impl Reader {
             println!("\nCircuit: {}", ckt.name);
             println!(" Ports: {}", ckt.num_ports);
             ckt.list_parameters();
+            ckt.list_models();
             ckt.list_nodes();
             ckt.list_elements();
             ckt.list_instantiations();
@@ -394,8 +568,10 @@ impl Reader {
         self.there_are_errors
     }
 
-    // only support one parameter per option line
     /// Parse a control block option command
+    ///
+    /// Flags and `<NAME> = <value>` settings can be mixed on one line, e.g.
+    /// `option method=gear maxord=3`
     fn extract_option(&mut self, bits: &[&str]) {
 
         // just 'option' with no arguments? - print the options as they stand
@@ -404,55 +580,312 @@ impl Reader {
             return;
         }
 
-        // FIXME - might be "abstol=2e6" too - lower case, no spaces surrounding '='
-        if bits[2] != "=" {
-            println!("*ERROR* Expected '=' in option setting");
-            return;
+        // might be "method=trap" too, without spaces surrounding '='
+        let text = bits[1..].join(" ").replace('=', " = ");
+        let words: Vec<&str> = text.split_whitespace().collect();
+
+        let mut i = 0;
+        while i < words.len() {
+            if words.get(i + 1) == Some(&"=") {
+                if let Some(value) = words.get(i + 2) {
+                    self.extract_option_setting(words[i], value);
+                } else {
+                    println!("*ERROR* Expected a value for option '{}'", words[i]);
+                    self.there_are_errors = true;
+                }
+                i += 3;
+            } else {
+                // flags
+                match words[i].to_lowercase().as_str() {
+                    "interp" => self.cfg.interp = true,
+                    "nointerp" => self.cfg.interp = false,
+                    "=" => {
+                        println!("*ERROR* Expected a name before '=' in option");
+                        self.there_are_errors = true;
+                    },
+                    _ => println!("*WARN* Ignoring unrecognised option '{}'", words[i]),
+                }
+                i += 1;
+            }
         }
+    }
 
-        match bits[1] {
-            "ABSTOL" => {
-                self.cfg.ABSTOL = extract_value(bits[3]).unwrap();
+    /// Set one `<NAME> = <value>` option
+    fn extract_option_setting(&mut self, name: &str, value: &str) {
+        match name.to_lowercase().as_str() {
+            "abstol" => {
+                if let Some(x) = self.option_value(name, value) {
+                    self.cfg.ABSTOL = x;
+                }
+            },
+            "reltol" => {
+                if let Some(x) = self.option_value(name, value) {
+                    self.cfg.RELTOL = x;
+                }
             },
-            "RELTOL" => {
-                self.cfg.RELTOL = extract_value(bits[3]).unwrap();
+            "rmax" => {
+                if let Some(x) = self.option_value(name, value) {
+                    self.cfg.RMAX = x;
+                }
             },
-            "RMAX" => {
-                self.cfg.RMAX = extract_value(bits[3]).unwrap();
+            "trtol" => {
+                match extract_value(value) {
+                    Some(x) if x > 0.0 => self.cfg.TRTOL = x,
+                    _ => {
+                        println!("*ERROR* trtol must be positive, not '{}'", value);
+                        self.there_are_errors = true;
+                    }
+                }
             },
+            "method" => {
+                self.cfg.method = match value.to_lowercase().as_str() {
+                    "trap" | "trapezoidal" => Method::Trapezoidal,
+                    "euler" => Method::Euler,
+                    "gear" => Method::Gear,
+                    _ => {
+                        println!("*ERROR* unsupported integration method '{}'", value);
+                        self.there_are_errors = true;
+                        return;
+                    }
+                };
+            },
+            "maxord" => {
+                match extract_value(value) {
+                    Some(n) if (2.0..=6.0).contains(&n) && n.fract() == 0.0 => {
+                        self.cfg.maxord = n as usize;
+                    },
+                    _ => {
+                        println!("*ERROR* maxord must be 2 to 6, not '{}'", value);
+                        self.there_are_errors = true;
+                    }
+                }
+            },
+            _ => println!("*WARN* Ignoring unrecognised option '{}'", name),
+        }
+    }
+
+    /// Value of a numeric option, or `None` with an error if it isn't one
+    fn option_value(&mut self, name: &str, value: &str) -> Option<f64> {
+        let x = extract_value(value);
+        if x.is_none() {
+            println!("*ERROR* bad value '{}' for option '{}'", value, name);
+            self.there_are_errors = true;
+        }
+        x
+    }
+
+    /// Parse a DC sweep command
+    ///
+    /// `dc <src> <start> <stop> <incr> [<src2> <start2> <stop2> <incr2>]`
+    ///
+    /// The second source, if there is one, is the outer sweep.
+    fn extract_dc_sweep(&mut self, bits: &[&str]) {
+        if bits.len() != 5 && bits.len() != 9 {
+            println!("*ERROR* expected 'dc <src> <start> <stop> <incr> [<src2> <start2> <stop2> <incr2>]'");
+            self.there_are_errors = true;
+            return;
+        }
+
+        for (i, sweep_bits) in bits[1..].chunks(4).enumerate() {
+            if !sweep_bits[0].starts_with('V') && !sweep_bits[0].starts_with('I') {
+                println!("*ERROR* can only sweep V or I sources, not '{}'", sweep_bits[0]);
+                self.there_are_errors = true;
+                return;
+            }
+
+            let start = extract_value(sweep_bits[1]);
+            let stop = extract_value(sweep_bits[2]);
+            let step = extract_value(sweep_bits[3]);
+            if let (Some(v1), Some(v2), Some(dv)) = (start, stop, step) {
+                if i == 0 {
+                    self.cfg.set_dc_sweep(sweep_bits[0], v1, v2, dv);
+                } else {
+                    self.cfg.set_dc_sweep_outer(sweep_bits[0], v1, v2, dv);
+                }
+            } else {
+                println!("*ERROR* bad values in DC sweep command");
+                self.there_are_errors = true;
+            }
+        }
+    }
+
+    /// Parse a WAV file output command
+    ///
+    /// `wav <file> <signal> [<signal2>] [rate=<sps>] [bits=<n>] [norm]`. Two
+    /// signals make a stereo file. The defaults are 48ksps and 16 bits.
+    fn extract_wav_output(&mut self, bits: &[&str]) {
+        if bits.len() < 3 {
+            println!("*ERROR* expected 'wav <file> <signal> [<signal2>] [rate=<sps>] [bits=<n>] [norm]'");
+            self.there_are_errors = true;
+            return;
+        }
+
+        let mut wav = WavOutput {
+            filename: bits[1].trim_matches('"').to_string(),
+            signals: vec![],
+            rate: 48000,
+            bits: 16,
+            normalise: false,
+        };
+
+        // `v(a, b)` may have been split at the space
+        let text = bits[2..].join(" ").replace(", ", ",");
+        for word in text.split_whitespace() {
+            if word == "norm" {
+                wav.normalise = true;
+            } else if let Some((name, value)) = word.split_once('=') {
+                match (name, extract_value(value)) {
+                    ("rate", Some(v)) if v >= 1.0 => wav.rate = v as u32,
+                    ("bits", Some(v)) if [8.0, 16.0, 24.0, 32.0].contains(&v) => wav.bits = v as u16,
+                    _ => {
+                        println!("*ERROR* bad wav option '{}'", word);
+                        self.there_are_errors = true;
+                    }
+                }
+            } else if let Some(signal) = extract_wav_signal(word) {
+                wav.signals.push(signal);
+            } else {
+                println!("*ERROR* can't write '{}' to a WAV file, expected v(...) or i(...)", word);
+                self.there_are_errors = true;
+            }
+        }
+
+        if wav.signals.is_empty() || wav.signals.len() > 2 {
+            println!("*ERROR* wav needs one signal for mono or two for stereo");
+            self.there_are_errors = true;
+            return;
+        }
+        trace!("*INFO* {:?}", wav);
+        self.cfg.add_wav_output(wav);
+    }
+
+    /// Parse `tran <tstep> <tstop> [<tstart> [<tmax>]] [uic]`, which is
+    /// also the `.tran` dot-card
+    fn extract_tran(&mut self, bits: &[&str]) {
+        let wavefile = Path::new("waves")
+            .join(&self.cfg.ckt_name)
+            .join("tran.dat");
+        self.cfg.set_wavefile(wavefile.to_str().expect("CasdfasDF"));
+
+        let mut values = vec![];
+        for bit in bits.iter().skip(1) {
+            if bit.eq_ignore_ascii_case("uic") {
+                self.cfg.uic = true;
+            } else if let Some(value) = extract_value(bit) {
+                values.push(value);
+            } else {
+                println!("*ERROR* bad value '{}' in '{}'", bit, bits[0]);
+                self.there_are_errors = true;
+                return;
+            }
+        }
+
+        if values.len() < 2 || values.len() > 4 {
+            println!("*ERROR* expected '{} <tstep> <tstop> [<tstart> [<tmax>]] [uic]'", bits[0]);
+            self.there_are_errors = true;
+            return;
+        }
+        let tstart = values.get(2).copied().unwrap_or(0.0);
+        self.cfg.set_transient(values[1], values[0], tstart);
+        if let Some(&tmax) = values.get(3) {
+            if tmax <= 0.0 {
+                println!("*ERROR* tmax must be greater than zero in '{}'", bits[0]);
+                self.there_are_errors = true;
+                return;
+            }
+            self.cfg.TMAX = Some(tmax);
+        }
+    }
+
+    /// Parse an AC analysis command
+    ///
+    /// `ac dec|oct|lin <points> <fstart> <fstop>`
+    fn extract_ac(&mut self, bits: &[&str]) {
+        if bits.len() != 5 {
+            println!("*ERROR* expected 'ac dec|oct|lin <points> <fstart> <fstop>'");
+            self.there_are_errors = true;
+            return;
+        }
+
+        let sweep = match bits[1] {
+            "dec" => AcSweep::Decade,
+            "oct" => AcSweep::Octave,
+            "lin" => AcSweep::Linear,
             _ => {
+                println!("*ERROR* unrecognised AC sweep type '{}'", bits[1]);
+                self.there_are_errors = true;
+                return;
             }
+        };
+
+        let npoints = extract_value(bits[2]);
+        let fstart = extract_value(bits[3]);
+        let fstop = extract_value(bits[4]);
+        if let (Some(np), Some(f1), Some(f2)) = (npoints, fstart, fstop) {
+            self.cfg.set_ac(sweep, np as usize, f1, f2);
+        } else {
+            println!("*ERROR* bad values in AC command");
+            self.there_are_errors = true;
         }
     }
 
+    /// Parse the value part of a DC source, with an optional AC spec
+    ///
+    /// Handles `<value>`, `DC <value>`, `AC <mag> [<phase>]` and any
+    /// combination of them. Returns `(dc_value, ac_mag, ac_phase)`.
+    fn extract_dc_ac(&mut self, bits: &[&str]) -> (f64, f64, f64) {
+        let mut value = 0.0;
+        let mut ac_mag = 0.0;
+        let mut ac_phase = 0.0;
+
+        let mut i = 0;
+        while i < bits.len() {
+            match bits[i] {
+                "DC" | "dc" => {
+                    i += 1;
+                    if let Some(v) = bits.get(i).and_then(|b| extract_value(b)) {
+                        value = v;
+                    } else {
+                        println!("*ERROR* expected a value after 'DC'");
+                        self.there_are_errors = true;
+                    }
+                },
+                "AC" | "ac" => {
+                    i += 1;
+                    if let Some(v) = bits.get(i).and_then(|b| extract_value(b)) {
+                        ac_mag = v;
+                    } else {
+                        println!("*ERROR* expected a magnitude after 'AC'");
+                        self.there_are_errors = true;
+                    }
+                    // phase is optional
+                    if let Some(v) = bits.get(i+1).and_then(|b| extract_value(b)) {
+                        ac_phase = v;
+                        i += 1;
+                    }
+                },
+                txt => {
+                    if let Some(v) = extract_value(txt) {
+                        value = v;
+                    } else {
+                        println!("*ERROR* Unrecognised source value '{}'", txt);
+                        self.there_are_errors = true;
+                    }
+                }
+            }
+            i += 1;
+        }
+        trace!("*INFO* DC {} AC {} {}", value, ac_mag, ac_phase);
+        (value, ac_mag, ac_phase)
+    }
+
     /// Parse a Sine Current Source description from SPICE
     fn extract_i_sine(&mut self, bits: &[&str]) -> CurrentSourceSine {
         let _ = extract_identifier(bits[0]);
         let node1 = self.extract_node(bits[1]);
         let node2 = self.extract_node(bits[2]);
-
-        // ugly stuff...
-        // push all the remaining bits of the SPICE line into 1 string
-        let mut line = "".to_string();
-        for b in bits[3..].iter() {
-            line += *b;
-            line.push(' ');
-        }
-
-        // then when we remove "SIN" "(" and ")" we should be left with
-        // some numbers that we can extract
-        line = line.replace("SIN", "");
-        line = line.replace('(', "");
-        line = line.replace(')', "");
-
-        let all_bits :Vec<&str> = line.split_whitespace().collect();
-        if all_bits.len() != 3 {
-            println!("*ERROR* not enough parameters to SIN()");
-        }
-        let offset = extract_value(all_bits[0]).unwrap();
-        let amplitude = extract_value(all_bits[1]).unwrap();
-        let frequency = extract_value(all_bits[2]).unwrap();
-        trace!("*INFO* ISIN {} {} {}", offset, amplitude, frequency);
+        let [offset, amplitude, frequency, delay, theta, phase] = self.extract_sine_values(bits);
+        trace!("*INFO* ISIN {} {} {} {} {} {}", offset, amplitude, frequency, delay, theta, phase);
 
         CurrentSourceSine {
             p: node1,
@@ -460,130 +893,244 @@ impl Reader {
             vo: offset,
             va: amplitude,
             freq: frequency,
+            td: delay,
+            theta,
+            phase,
         }
     }
 
     // extract the stuff from SIN()
     fn extract_v_sine(&mut self, bits: &[&str]) -> VoltageSourceSine {
-        let _ = extract_identifier(bits[0]);
+        let ident = extract_identifier(bits[0]);
         let node1 = self.extract_node(bits[1]);
         let node2 = self.extract_node(bits[2]);
-
-        // ugly stuff...
-        // push all the remaining bits of the SPICE line into 1 string
-        let mut line = "".to_string();
-        for b in bits[3..].iter() {
-            line += *b;
-            line.push(' ');
-        }
-
-        // then when we remove "SIN" "(" and ")" we should be left with
-        // some numbers that we can extract
-        line = line.replace("SIN", "");
-        line = line.replace('(', "");
-        line = line.replace(')', "");
-
-        let all_bits :Vec<&str> = line.split_whitespace().collect();
-        if all_bits.len() != 3 {
-            println!("*ERROR* not enough parameters to SIN()");
-        }
-        let offset = extract_value(all_bits[0]).unwrap();
-        let amplitude = extract_value(all_bits[1]).unwrap();
-        let frequency = extract_value(all_bits[2]).unwrap();
-        trace!("*INFO* VSIN {} {} {}", offset, amplitude, frequency);
+        let [offset, amplitude, frequency, delay, theta, phase] = self.extract_sine_values(bits);
+        trace!("*INFO* VSIN {} {} {} {} {} {}", offset, amplitude, frequency, delay, theta, phase);
 
         VoltageSourceSine {
+            ident,
             p: node1,
             n: node2,
             vo: offset,
             va: amplitude,
             freq: frequency,
+            td: delay,
+            theta,
+            phase,
             idx: 0
         }
     }
 
+    /// Numbers in `SIN(vo va freq [td theta phase])`
+    ///
+    /// The delay, damping factor and phase default to 0. The numbers can be
+    /// separated by spaces or commas.
+    fn extract_sine_values(&mut self, bits: &[&str]) -> [f64; 6] {
+        let text = bits[3..].join(" ")
+            .replace("SIN", "")
+            .replace(['(', ')', ','], " ");
+
+        let mut vals = [0.0; 6];
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.len() < 3 || words.len() > 6 {
+            println!("*ERROR* expected 3 to 6 parameters to SIN() on '{}'", bits[0]);
+            self.there_are_errors = true;
+        }
+        for (val, word) in vals.iter_mut().zip(&words) {
+            if let Some(v) = extract_value(word) {
+                *val = v;
+            } else {
+                println!("*ERROR* bad value '{}' in SIN() on '{}'", word, bits[0]);
+                self.there_are_errors = true;
+            }
+        }
+        vals
+    }
+
 
-    /// extract the stuff from PWL
-    fn extract_v_pwl(&mut self, bits: &[&str]) -> VoltageSourcePwl {
-        let _ = extract_identifier(bits[0]);
-        let node1 = self.extract_node(bits[1]);
-        let node2 = self.extract_node(bits[2]);
+    /// Extract a waveform for a `V` or `I` source: `PULSE(...)`, `EXP(...)`,
+    /// `SFFM(...)`, `AM(...)`, `PWL(...)` or `WAV(...)`
+    ///
+    /// The numbers can be separated by spaces or commas. `filepath` is the
+    /// deck the source is in, for finding `PWL FILE=`s.
+    fn extract_waveform(&mut self, bits: &[&str], filepath: &Path) -> Option<Waveform> {
+        if bits[3].to_uppercase().starts_with("PWL") {
+            return self.extract_pwl(bits, filepath);
+        } else if bits[3].to_uppercase().starts_with("WAV") {
+            return self.extract_wav(bits, filepath);
+        }
+        let text = bits[3..].join(" ")
+            .replace(['(', ')', ','], " ");
+        let words: Vec<&str> = text.split_whitespace().collect();
+
+        let mut vals = vec![];
+        for word in &words[1..] {
+            if let Some(v) = extract_value(word) {
+                vals.push(v);
+            } else {
+                println!("*ERROR* bad value '{}' in {}() on '{}'", word, words[0], bits[0]);
+                self.there_are_errors = true;
+                return None
+            }
+        }
 
-        let mut src = VoltageSourcePwl {
-            p: node1,
-            n: node2,
-            pat: vec![],
-            repeat: -1.0,
-            t_delay: 0.0,
-            idx: 0
+        let wave = match words[0].to_uppercase().as_str() {
+            "PULSE" => Pulse::from_values(&vals).map(Waveform::Pulse),
+            "EXP" => Exponential::from_values(&vals).map(Waveform::Exp),
+            "SFFM" => Sffm::from_values(&vals).map(Waveform::Sffm),
+            "AM" => Am::from_values(&vals).map(Waveform::Am),
+            _ => None,
         };
+        if wave.is_none() {
+            println!("*ERROR* can't understand {}() on '{}'", words[0], bits[0]);
+            self.there_are_errors = true;
+        }
+        trace!("*INFO* {:?}", wave);
+        wave
+    }
+
+    /// Extract a piecewise linear waveform
+    ///
+    /// `PWL(<t1> <v1> <t2> <v2> ...) [td=<delay>] [r=<time>]`, or
+    /// `PWL FILE=<filename> [td=<delay>] [r=<time>]` where the file has a
+    /// time and a value on each line. The file is relative to the deck.
+    fn extract_pwl(&mut self, bits: &[&str], filepath: &Path) -> Option<Waveform> {
 
         // work from the end and pick off any variables:
-        // in this case, delay and repeat time
-        let mut n_params = 0;
-        for i in (0..bits.len()).rev() {
-            if bits[i].contains('=') {
-                n_params += 1;
-                if let Some(param) = self.extract_override(bits[i]) {
+        // in this case, delay, repeat time and the file
+        let mut td = 0.0;
+        let mut repeat = None;
+        let mut filename = None;
+        let mut end = bits.len();
+        while end > 3 && bits[end - 1].contains('=') {
+            end -= 1;
+            let (name, value) = bits[end].split_once('=').unwrap();
+            let name = name.to_lowercase();
+            if name == "file" {
+                filename = Some(value.trim_matches('"').to_string());
+                continue;
+            }
+            match (name.as_str(), extract_value(value)) {
+                ("td", Some(v)) => td = v,
+                ("r", Some(v)) => repeat = Some(v),
+                _ => {
+                    println!("*ERROR* unrecognised PWL parameter '{}' on '{}'", bits[end], bits[0]);
+                    self.there_are_errors = true;
+                    return None
+                }
+            }
+        }
 
-                    // eugh... assume the parameter value is a literal.
-                    let val = if let Some(Literal(v)) = param.expr {
-                        v
-                    } else {
-                        0.0
-                    };
+        let text = if let Some(filename) = filename {
+            let pwl_path = look_for_file(filepath, &filename).unwrap_or_default();
+            if let Ok(contents) = std::fs::read_to_string(&pwl_path) {
+                // skip comments
+                contents.lines()
+                    .filter(|l| !l.starts_with('*') && !l.starts_with('#'))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            } else {
+                println!("*ERROR* can't read PWL file '{}' on '{}'", pwl_path.display(), bits[0]);
+                self.there_are_errors = true;
+                return None
+            }
+        } else {
+            // drop the "PWL"
+            bits[3..end].join(" ")[3..].to_string()
+        };
+        let text = text.replace(['(', ')', ','], " ");
 
-                    match param.name.as_str() {
-                        "r" => { src.repeat = val},
-                        "td" => { src.t_delay = val},
-                        _ => {
-                            println!("*WARNING* unrecogised PWL parameter '{}'",
-                                param.name);
-                        }
-                    }
-                } else {
-                    println!("*WARNING* something bad in VPWL");
-                }
+        let mut vals = vec![];
+        for word in text.split_whitespace() {
+            if let Some(v) = extract_value(word) {
+                vals.push(v);
             } else {
-                break;
+                println!("*ERROR* bad value '{}' in PWL on '{}'", word, bits[0]);
+                self.there_are_errors = true;
+                return None
             }
         }
-
-        // ugly stuff...
-        // push all the remaining bits of the SPICE line into 1 string
-        let mut line = "".to_string();
-        let end = bits.len() - n_params;
-        for b in bits[3..end].iter() {
-            line += *b;
-            line.push(' ');
+        if vals.len() % 2 != 0 {
+            println!("*ERROR* unmatched time-value pair in PWL on '{}'", bits[0]);
+            self.there_are_errors = true;
+            return None
         }
 
-        // then when we remove "PWL" "(" and ")" we should be left with
-        // some numbers that we can extract
-        line = line.replace("PWL", "");
-        line = line.replace('(', "");
-        line = line.replace(')', "");
-        //trace!("Line: {}", line);
-        //println!("Line: {}", line);
+        let points = vals.chunks(2).map(|tv| (tv[0], tv[1])).collect();
+        match Pwl::new(points, td, repeat) {
+            Ok(pwl) => {
+                trace!("*INFO* {:?}", pwl);
+                Some(Waveform::Pwl(pwl))
+            }
+            Err(msg) => {
+                println!("*ERROR* {} in PWL on '{}'", msg, bits[0]);
+                self.there_are_errors = true;
+                None
+            }
+        }
+    }
 
-        let all_bits: Vec<&str> = line.split(',').collect();
 
-        let mut tvs: Vec<(f64, f64)> = vec![];
+    /// Extract a waveform from one channel of a WAV file
+    ///
+    /// `WAV(file=<filename> [chan=<n>] [gain=<value>] [td=<delay>])`. The
+    /// file is relative to the deck.
+    fn extract_wav(&mut self, bits: &[&str], filepath: &Path) -> Option<Waveform> {
+        // drop the "WAV"
+        let text = bits[3..].join(" ")[3..].replace(['(', ')', ','], " ");
+
+        let mut filename = None;
+        let mut chan = 0;
+        let mut gain = 1.0;
+        let mut td = 0.0;
+        for word in text.split_whitespace() {
+            let (name, value) = word.split_once('=').unwrap_or((word, ""));
+            let name = name.to_lowercase();
+            if name == "file" {
+                filename = Some(value.trim_matches('"').to_string());
+                continue;
+            }
+            match (name.as_str(), extract_value(value)) {
+                ("chan", Some(v)) if v >= 0.0 => chan = v as usize,
+                ("gain", Some(v)) => gain = v,
+                ("td", Some(v)) => td = v,
+                _ => {
+                    println!("*ERROR* unrecognised WAV parameter '{}' on '{}'", word, bits[0]);
+                    self.there_are_errors = true;
+                    return None
+                }
+            }
+        }
 
-        for tv_pair in all_bits.chunks(2) {
-            if tv_pair.len() == 1 {
-                println!("*WARNING* Unmatched time value: {}", tv_pair[0]);
+        let Some(filename) = filename else {
+            println!("*ERROR* no 'file=' in WAV on '{}'", bits[0]);
+            self.there_are_errors = true;
+            return None
+        };
+        let wav_path = look_for_file(filepath, &filename).unwrap_or_default();
+        let mut data = match wavfile::read(&wav_path) {
+            Ok(data) => data,
+            Err(msg) => {
+                println!("*ERROR* {} in WAV on '{}'", msg, bits[0]);
+                self.there_are_errors = true;
+                return None
             }
-            let t = extract_value(tv_pair[0].trim()).unwrap();
-            let v = extract_value(tv_pair[1].trim()).unwrap();
-            tvs.push((t,v));
-            trace!("time: {:?}, value: {:?}", t, v);
+        };
+        if chan >= data.channels.len() {
+            println!("*ERROR* '{}' has no channel {} for '{}'", filename, chan, bits[0]);
+            self.there_are_errors = true;
+            return None
         }
-        src.pat = tvs;
 
-        //trace!("*INFO* VPWL {} {} {}", offset, amplitude, frequency);
-        trace!("{:?}", src);
-        src
+        println!("*INFO* Read {} samples at {} Hz from '{}' for '{}'",
+            data.channels[chan].len(), data.sample_rate, wav_path.display(), bits[0]);
+        Some(Waveform::Wav(Wav {
+            filename,
+            samples: data.channels.swap_remove(chan),
+            sample_rate: data.sample_rate as f64,
+            gain,
+            td,
+        }))
     }
 
 
@@ -705,6 +1252,60 @@ impl Reader {
         }
     }
 
+    /// Pull an `IC=<value>` off the end of a capacitor or inductor
+    ///
+    /// Returns the rest of the bits, and the initial condition as a `/IC`
+    /// parameter so it can be a bracket expression like the value.
+    fn extract_element_ic<'a>(&mut self, bits: &[&'a str]) -> (Vec<&'a str>, Option<Parameter>) {
+        let mut rest = vec![];
+        let mut ic = None;
+        for bit in bits {
+            if bit.len() > 3 && bit[..3].eq_ignore_ascii_case("IC=") {
+                if let Some(mut param) = self.extract_override(bit) {
+                    param.name = "/IC".to_string();
+                    ic = Some(param);
+                }
+            } else {
+                rest.push(*bit);
+            }
+        }
+        (rest, ic)
+    }
+
+    /// Extract node voltages for `.ic` and `.nodeset`:
+    /// `<card> v(<node>)=<value> ...`
+    ///
+    /// Nodes can be hierarchical, e.g. `v(Xsystem1.soutp)=2`. They're
+    /// looked up once the circuit is expanded.
+    fn extract_node_voltages(&mut self, bits: &[&str]) -> Vec<(String, f64)> {
+        let text = bits[1..].join(" ").replace('=', " = ");
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.is_empty() || !words.len().is_multiple_of(3) {
+            println!("*ERROR* expected '{} v(<node>)=<value> ...'", bits[0]);
+            self.there_are_errors = true;
+            return vec![];
+        }
+
+        let mut voltages = vec![];
+        for nv in words.chunks(3) {
+            let node = match extract_wav_signal(nv[0]) {
+                Some(WavSignal::Voltage(node, None)) if nv[1] == "=" => node,
+                _ => {
+                    println!("*ERROR* expected 'v(<node>)=<value>', not '{}'", nv.join(""));
+                    self.there_are_errors = true;
+                    continue;
+                }
+            };
+            if let Some(value) = extract_value(nv[2]) {
+                voltages.push((node, value));
+            } else {
+                println!("*ERROR* bad value '{}' for v({}) in {}", nv[2], node, bits[0]);
+                self.there_are_errors = true;
+            }
+        }
+        voltages
+    }
+
     /// Extract an instantiation of a device primitive
     pub fn extract_primitive(
         &mut self,
@@ -750,6 +1351,269 @@ impl Reader {
     }
 
 
+    /// Extract an instantiation of a device that takes a `.model`
+    ///
+    /// `<ident> <node1> ... <nodeN> <model> [<NAME>=<value> ...]`
+    ///
+    /// Named parameters are stored as `/<NAME>`, so they can be bracket
+    /// expressions like the values of other primitives.
+    pub fn extract_device(&mut self, bits: &[&str], num_ports: usize)
+        -> Option<Instance>
+    {
+        trace!("extracting device with a model");
+
+        if bits.len() < (1 + num_ports + 1) {
+            println!("*ERROR* not enough bits");
+            return None
+        }
+
+        let ident = extract_identifier(bits[0]);
+        let mut inst = Instance::new(&ident, r"/device");
+
+        for bit in bits.iter().skip(1).take(num_ports) {
+            let node = self.extract_node(bit);
+            inst.conns.push(node);
+        }
+
+        inst.model = Some(bits[1 + num_ports].to_string());
+
+        for param_text in bits.iter().skip(2 + num_ports) {
+            if let Some(mut param) = self.extract_override(param_text) {
+                param.name = format!("/{}", param.name.to_uppercase());
+                inst.params.push(param);
+            } else {
+                println!("*ERROR* can't understand '{}' on '{}'", param_text, ident);
+                return None
+            }
+        }
+
+        trace!("Device: {}", inst);
+
+        Some(inst)
+    }
+
+    /// Extract a device model: `.model <name> <kind> (<PARAM>=<value> ...)`
+    ///
+    /// The brackets are optional, and there can be spaces around the `=`.
+    fn extract_model(&mut self, bits: &[&str]) -> Option<Model> {
+        trace!("extracting model");
+
+        if bits.len() < 3 {
+            println!("*ERROR* not enough bits");
+            return None
+        }
+
+        let name = bits[1];
+        let text = bits[2..].join(" ")
+            .replace(['(', ')'], " ")
+            .replace('=', " = ");
+        let words: Vec<&str> = text.split_whitespace().collect();
+        if words.is_empty() {
+            println!("*ERROR* no model type for '{}'", name);
+            return None
+        }
+
+        let kind = match ModelKind::from_spice(words[0]) {
+            Some(kind) => kind,
+            None => {
+                println!("*ERROR* unsupported model type '{}' for '{}'", words[0], name);
+                return None
+            }
+        };
+        let mut model = Model::new(name, kind);
+
+        // what's left should be `<PARAM> = <value>` triples
+        if !(words.len() - 1).is_multiple_of(3) {
+            println!("*ERROR* model parameters for '{}' don't look like NAME=value", name);
+            return None
+        }
+        for chunk in words[1..].chunks(3) {
+            if chunk[1] != "=" {
+                println!("*ERROR* expected '=' after '{}' in model '{}'", chunk[0], name);
+                return None
+            }
+            if let Some(value) = extract_value(chunk[2]) {
+                model.params.push((chunk[0].to_uppercase(), value));
+            } else {
+                println!("*ERROR* bad value '{}' for '{}' in model '{}'",
+                    chunk[2], chunk[0], name);
+                return None
+            }
+        }
+
+        trace!("{}", model);
+        Some(model)
+    }
+
+    /// Extract a mutual inductance: `K<ident> <L1> <L2> <k>`
+    ///
+    /// The inductors are referred to by name, and are only looked up once
+    /// the circuit is expanded.
+    fn extract_mutual(&mut self, bits: &[&str]) -> Option<Instance> {
+        trace!("extracting mutual inductance");
+
+        if bits.len() < 4 {
+            println!("*ERROR* not enough bits");
+            return None
+        }
+
+        let ident = extract_identifier(bits[0]);
+        let mut inst = Instance::new(&ident, r"/device");
+
+        for lname in &bits[1..3] {
+            if !lname.starts_with('L') {
+                println!("*ERROR* '{}' couples '{}', which is not an inductor",
+                    ident, lname);
+                return None
+            }
+            inst.refs.push(lname.to_string());
+        }
+
+        if let Some(expr) = extract_expression(bits[3]) {
+            let param = Parameter::override_from_expression("/param0", &expr);
+            inst.params.push(param);
+        } else {
+            println!("*ERROR* can't read coupling factor '{}'", bits[3]);
+            return None
+        }
+
+        trace!("Mutual: {}", inst);
+
+        Some(inst)
+    }
+
+    /// Extract a current-controlled source: `F|H<ident> <n+> <n-> <Vsrc> <gain>`
+    ///
+    /// The controlling voltage source is referred to by name, and is only
+    /// looked up once the circuit is expanded.
+    fn extract_current_controlled(&mut self, bits: &[&str]) -> Option<Instance> {
+        trace!("extracting current-controlled source");
+
+        if bits.len() < 5 {
+            println!("*ERROR* not enough bits");
+            return None
+        }
+
+        let ident = extract_identifier(bits[0]);
+        let mut inst = Instance::new(&ident, r"/device");
+
+        for bit in &bits[1..3] {
+            let node = self.extract_node(bit);
+            inst.conns.push(node);
+        }
+
+        if !bits[3].starts_with('V') {
+            println!("*ERROR* '{}' is controlled by '{}', which is not a voltage source",
+                ident, bits[3]);
+            return None
+        }
+        inst.refs.push(bits[3].to_string());
+
+        if let Some(expr) = extract_expression(bits[4]) {
+            let param = Parameter::override_from_expression("/param0", &expr);
+            inst.params.push(param);
+        } else {
+            println!("*ERROR* can't read gain '{}'", bits[4]);
+            return None
+        }
+
+        trace!("Current-controlled: {}", inst);
+
+        Some(inst)
+    }
+
+    /// Extract a switch:
+    ///
+    /// * `S<ident> <n+> <n-> <nc+> <nc-> <model> [ON|OFF]`
+    /// * `W<ident> <n+> <n-> <Vsrc> <model> [ON|OFF]`
+    ///
+    /// Switches start off unless `ON` is given.
+    fn extract_switch(&mut self, bits: &[&str]) -> Option<Instance> {
+        trace!("extracting switch");
+
+        let mut bits = bits.to_vec();
+        let on = match bits.last().map(|b| b.to_uppercase()) {
+            Some(ref b) if b == "ON" => { bits.pop(); true },
+            Some(ref b) if b == "OFF" => { bits.pop(); false },
+            _ => false,
+        };
+
+        let mut inst = if bits[0].starts_with('S') {
+            self.extract_device(&bits, 4)?
+        } else {
+            if bits.len() != 5 {
+                println!("*ERROR* expected 'W<ident> <n+> <n-> <Vsrc> <model>'");
+                return None
+            }
+            let ident = extract_identifier(bits[0]);
+            if !bits[3].starts_with('V') {
+                println!("*ERROR* '{}' is controlled by '{}', which is not a voltage source",
+                    ident, bits[3]);
+                return None
+            }
+            let mut inst = Instance::new(&ident, r"/device");
+            for bit in &bits[1..3] {
+                let node = self.extract_node(bit);
+                inst.conns.push(node);
+            }
+            inst.refs.push(bits[3].to_string());
+            inst.model = Some(bits[4].to_string());
+            inst
+        };
+
+        if on {
+            let param = Parameter::override_from_expression("/ON", &Literal(1.0));
+            inst.params.push(param);
+        }
+
+        trace!("Switch: {}", inst);
+
+        Some(inst)
+    }
+
+    /// Extract a behavioural source: `B<ident> <n+> <n-> V|I={<expr>}`
+    ///
+    /// The expression can have spaces in it. It's checked here, but the
+    /// nodes and sources it uses are only looked up once the circuit is
+    /// expanded.
+    fn extract_behavioural(&mut self, bits: &[&str]) -> Option<Instance> {
+        trace!("extracting behavioural source");
+
+        if bits.len() < 4 {
+            println!("*ERROR* not enough bits");
+            return None
+        }
+
+        let ident = extract_identifier(bits[0]);
+        let mut inst = Instance::new(&ident, r"/device");
+
+        for bit in &bits[1..3] {
+            let node = self.extract_node(bit);
+            inst.conns.push(node);
+        }
+
+        let text = bits[3..].join(" ");
+        let Some((kind, expr)) = text.split_once('=') else {
+            println!("*ERROR* expected 'V=' or 'I=' on '{}'", ident);
+            return None
+        };
+        let kind = kind.trim().to_uppercase();
+        if kind != "V" && kind != "I" {
+            println!("*ERROR* expected 'V=' or 'I=' on '{}', not '{}='", ident, kind);
+            return None
+        }
+
+        let mut expr = expr.trim();
+        if let Some(inner) = expr.strip_prefix('{').and_then(|e| e.strip_suffix('}')) {
+            expr = inner;
+        }
+        parse_expression(expr)?;
+        inst.behaviour = Some(format!("{}={}", kind, expr));
+
+        trace!("Behavioural: {}", inst);
+
+        Some(inst)
+    }
 
     /// Return a circuit that is the expansion of the toplevel circuit
     ///
@@ -773,6 +1637,24 @@ fn extract_identifier(text: &str) -> String {
     text.to_string()
 }
 
+/// Signal for a WAV file: `v(<node>)`, `v(<node1>,<node2>)` or `i(<ident>)`
+fn extract_wav_signal(text: &str) -> Option<WavSignal> {
+    let (kind, rest) = text.split_once('(')?;
+    let inner = rest.strip_suffix(')')?;
+    match (kind, inner.split_once(',')) {
+        ("v" | "V", Some((p, n))) => Some(WavSignal::Voltage(p.to_string(), Some(n.to_string()))),
+        ("v" | "V", None) => Some(WavSignal::Voltage(inner.to_string(), None)),
+        ("i" | "I", None) => Some(WavSignal::Current(inner.to_string())),
+        _ => None,
+    }
+}
+
+/// Does a source value start with one of the waveforms in `Waveform`?
+fn is_waveform(text: &str) -> bool {
+    let name = text.split('(').next().unwrap_or("").to_uppercase();
+    matches!(name.as_str(), "PULSE" | "EXP" | "SFFM" | "AM" | "PWL" | "WAV")
+}
+
 
 /// Look around for an include or library file.
 ///
@@ -943,5 +1825,57 @@ mod tests {
         assert_eq!(ckt.node_id_lut.len(), 4);
     }
 
+    #[test]
+    fn bjt_models() {
+        let mut rdr = Reader::new();
+        rdr.read(Path::new("./ngspice/bjt_pnp_ce.spi"));
+        assert_eq!(rdr.ckts[0].models.len(), 1);
+
+        let model = rdr.ckts[0].find_model("qp").unwrap();
+        assert_eq!(model.kind, ModelKind::Pnp);
+        assert_nearly(model.get("IS").unwrap(), 1e-16);
+        assert_nearly(model.get_or("BF", 1.0), 100.0);
+        assert_nearly(model.get_or("VAF", 0.0), 0.0);
+
+        assert_eq!(rdr.ckts[0].instances[1].model, Some("QP".to_string()));
+    }
+
+    #[test]
+    fn option_method() {
+        let mut rdr = Reader::new();
+        assert_eq!(rdr.cfg.method, Method::Trapezoidal);
+        rdr.extract_option(&["option", "method=euler"]);
+        assert_eq!(rdr.cfg.method, Method::Euler);
+        rdr.extract_option(&["option", "method", "=", "trap"]);
+        assert_eq!(rdr.cfg.method, Method::Trapezoidal);
+        rdr.extract_option(&["option", "RMAX=10", "reltol=1m"]);
+        assert_nearly(rdr.cfg.RMAX, 10.0);
+        assert_nearly(rdr.cfg.RELTOL, 1e-3);
+        assert!(!rdr.there_are_errors);
+        rdr.extract_option(&["option", "RMAX=abc"]);
+        assert!(rdr.there_are_errors);
+        assert_nearly(rdr.cfg.RMAX, 10.0);
+        rdr.there_are_errors = false;
+        rdr.extract_option(&["option", "method=gear", "maxord", "=", "5", "interp"]);
+        assert_eq!(rdr.cfg.method, Method::Gear);
+        assert_eq!(rdr.cfg.maxord, 5);
+        assert!(rdr.cfg.interp);
+        assert!(!rdr.there_are_errors);
+        rdr.extract_option(&["option", "maxord=7"]);
+        assert!(rdr.there_are_errors);
+        rdr.there_are_errors = false;
+        rdr.extract_option(&["option", "method=simpson"]);
+        assert!(rdr.there_are_errors);
+    }
 
+    #[test]
+    fn option_trtol() {
+        let mut rdr = Reader::new();
+        assert_nearly(rdr.cfg.TRTOL, 7.0);
+        rdr.extract_option(&["option", "trtol=1"]);
+        assert_nearly(rdr.cfg.TRTOL, 1.0);
+        assert!(!rdr.there_are_errors);
+        rdr.extract_option(&["option", "trtol=0"]);
+        assert!(rdr.there_are_errors);
+    }
 }
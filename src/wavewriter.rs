@@ -14,6 +14,35 @@ use circuit::NodeId;
 pub struct WaveWriter<'hash>{
     file: Option<File>,
     lut: &'hash HashMap<NodeId, String>,
+    branch_lut: Option<&'hash HashMap<usize, String>>,
+    grid: Option<Grid>,
+}
+
+/// Output time grid, and the last vector dumped for interpolating from
+struct Grid {
+    t_start: f64,
+    t_step: f64,
+    k: usize,
+    last: Option<(f64, Vec<f64>)>,
+}
+
+impl<'hash> WaveWriter<'hash> {
+
+    /// Name the branch current columns after their elements
+    ///
+    /// Without this, branch currents are named by their index.
+    pub fn set_branch_lut(&mut self, branch_lut: &'hash HashMap<usize, String>) {
+        self.branch_lut = Some(branch_lut);
+    }
+
+    /// Only write rows at `t_start + k * t_step`
+    ///
+    /// The vectors passed to `dump_vector()` are linearly interpolated onto
+    /// the grid. Without this, every vector is written as it comes.
+    pub fn set_grid(&mut self, t_start: f64, t_step: f64) {
+        self.grid = Some(Grid { t_start, t_step, k: 0, last: None });
+    }
+
 }
 
 impl WaveWriter<'_> {
@@ -34,6 +63,8 @@ impl WaveWriter<'_> {
         let mut writer = WaveWriter {
             file: None,
             lut,
+            branch_lut: None,
+            grid: None,
         };
 
         // open the path to write
@@ -52,21 +83,35 @@ impl WaveWriter<'_> {
         Some(writer)
     }
 
+    fn branch_name(&self, idx: usize) -> String {
+        match self.branch_lut.and_then(|lut| lut.get(&idx)) {
+            Some(name) => name.to_string(),
+            None => format!("{}", idx),
+        }
+    }
+
 
     pub fn header(&mut self, c_nodes: usize, c_vsrcs: usize) {
+        self.header_cols(&[("Time", "s")], c_nodes, c_vsrcs);
+    }
+
+    /// Header with different leading columns, e.g. for a DC sweep
+    ///
+    /// `cols` is a list of `(name, unit)`. The first one is the X-axis.
+    pub fn header_cols(&mut self, cols: &[(&str, &str)], c_nodes: usize, c_vsrcs: usize) {
 
-        let mut names = "Time".to_string();
+        let mut names = cols.iter().map(|c| c.0).collect::<Vec<_>>().join("\t");
 
         for i in 0..c_nodes {
             let name = &self.lut[&i];
             names += &format!("\tv({})", name);
         }
         for j in 0..c_vsrcs {
-            names += &format!("\ti({})", j);
+            names += &format!("\ti({})", self.branch_name(j));
         }
         names += "\n";
 
-        let mut units = "s".to_string();
+        let mut units = cols.iter().map(|c| c.1).collect::<Vec<_>>().join("\t");
         for _ in 0..c_nodes {
             units += "\tV";
         }
@@ -81,9 +126,81 @@ impl WaveWriter<'_> {
         }
     }
 
+    /// Header for AC analysis results
+    ///
+    /// Each node voltage and branch current gets a magnitude and a phase
+    /// column.
+    pub fn header_ac(&mut self, c_nodes: usize, c_vsrcs: usize) {
+
+        let mut names = "Freq".to_string();
+
+        for i in 0..c_nodes {
+            let name = &self.lut[&i];
+            names += &format!("\tvm({})\tvp({})", name, name);
+        }
+        for j in 0..c_vsrcs {
+            let name = self.branch_name(j);
+            names += &format!("\tim({})\tip({})", name, name);
+        }
+        names += "\n";
+
+        let mut units = "Hz".to_string();
+        for _ in 0..c_nodes {
+            units += "\tV\tdeg";
+        }
+        for _ in 0..c_vsrcs {
+            units += "\tA\tdeg";
+        }
+        units += "\n";
+
+        if let Some(ref mut file) = self.file {
+            let _ = file.write_all(names.as_bytes());
+            let _ = file.write_all(units.as_bytes());
+        }
+    }
+
     pub fn dump_vector(&mut self, time: f64, vars: &[f64]) {
+        if let Some(mut grid) = self.grid.take() {
+            // write all the grid points up to here, allowing for a little
+            // floating point slop in landing on the last one
+            loop {
+                let t_grid = grid.t_start + grid.k as f64 * grid.t_step;
+                if t_grid > time + grid.t_step * 1e-6 {
+                    break;
+                }
+                match grid.last {
+                    Some((t_last, ref last)) if t_grid < time && t_grid > t_last => {
+                        let frac = (t_grid - t_last) / (time - t_last);
+                        let row: Vec<f64> = last.iter().zip(vars)
+                            .map(|(x1, x2)| x1 + (x2 - x1) * frac)
+                            .collect();
+                        self.write_row(t_grid, &row);
+                    },
+                    // before the first vector, or on this one
+                    _ => self.write_row(t_grid, vars),
+                }
+                grid.k += 1;
+            }
+            grid.last = Some((time, vars.to_vec()));
+            self.grid = Some(grid);
+        } else {
+            self.write_row(time, vars);
+        }
+    }
+
+    /// Start a new block of rows, e.g. for the next value of an outer sweep
+    ///
+    /// Blocks are separated by a blank line.
+    pub fn new_block(&mut self) {
+        if let Some(ref mut file) = self.file {
+            let _ = file.write_all(b"\n");
+        }
+    }
+
+    fn write_row(&mut self, time: f64, vars: &[f64]) {
         if let Some(ref mut file) = self.file {
-            let mut line = format!("{:0.9}", time);
+            // timepoints come from the truncation error, so can land anywhere
+            let mut line = format!("{:0.15}", time);
 
             for var in vars {
                 let bit = format!("\t{:0.9}", var);
@@ -0,0 +1,220 @@
+//! WAV Audio Files
+//!
+//! Read the samples out of a RIFF WAV file, so real audio can drive a
+//! circuit, and write simulation results out to one so they can be played.
+//! PCM with 8, 16, 24 or 32 bits per sample is supported, as is 32-bit
+//! float. Samples are scaled to between -1.0 and +1.0.
+//!
+//! A nice diagram of the format is linked from `doc/topics/wavfiles.md`.
+
+use std::path::Path;
+
+const FORMAT_PCM: u16 = 1;
+const FORMAT_FLOAT: u16 = 3;
+const FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+
+/// Samples from a WAV file, one list per channel
+#[derive(Clone, Debug)]
+pub struct WavData {
+    pub sample_rate: u32,
+    pub channels: Vec<Vec<f64>>,
+}
+
+/// Read a WAV file
+pub fn read(path: &Path) -> Result<WavData, String> {
+    let bytes = std::fs::read(path)
+        .map_err(|e| format!("can't read '{}': {}", path.display(), e))?;
+    parse(&bytes)
+}
+
+fn u16_at(bytes: &[u8], i: usize) -> u16 {
+    u16::from_le_bytes([bytes[i], bytes[i + 1]])
+}
+
+fn u32_at(bytes: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]])
+}
+
+/// Pull the samples out of the bytes of a WAV file
+pub fn parse(bytes: &[u8]) -> Result<WavData, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a RIFF WAVE file".to_string());
+    }
+
+    // walk the chunks, looking for the format and the data
+    let mut fmt = None;
+    let mut data = None;
+    let mut i = 12;
+    while i + 8 <= bytes.len() {
+        let id = &bytes[i..i + 4];
+        let size = u32_at(bytes, i + 4) as usize;
+        let body = &bytes[i + 8..bytes.len().min(i + 8 + size)];
+        if id == b"fmt " && body.len() >= 16 {
+            fmt = Some(body);
+        } else if id == b"data" {
+            data = Some(body);
+        }
+        i += 8 + size + size % 2; // chunks are padded to an even length
+    }
+    let fmt = fmt.ok_or("no 'fmt ' chunk")?;
+    let data = data.ok_or("no 'data' chunk")?;
+
+    let mut format = u16_at(fmt, 0);
+    let c_channels = u16_at(fmt, 2) as usize;
+    let sample_rate = u32_at(fmt, 4);
+    let bits = u16_at(fmt, 14);
+    if format == FORMAT_EXTENSIBLE && fmt.len() >= 26 {
+        format = u16_at(fmt, 24); // first two bytes of the sub-format GUID
+    }
+    if c_channels == 0 || sample_rate == 0 {
+        return Err("no channels, or a zero sample rate".to_string());
+    }
+
+    let decode: fn(&[u8]) -> f64 = match (format, bits) {
+        (FORMAT_PCM, 8) => |b| (b[0] as f64 - 128.0) / 128.0,
+        (FORMAT_PCM, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f64 / 32768.0,
+        (FORMAT_PCM, 24) => |b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f64 / 8388608.0,
+        (FORMAT_PCM, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64 / 2147483648.0,
+        (FORMAT_FLOAT, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+        _ => return Err(format!("unsupported format {} with {} bits per sample", format, bits)),
+    };
+
+    // samples for each channel are interleaved
+    let c_bytes = bits as usize / 8;
+    let mut channels = vec![vec![]; c_channels];
+    for frame in data.chunks_exact(c_bytes * c_channels) {
+        for (ch, sample) in channels.iter_mut().zip(frame.chunks_exact(c_bytes)) {
+            ch.push(decode(sample));
+        }
+    }
+
+    Ok(WavData { sample_rate, channels })
+}
+
+/// Write a WAV file, one list of samples per channel
+///
+/// 8, 16 and 24 bits are written as PCM, and 32 bits as float. PCM samples
+/// outside +/-1.0 are clipped. Returns the number of clipped samples.
+pub fn write(path: &Path, sample_rate: u32, bits: u16, channels: &[Vec<f64>])
+    -> Result<usize, String>
+{
+    let format = match bits {
+        8 | 16 | 24 => FORMAT_PCM,
+        32 => FORMAT_FLOAT,
+        _ => return Err(format!("can't write {} bits per sample", bits)),
+    };
+    let c_channels = channels.len() as u16;
+    let c_frames = channels.iter().map(|ch| ch.len()).min().unwrap_or(0);
+    let block_align = c_channels * bits / 8;
+    let c_data = c_frames as u32 * block_align as u32;
+
+    let mut bytes = b"RIFF".to_vec();
+    bytes.extend((36 + c_data).to_le_bytes());
+    bytes.extend(b"WAVEfmt ");
+    bytes.extend(16u32.to_le_bytes());
+    bytes.extend(format.to_le_bytes());
+    bytes.extend(c_channels.to_le_bytes());
+    bytes.extend(sample_rate.to_le_bytes());
+    bytes.extend((sample_rate * block_align as u32).to_le_bytes());
+    bytes.extend(block_align.to_le_bytes());
+    bytes.extend(bits.to_le_bytes());
+    bytes.extend(b"data");
+    bytes.extend(c_data.to_le_bytes());
+
+    // interleave the channels
+    let mut c_clipped = 0;
+    for i in 0..c_frames {
+        for ch in channels {
+            let x = ch[i];
+            if x.abs() > 1.0 {
+                c_clipped += 1;
+            }
+            let x = x.clamp(-1.0, 1.0);
+            match bits {
+                8 => bytes.push((x * 127.0 + 128.0).round() as u8),
+                16 => bytes.extend(((x * 32767.0).round() as i16).to_le_bytes()),
+                24 => bytes.extend(&((x * 8388607.0).round() as i32).to_le_bytes()[0..3]),
+                _ => bytes.extend((ch[i] as f32).to_le_bytes()),
+            }
+        }
+    }
+    if format == FORMAT_FLOAT {
+        c_clipped = 0; // float can go over full scale
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("can't make '{}': {}", parent.display(), e))?;
+    }
+    std::fs::write(path, bytes)
+        .map_err(|e| format!("can't write '{}': {}", path.display(), e))?;
+    Ok(c_clipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wrap some sample bytes up in a WAV file
+    fn wav_bytes(format: u16, c_channels: u16, bits: u16, samples: &[u8]) -> Vec<u8> {
+        let mut bytes = b"RIFF".to_vec();
+        bytes.extend((36 + samples.len() as u32).to_le_bytes());
+        bytes.extend(b"WAVEfmt ");
+        bytes.extend(16u32.to_le_bytes());
+        bytes.extend(format.to_le_bytes());
+        bytes.extend(c_channels.to_le_bytes());
+        bytes.extend(8000u32.to_le_bytes());
+        let block_align = c_channels * bits / 8;
+        bytes.extend((8000 * block_align as u32).to_le_bytes());
+        bytes.extend(block_align.to_le_bytes());
+        bytes.extend(bits.to_le_bytes());
+        bytes.extend(b"data");
+        bytes.extend((samples.len() as u32).to_le_bytes());
+        bytes.extend(samples);
+        bytes
+    }
+
+    #[test]
+    fn sample_formats() {
+        let wav = parse(&wav_bytes(FORMAT_PCM, 1, 8, &[0, 128, 192])).unwrap();
+        assert_eq!(wav.sample_rate, 8000);
+        assert_eq!(wav.channels, vec![vec![-1.0, 0.0, 0.5]]);
+
+        // stereo, 16-bit
+        let wav = parse(&wav_bytes(FORMAT_PCM, 2, 16, &[0, 0x40, 0, 0xC0, 0xFF, 0x7F, 0, 0x80])).unwrap();
+        assert_eq!(wav.channels[0], vec![0.5, 32767.0 / 32768.0]);
+        assert_eq!(wav.channels[1], vec![-0.5, -1.0]);
+
+        let wav = parse(&wav_bytes(FORMAT_PCM, 1, 24, &[0, 0, 0xC0, 0, 0, 0x20])).unwrap();
+        assert_eq!(wav.channels[0], vec![-0.5, 0.25]);
+
+        let samples: Vec<u8> = [0.25f32, -0.75].iter().flat_map(|x| x.to_le_bytes()).collect();
+        let wav = parse(&wav_bytes(FORMAT_FLOAT, 1, 32, &samples)).unwrap();
+        assert_eq!(wav.channels[0], vec![0.25, -0.75]);
+
+        assert!(parse(&wav_bytes(FORMAT_FLOAT, 1, 16, &[0, 0])).is_err());
+        assert!(parse(b"RIFF....AVI ").is_err());
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let path = Path::new("waves/wavfile/stereo.wav");
+        let left = vec![0.0, 0.5, -0.5, 1.5];
+        let right = vec![0.25, -0.25, 0.75, -1.0];
+        for bits in [8, 16, 24, 32] {
+            let c_clipped = write(path, 48000, bits, &[left.clone(), right.clone()]).unwrap();
+            let wav = read(path).unwrap();
+            assert_eq!(wav.sample_rate, 48000);
+
+            let tolerance = if bits == 8 { 1.0 / 64.0 } else { 1e-4 };
+            let expected = if bits == 32 { left.clone() } else { vec![0.0, 0.5, -0.5, 1.0] };
+            for (x, y) in wav.channels[0].iter().zip(&expected) {
+                assert!((x - y).abs() < tolerance, "{} isn't {} with {} bits", x, y, bits);
+            }
+            for (x, y) in wav.channels[1].iter().zip(&right) {
+                assert!((x - y).abs() < tolerance, "{} isn't {} with {} bits", x, y, bits);
+            }
+            assert_eq!(c_clipped, if bits == 32 { 0 } else { 1 });
+        }
+    }
+}
@@ -35,3 +35,36 @@ spice!(spice_irrrr, "./ngspice/test_irrrr.spi");
 // Transient analysis of a HPF
 spice!(spice_irrc, "./ngspice/test_irrc.spi");
 
+// Transient analysis of a transformer feeding a rectifier
+spice!(spice_transformer, "./ngspice/transformer_fullwave_rectifier.spi");
+
+// Transient analysis of a BJT switch
+spice!(spice_bjt_inverter, "./ngspice/bjt_inverter.spi");
+
+// Transient analysis of a CMOS inverter
+spice!(spice_cmos_inverter, "./ngspice/mos_cmos_inverter.spi");
+
+// Current-controlled sources in subcircuits
+spice!(spice_cc_mirror_subckt, "./ngspice/cc_mirror_subckt.spi");
+
+// Behavioural soft clipper in a subcircuit
+spice!(spice_behav_subckt_clipper, "./ngspice/behav_subckt_clipper.spi");
+
+// Sample-and-hold with a voltage-controlled switch
+spice!(spice_switch_sample_hold, "./ngspice/switch_sample_hold.spi");
+
+// Pulse sources, landing on the corners
+spice!(spice_pulse_rc, "./ngspice/pulse_rc.spi");
+spice!(spice_exp_sffm_am, "./ngspice/exp_sffm_am.spi");
+spice!(spice_sin_damped, "./ngspice/sin_damped.spi");
+spice!(spice_pwl_sources, "./ngspice/pwl_sources.spi");
+spice!(spice_wav_source, "./ngspice/wav_source.spi");
+spice!(spice_wav_output, "./ngspice/wav_output.spi");
+spice!(spice_tran_interp, "./ngspice/tran_interp.spi");
+spice!(spice_tran_tmax, "./ngspice/tran_tmax.spi");
+spice!(spice_ic_uic, "./ngspice/ic_uic.spi");
+spice!(spice_ic_dcop, "./ngspice/ic_dcop.spi");
+spice!(spice_nodeset_latch, "./ngspice/nodeset_latch.spi");
+spice!(spice_trap_lc, "./ngspice/trap_lc.spi");
+spice!(spice_gear_rc, "./ngspice/gear_rc.spi");
+spice!(spice_lte_rc, "./ngspice/lte_rc.spi");
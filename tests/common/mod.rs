@@ -1,6 +1,53 @@
+use std::fs;
+use std::path::Path;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::analysis::Configuration;
+
 #[allow(dead_code)]
 pub fn assert_nearly(x: f64, expected: f64) {
     const EPSILON: f64 = 1e-5;
     let delta = (x - expected).abs();
     assert!( delta < EPSILON, "{} isn't approximately {}", x, expected);
 }
+
+/// Read the named columns from a waveform file, a row per timepoint
+#[allow(dead_code)]
+pub fn read_waves(wavefile: &str, columns: &[&str]) -> Vec<Vec<f64>> {
+    let waves = fs::read_to_string(wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let cols: Vec<usize> = columns.iter()
+        .map(|c| names.iter().position(|n| n == c)
+            .unwrap_or_else(|| panic!("no '{}' column in {}", c, wavefile)))
+        .collect();
+    lines.skip(1)
+        .map(|line| {
+            let vals: Vec<f64> = line.split('\t').map(|x| x.parse::<f64>().unwrap()).collect();
+            cols.iter().map(|&i| vals[i]).collect()
+        })
+        .collect()
+}
+
+/// Run the transient in a SPICE deck, after `setup` has had a go at its
+/// configuration, and return the named columns
+#[allow(dead_code)]
+pub fn run_spi(
+    spice_file: &str,
+    columns: &[&str],
+    setup: impl FnOnce(&mut Configuration),
+) -> Vec<Vec<f64>> {
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(Path::new(spice_file)));
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    setup(&mut cfg);
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert_eq!(stats.end, cfg.TSTOP);
+
+    read_waves(&cfg.wavefile, columns)
+}
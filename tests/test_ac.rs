@@ -0,0 +1,101 @@
+//! AC small-signal analysis checks
+
+use std::path::Path;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::element::diode::thermal_voltage;
+
+mod common;
+use crate::common::assert_nearly;
+
+#[test]
+fn test_ac_rc_lpf() {
+
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/ac_rc_lpf.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration();
+
+    // 10 points per decade over 5 decades, plus the end point
+    assert_eq!(cfg.ac_frequencies().len(), 51);
+
+    let stats = eng.go(&ckt, cfg).unwrap();
+    assert_nearly(stats.end, 100e3);
+
+    let sols = eng.ac().unwrap();
+    let rc = 1e3 * 1e-6;
+    for (freq, v) in &sols {
+        let wrc = 2.0 * std::f64::consts::PI * freq * rc;
+        let mag = 1.0 / (1.0 + wrc * wrc).sqrt();
+        let phase = -wrc.atan().to_degrees();
+
+        assert_nearly(v[1].norm(), 1.0); // v(in)
+        assert_nearly(v[2].norm(), mag); // v(out)
+        assert!((v[2].arg_deg() - phase).abs() < 1e-3,
+            "phase {} isn't {} at {}Hz", v[2].arg_deg(), phase, freq);
+    }
+}
+
+#[test]
+fn test_ac_diode_small_signal() {
+
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/ac_diode.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration();
+
+    let _ = eng.go(&ckt, cfg).unwrap();
+    let sols = eng.ac().unwrap();
+    assert_eq!(sols.len(), 3);
+    assert_nearly(sols[1].0, 2e3);
+
+    // AC voltage is the AC current over the diode small-signal conductance
+    // at the 1mA bias point
+    let g_d = (1e-3 + 1e-9) / thermal_voltage(27.0);
+    for (_, v) in &sols {
+        assert!((v[1].norm() - 1e-6 / g_d).abs() < 1e-9,
+            "{} isn't {}", v[1].norm(), 1e-6 / g_d);
+        assert!(v[1].arg_deg().abs() < 1e-6);
+    }
+}
+
+#[test]
+fn test_ac_rl_hpf() {
+
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/ac_rl_hpf.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration();
+
+    let _ = eng.go(&ckt, cfg).unwrap();
+    let sols = eng.ac().unwrap();
+
+    let (r, l) = (10.0, 10e-6);
+    for (freq, v) in &sols {
+        let wl = 2.0 * std::f64::consts::PI * freq * l;
+        let mag = wl / (r * r + wl * wl).sqrt();
+        let phase = (r / wl).atan().to_degrees();
+
+        assert_nearly(v[2].norm(), mag); // v(out)
+        assert!((v[2].arg_deg() - phase).abs() < 1e-3,
+            "phase {} isn't {} at {}Hz", v[2].arg_deg(), phase, freq);
+    }
+}
@@ -0,0 +1,137 @@
+//! Behavioural `B` sources - voltage and current forms, branch currents in
+//! expressions, Newton on a nonlinear expression, AC and subcircuits
+
+use std::path::Path;
+use std::fs;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::circuit::Circuit;
+use tiny_spice::element::Element;
+use tiny_spice::analysis::{Kind, AcSweep};
+
+mod common;
+use crate::common::assert_nearly;
+
+fn read(filename: &str) -> (Circuit, tiny_spice::analysis::Configuration) {
+    let spice_file = Path::new(filename);
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+    (reader.get_expanded_circuit(), reader.configuration().clone())
+}
+
+/// Set `Vin`, and return the node voltages at the DC operating point
+fn sources_at(v_in: f64) -> (Circuit, Vec<f64>) {
+    let (mut ckt, cfg) = read("./ngspice/behav_sources.spi");
+
+    for el in &mut ckt.elements {
+        if let Element::V(ref mut src) = el {
+            if src.ident == "Vin" {
+                src.value = v_in;
+            }
+        }
+    }
+
+    let mut eng = engine::Engine::new();
+    eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+    (ckt, v)
+}
+
+#[test]
+fn test_behavioural_soft_clip() {
+    for v_in in [-1.0_f64, -0.2, 0.0, 0.1, 0.5, 2.0] {
+        let (ckt, v) = sources_at(v_in);
+        let v_clip = v[ckt.get_node_id("clip").unwrap()];
+        assert_nearly(v_clip, (v_in * 3.0).tanh());
+    }
+}
+
+#[test]
+fn test_behavioural_vca() {
+    for v_in in [-1.0, 0.5, 2.0] {
+        let (ckt, v) = sources_at(v_in);
+        let v_vca = v[ckt.get_node_id("vca").unwrap()];
+        assert_nearly(v_vca, v_in * 2.0);
+    }
+}
+
+#[test]
+fn test_behavioural_branch_current() {
+    let (ckt, v) = sources_at(0.5);
+    let v_imon = v[ckt.get_node_id("imon").unwrap()];
+    assert_nearly(v_imon, 0.5);
+}
+
+#[test]
+fn test_behavioural_square_law() {
+    let (ckt, cfg) = read("./ngspice/behav_square_law.spi");
+    let mut eng = engine::Engine::new();
+    let stats = eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+
+    // (5 - x)/1k = 1m * x^2
+    let expected = (-1.0 + 21.0_f64.sqrt()) / 2.0;
+    assert_nearly(v[ckt.get_node_id("x").unwrap()], expected);
+    assert!(stats.iterations > 2);
+}
+
+#[test]
+fn test_behavioural_small_signal() {
+    let (ckt, mut cfg) = read("./ngspice/behav_sources.spi");
+    cfg.kind = Some(Kind::Ac);
+    cfg.set_ac(AcSweep::Decade, 1, 1e3, 1e4);
+    cfg.set_wavefile("waves/behav_sources/ac.dat");
+
+    let mut eng = engine::Engine::new();
+    let _ = eng.go(&ckt, &cfg).unwrap();
+    let sols = eng.ac().unwrap();
+
+    // slope of the clipper and the VCA at the operating point
+    let (_, v) = &sols[0];
+    let gain_clip = 3.0 * (1.0 - 1.5_f64.tanh().powi(2));
+    assert_nearly(v[ckt.get_node_id("clip").unwrap()].norm(), gain_clip);
+    assert_nearly(v[ckt.get_node_id("vca").unwrap()].norm(), 2.0);
+}
+
+#[test]
+fn test_behavioural_in_subckt() {
+    let (ckt, mut cfg) = read("./ngspice/behav_subckt_clipper.spi");
+    cfg.set_wavefile("waves/behav_subckt_clipper/tran.dat");
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert!(stats.end >= cfg.TSTOP);
+
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    // ports can be named from either side of the hierarchy
+    let v_in = names.iter().position(|n| n.ends_with("in)")).unwrap();
+    let v_out = names.iter().position(|n| n.ends_with("out)")).unwrap();
+
+    for line in lines.skip(1) {
+        let vals: Vec<f64> = line.split('\t')
+            .map(|x| x.parse::<f64>().unwrap())
+            .collect();
+        assert_nearly(vals[v_out], (vals[v_in] * 3.0).tanh() / 3.0);
+    }
+}
+
+#[test]
+fn test_behavioural_hierarchical_node() {
+    let (ckt, cfg) = read("./ngspice/behav_hier_node.spi");
+    let mut eng = engine::Engine::new();
+    eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+
+    // `Bprobe` comes before `Xd` in the deck, so `Xd.mid` isn't there yet
+    assert_nearly(v[ckt.get_node_id("Xd.mid").unwrap()], 1.5);
+    assert_nearly(v[ckt.get_node_id("probe").unwrap()], 3.0);
+
+    // 1.5mA through the sense source inside the divider
+    assert_nearly(v[ckt.get_node_id("sense").unwrap()], 1.5);
+}
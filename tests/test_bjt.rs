@@ -0,0 +1,122 @@
+//! BJT checks - forward active DC bias, Early effect, small-signal gain
+//! and a switching transient
+
+use std::path::Path;
+use std::fs;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::element::diode::thermal_voltage;
+use tiny_spice::analysis::{Kind, AcSweep};
+
+mod common;
+use crate::common::assert_nearly;
+
+fn dc_op(filename: &str) -> Vec<f64> {
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new(filename);
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration();
+
+    eng.dc_operating_point(&ckt, cfg);
+    eng.dc().unwrap()
+}
+
+#[test]
+fn test_bjt_npn_forward_active() {
+    let v = dc_op("./ngspice/bjt_npn_ce.spi");
+
+    // nodes: gnd, vcc, b, c
+    let i_c: f64 = 100.0 * 10e-6;
+    assert_nearly(v[2], thermal_voltage(27.0) * (i_c / 1e-16 + 1.0).ln());
+    assert_nearly(v[3], 5.0 - 1e3 * i_c);
+}
+
+#[test]
+fn test_bjt_pnp_forward_active() {
+    let v = dc_op("./ngspice/bjt_pnp_ce.spi");
+
+    // nodes: gnd, vee, b, c
+    let i_c: f64 = 100.0 * 10e-6;
+    assert_nearly(v[2], -thermal_voltage(27.0) * (i_c / 1e-16 + 1.0).ln());
+    assert_nearly(v[3], -5.0 + 1e3 * i_c);
+}
+
+#[test]
+fn test_bjt_early_effect() {
+    let v = dc_op("./ngspice/bjt_npn_early.spi");
+
+    // base current sets the forward current, the collector current is
+    // that scaled by (1 - v_bc/VAF)
+    let (i_f, r_c, v_af): (f64, f64, f64) = (100.0 * 10e-6, 1e3, 50.0);
+    let v_be = thermal_voltage(27.0) * (i_f / 1e-16 + 1.0).ln();
+    let i_c = i_f * (1.0 - (v_be - 5.0) / v_af) / (1.0 + i_f * r_c / v_af);
+
+    assert_nearly(v[2], v_be);
+    assert_nearly(v[3], 5.0 - r_c * i_c);
+}
+
+#[test]
+fn test_bjt_small_signal_gain() {
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/bjt_npn_ce.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    cfg.kind = Some(Kind::Ac);
+    cfg.set_ac(AcSweep::Decade, 1, 1e3, 1e4);
+    cfg.set_wavefile("waves/bjt_npn_ce/ac.dat");
+
+    let _ = eng.go(&ckt, &cfg).unwrap();
+    let sols = eng.ac().unwrap();
+
+    // 1uA into the base is beta times that out of the collector
+    let (_, v) = &sols[0];
+    assert_nearly(v[3].norm(), 100.0 * 1e-6 * 1e3);
+    assert!((v[3].arg_deg().abs() - 180.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_bjt_inverter() {
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/bjt_inverter.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    cfg.set_wavefile("waves/bjt_inverter/tran.dat");
+
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert!(stats.end >= cfg.TSTOP);
+
+    // collector is pulled low while the base is driven, and high after
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut saturated = false;
+    let mut last_v_c = 0.0;
+    for line in waves.lines().skip(2) {
+        let vals: Vec<f64> = line.split('\t')
+            .map(|x| x.parse::<f64>().unwrap())
+            .collect();
+        if vals[0] > 200e-6 && vals[0] < 300e-6 {
+            assert!(vals[5] < 0.2, "v(c) = {} at {}", vals[5], vals[0]);
+            saturated = true;
+        }
+        last_v_c = vals[5];
+    }
+    assert!(saturated);
+    assert_nearly(last_v_c, 5.0);
+}
@@ -0,0 +1,114 @@
+//! Simple DC test of CCCS and CCVS
+
+use std::path::Path;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::element::Element;
+
+mod common;
+use crate::common::assert_nearly;
+
+#[test]
+fn test_cc_vs_cs_basic() {
+
+    // Initialise the SPICE Engine
+    //  (do this first to get the banner at the top)
+    let mut eng = engine::Engine::new();
+
+    // See if the filename exists
+    let spice_file = Path::new("./ngspice/cc_vs_cs_basic.spi");
+    spice_file.try_exists().expect("Can't access spice file");
+
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    if errors_exist {
+        println!("*FATAL* Errors in SPICE Deck so not doing simulations");
+        return;
+    }
+
+    let mut ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration();
+
+    // find the sinewave source and hack the offset to get a non-zero dc value
+    for el in &mut ckt.elements {
+        if let Element::Isin(ref mut src) = el {
+            src.vo = 3.0;
+        }
+    }
+
+    let stats = eng.dc_operating_point(&ckt, cfg);
+    let v = eng.dc().unwrap();
+
+    assert_nearly(v[1], 3.0);
+    assert_nearly(v[2], 3.0); // through the 0V sense source
+    assert_nearly(v[3], 6.0); // cccs
+    assert_nearly(v[4], 9.0); // ccvs
+
+    // linear solve should only take 2 steps to converge
+    assert_eq!(stats.iterations, 2);
+
+}
+
+#[test]
+fn test_cc_sources_in_subckts() {
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/cc_mirror_subckt.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration();
+
+    // controls are found through the hierarchy
+    for el in &ckt.elements {
+        match el {
+            Element::Cccs(ref src) => assert_eq!(src.vsrc, "Xm.Vs"),
+            Element::Ccvs(ref src) => assert_eq!(src.vsrc, "Xt.Vs"),
+            _ => {}
+        }
+    }
+
+    eng.dc_operating_point(&ckt, cfg);
+    let v = eng.dc().unwrap();
+
+    // 1mA mirrored with a gain of 2 into 1k
+    assert_nearly(v[ckt.get_node_id("b").unwrap()], 2.0);
+
+    // 2mA through a 500R transresistance
+    assert_nearly(v[ckt.get_node_id("d").unwrap()], 1.0);
+}
+
+#[test]
+#[should_panic]
+fn test_cc_missing_vsrc() {
+    let spice_file = Path::new("./ngspice/cc_missing_vsrc.spi");
+    let mut reader = spice::Reader::new();
+    let _ = reader.read(spice_file);
+    let _ = reader.get_expanded_circuit();
+}
+
+#[test]
+fn test_cc_sin_control() {
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/cc_sin_control.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration();
+
+    eng.dc_operating_point(&ckt, cfg);
+    let v = eng.dc().unwrap();
+
+    // the SIN() source sits at its 1V offset, driving 1mA out into 1k,
+    // so -1mA flows through it
+    assert_nearly(v[ckt.get_node_id("n1").unwrap()], -2.0); // cccs
+    assert_nearly(v[ckt.get_node_id("n2").unwrap()], -0.5); // ccvs
+}
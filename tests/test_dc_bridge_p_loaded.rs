@@ -19,8 +19,8 @@ fn test_dc_bridge_loaded_2v0() {
     let v = eng.dc().unwrap();
     println!("\n*INFO* Done");
 
-    assert_nearly(v[3], 9.4624);
-    assert_nearly(v[4], 0.53759);
+    assert_nearly(v[3], 9.58556);
+    assert_nearly(v[4], 0.41444);
 }
 
 #[test]
@@ -36,8 +36,8 @@ fn test_dc_bridge_loaded_gnd() {
 
     println!("\n*INFO* Done");
 
-    assert_nearly(v[3], 9.4624);
-    assert_nearly(v[2], 0.53759);
+    assert_nearly(v[3], 9.58556);
+    assert_nearly(v[2], 0.41444);
 }
 
 #[allow(dead_code)]
@@ -45,8 +45,8 @@ fn build_v() -> Circuit {
     let mut ckt = Circuit::new();
 
     // bridge input voltage
-    ckt.elements.push(Element::V(VoltageSource{p: 1, n: 2, value: 10.0, idx:0 }));
-    ckt.elements.push(Element::V(VoltageSource{p: 2, n: 0, value: 0.0 , idx:1 }));
+    ckt.elements.push(Element::V(VoltageSource::new(1, 2, 10.0, 0)));
+    ckt.elements.push(Element::V(VoltageSource::new(2, 0, 0.0, 1)));
 
     // Diode bridge
     //  (1) is top
@@ -67,7 +67,7 @@ fn build_vv() -> Circuit {
     let mut ckt = Circuit::new();
 
     // bridge input voltage
-    ckt.elements.push(Element::V(VoltageSource{p: 1, n: 0, value: 10.0, idx:0}));
+    ckt.elements.push(Element::V(VoltageSource::new(1, 0, 10.0, 0)));
 
     // Diode bridge
     //  (1) is top
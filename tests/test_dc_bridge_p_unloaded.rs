@@ -28,8 +28,8 @@ fn test_diode_bridge_unloaded_10V() {
     //assert_nearly(v[4], 0.190917);
 
     // after
-    assert_nearly(v[3], 9.976717);
-    assert_nearly(v[4], 0.023283);
+    assert_nearly(v[3], 9.982081);
+    assert_nearly(v[4], 0.017919);
 }
 
 
@@ -37,8 +37,8 @@ fn build() -> Circuit {
     let mut ckt = Circuit::new();
 
     // bridge input voltage
-    ckt.elements.push(Element::V(VoltageSource{p: 1, n: 2, value: 10.0, idx:0 }));
-    ckt.elements.push(Element::V(VoltageSource{p: 2, n: 0, value: 0.0 , idx:1 }));
+    ckt.elements.push(Element::V(VoltageSource::new(1, 2, 10.0, 0)));
+    ckt.elements.push(Element::V(VoltageSource::new(2, 0, 0.0, 1)));
 
     // Diode bridge
     //  (1) is top
@@ -0,0 +1,123 @@
+//! Diodes with `.model` cards - parameters from a `.lib` section, forward
+//! bias through the series resistance, reverse breakdown and junction
+//! capacitance
+
+use std::path::Path;
+use std::fs;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::circuit::Circuit;
+use tiny_spice::element::Element;
+use tiny_spice::element::diode::thermal_voltage;
+
+mod common;
+use crate::common::assert_nearly;
+
+fn read(filename: &str) -> (Circuit, tiny_spice::analysis::Configuration) {
+    let spice_file = Path::new(filename);
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+    (reader.get_expanded_circuit(), reader.configuration().clone())
+}
+
+/// Bias the D1N4148 with `i1` flowing into the anode, and return `v(a)`
+fn d1n4148_bias(i1: f64) -> f64 {
+    let (mut ckt, cfg) = read("./ngspice/diode_model.spi");
+
+    for el in &mut ckt.elements {
+        if let Element::I(ref mut src) = el {
+            src.value = i1;
+        }
+    }
+
+    let mut eng = engine::Engine::new();
+    eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+    v[ckt.get_node_id("a").unwrap()]
+}
+
+// IS=2.52n N=1.752 RS=0.568 CJO=4p TT=20n BV=100 IBV=100u
+const IS: f64 = 2.52e-9;
+const N: f64 = 1.752;
+const RS: f64 = 0.568;
+
+#[test]
+fn test_diode_model_from_lib_section() {
+    let (ckt, _) = read("./ngspice/diode_model.spi");
+
+    let mut c_diodes = 0;
+    let mut c_res = 0;
+    for el in &ckt.elements {
+        match el {
+            Element::D(ref d) => {
+                // not the IS=1e-9 model outside the `.lib` section
+                assert_nearly(d.i_sat * 1e9, IS * 1e9);
+                assert_nearly(d.n_emit, N);
+                assert_nearly(d.c_jo * 1e12, 4.0);
+                assert_nearly(d.t_t * 1e9, 20.0);
+                assert_nearly(d.bv, 100.0);
+                assert_nearly(d.ibv, 100e-6);
+                c_diodes += 1;
+            }
+            Element::R(ref r) => {
+                // series resistance to the internal anode
+                assert_nearly(r.value, RS);
+                c_res += 1;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(c_diodes, 1);
+    assert_eq!(c_res, 1);
+}
+
+#[test]
+fn test_diode_model_forward() {
+    let i_d = 1e-3;
+    let v_a = d1n4148_bias(i_d);
+    let vt_n = N * thermal_voltage(27.0);
+    let expected = vt_n * (i_d / IS + 1.0).ln() + i_d * RS;
+    assert_nearly(v_a, expected);
+}
+
+#[test]
+fn test_diode_model_breakdown() {
+    let i_d = -10e-3;
+    let v_a = d1n4148_bias(i_d);
+    let vt_n = N * thermal_voltage(27.0);
+    let expected = -(100.0 + vt_n * (-i_d / 100e-6).ln()) + i_d * RS;
+    assert!((v_a - expected).abs() < 1e-3, "{} isn't approximately {}", v_a, expected);
+}
+
+#[test]
+fn test_diode_junction_capacitance() {
+    let (ckt, mut cfg) = read("./ngspice/diode_cj_rc.spi");
+    cfg.set_wavefile("waves/diode_cj_rc/tran.dat");
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert!(stats.end >= cfg.TSTOP);
+
+    // with M=0, the reverse-biased junction is a plain 10pF capacitor. Loose
+    // tolerance for the backward-euler lag.
+    let tau = 10e3 * 10e-12;
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let v_a = names.iter().position(|n| *n == "v(a)").unwrap();
+
+    for line in lines.skip(1) {
+        let vals: Vec<f64> = line.split('\t')
+            .map(|x| x.parse::<f64>().unwrap())
+            .collect();
+        if vals[0] > 50e-9 {
+            let expected = -5.0 * (1.0 - (-(vals[0] - 1e-9) / tau).exp());
+            assert!((vals[v_a] - expected).abs() < 0.2,
+                "v(a) = {} at {}, expected {}", vals[v_a], vals[0], expected);
+        }
+    }
+}
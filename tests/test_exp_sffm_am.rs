@@ -0,0 +1,94 @@
+//! EXP, SFFM and AM sources - parsing, and the waveforms in a transient
+
+use std::path::Path;
+use std::fs;
+use std::f64::consts::PI;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::element::Element;
+use tiny_spice::element::waveform::Waveform;
+
+mod common;
+use crate::common::assert_nearly;
+
+#[test]
+fn test_exp_sffm_am_parse() {
+    let spice_file = Path::new("./ngspice/exp_sffm_am.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+    let ckt = reader.get_expanded_circuit();
+
+    let mut names = vec![];
+    for el in &ckt.elements {
+        match el {
+            Element::Vwave(ref src) => names.push(src.wave.name()),
+            Element::Iwave(ref src) => {
+                if let Waveform::Sffm(ref sffm) = src.wave {
+                    assert_nearly(sffm.fc, 10e3);
+                    assert_nearly(sffm.mdi, 2.0);
+                    assert_eq!(sffm.phasec, 0.0);
+                }
+                names.push(src.wave.name());
+            }
+            _ => {}
+        }
+    }
+    names.sort();
+    assert_eq!(names, vec!["AM", "EXP", "SFFM"]);
+}
+
+#[test]
+fn test_exp_sffm_am_waveforms() {
+    let spice_file = Path::new("./ngspice/exp_sffm_am.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    cfg.set_wavefile("waves/exp_sffm_am/tran.dat");
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert_eq!(stats.end, cfg.TSTOP);
+
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let col = |name: &str| names.iter().position(|n| *n == name).unwrap();
+    let (v_exp, v_sffm, v_am) = (col("v(v_exp)"), col("v(i_sffm)"), col("v(v_am)"));
+
+    let mut landed = false;
+    for line in lines.skip(1) {
+        let r: Vec<f64> = line.split('\t').map(|x| x.parse::<f64>().unwrap()).collect();
+        let t = r[0];
+
+        let mut expected = 0.0;
+        if t > 10e-6 {
+            expected += 2.0 * (1.0 - (-(t - 10e-6) / 10e-6).exp());
+        }
+        if t > 50e-6 {
+            expected -= 2.0 * (1.0 - (-(t - 50e-6) / 5e-6).exp());
+        }
+        assert_nearly(r[v_exp], expected);
+
+        // resistive, so 1mA is 1V
+        let expected = 0.5 + (2.0 * PI * 10e3 * t + 2.0 * (2.0 * PI * 1e3 * t).sin()).sin();
+        assert_nearly(r[v_sffm], expected);
+
+        let expected = if t < 20e-6 {
+            0.0
+        } else {
+            let t = t - 20e-6;
+            1.5 * (1.0 + (2.0 * PI * 2e3 * t).sin()) * (2.0 * PI * 20e3 * t).sin()
+        };
+        assert_nearly(r[v_am], expected);
+
+        if (t - 50e-6).abs() < 1e-12 {
+            landed = true;
+        }
+    }
+    assert!(landed, "no timepoint at the start of the EXP fall");
+}
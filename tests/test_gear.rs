@@ -0,0 +1,42 @@
+//! Gear integration: a stiff pair of RC decays, 10ns and 1ms, stepped at
+//! 10us. The fast one dies straight away without ringing, and the slow one
+//! follows its exponential closer than backward Euler manages.
+
+extern crate tiny_spice;
+
+use tiny_spice::analysis::Method;
+
+mod common;
+use crate::common::run_spi;
+
+/// Worst error in `v(slow)` against `exp(-t/1ms)`
+fn slow_error(rows: &[Vec<f64>]) -> f64 {
+    rows.iter()
+        .map(|r| (r[2] - (-r[0] / 1e-3).exp()).abs())
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn test_gear_stiff_rc() {
+    for maxord in 2..=6 {
+        let rows = run_spi("./ngspice/gear_rc.spi", &["Time", "v(fast)", "v(slow)"], |cfg| {
+            assert_eq!(cfg.method, Method::Gear);
+            cfg.maxord = maxord;
+            cfg.set_wavefile(&format!("waves/gear_rc/gear{}.dat", maxord));
+        });
+        assert!(slow_error(&rows) < 2e-5, "maxord {}: error {}", maxord, slow_error(&rows));
+        for r in rows.iter().filter(|r| r[0] > 20e-6) {
+            let (t, v_fast) = (r[0], r[1]);
+            assert!(v_fast.abs() < 1e-5, "maxord {}: v(fast) = {} at {}", maxord, v_fast, t);
+        }
+    }
+}
+
+#[test]
+fn test_euler_stiff_rc() {
+    let rows = run_spi("./ngspice/gear_rc.spi", &["Time", "v(fast)", "v(slow)"], |cfg| {
+        cfg.method = Method::Euler;
+        cfg.set_wavefile("waves/gear_rc/euler.dat");
+    });
+    assert!(slow_error(&rows) > 1e-3, "error {}", slow_error(&rows));
+}
@@ -0,0 +1,55 @@
+//! Initial conditions: `.ic`, `IC=` on capacitors and inductors, and `uic`
+
+use std::path::Path;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+
+mod common;
+use crate::common::{assert_nearly, run_spi};
+
+#[test]
+fn test_ic_uic() {
+    // `.ic` is on `v(Xsystem1.soutp)`, which is dumped under its top-level
+    // name `a`
+    let rows = run_spi("./ngspice/ic_uic.spi", &["Time", "v(a)", "v(b)", "i(L1)"], |_| {});
+
+    // straight from the initial conditions, through the subcircuit port
+    assert_nearly(rows[0][1], 2.0);
+    assert_nearly(rows[0][2], 1.0);
+    assert_nearly(rows[0][3], 1e-3);
+
+    // RC discharges with 10us time constants, the RL with 5us
+    for r in &rows {
+        let t = r[0];
+        assert!((r[1] - 2.0 * (-t / 10e-6).exp()).abs() < 0.02, "v(a) at {}", t);
+        assert!((r[2] - (-t / 10e-6).exp()).abs() < 0.01, "v(b) at {}", t);
+        assert!((r[3] - 1e-3 * (-t / 5e-6).exp()).abs() < 1e-5, "i(L1) at {}", t);
+    }
+}
+
+#[test]
+fn test_ic_holds_dc_operating_point() {
+    let rows = run_spi("./ngspice/ic_dcop.spi", &["Time", "v(in)", "v(out)"], |_| {});
+
+    // the capacitor's IC= is ignored without uic, but .ic holds `out` at 0V
+    assert_nearly(rows[0][1], 1.0);
+    assert_nearly(rows[0][2], 0.0);
+
+    // then it charges up with a 10us time constant
+    for r in &rows {
+        let t = r[0];
+        let expected = 1.0 - (-t / 10e-6).exp();
+        assert!((r[2] - expected).abs() < 0.01, "v(out) = {} at {}, not {}", r[2], t, expected);
+    }
+}
+
+#[test]
+fn test_ic_parsing() {
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(Path::new("./ngspice/ic_uic.spi")));
+    let cfg = reader.configuration();
+    assert!(cfg.uic);
+    assert_eq!(cfg.initial_conditions, vec![("Xsystem1.soutp".to_string(), 2.0)]);
+}
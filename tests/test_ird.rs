@@ -19,7 +19,7 @@ fn test_dc_ird() {
     let v = eng.dc().unwrap();
 
     println!("\n*INFO* Done");
-    assert_nearly(v[1], 0.73217);
+    assert_nearly(v[1], 0.56364);
 }
 
 
@@ -43,7 +43,7 @@ fn test_dc_ird_isat_1pA() {
 fn build(isat: f64) -> circuit::Circuit {
     let mut ckt = circuit::Circuit::new();
     ckt.elements.push(
-        circuit::Element::I(circuit::CurrentSource{p: 0, n: 1, value: 3.0}),
+        circuit::Element::I(circuit::CurrentSource::new(0, 1, 3.0)),
     );
     ckt.elements.push(
         circuit::Element::R(circuit::Resistor{ident: "r1".to_string(), a: 1, b: 0, value: 10.0}),
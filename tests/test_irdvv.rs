@@ -20,9 +20,9 @@ fn test_ird_vv() {
     let v = eng.dc().unwrap();
     println!("\n*INFO* Done");
 
-    assert_nearly(v[1], 0.73217);
-    assert_nearly(v[2], 0.73217); // 0V source
-    assert_nearly(v[3], 0.73217); // 0V source
+    assert_nearly(v[1], 0.56364);
+    assert_nearly(v[2], 0.56364); // 0V source
+    assert_nearly(v[3], 0.56364); // 0V source
     assert_nearly(v[4] + v[5], 3.0); // branch current
 }
 
@@ -30,12 +30,12 @@ fn test_ird_vv() {
 fn build() -> Circuit {
     let mut ckt = Circuit::new();
     ckt.elements.push(
-        Element::I(CurrentSource{p: 0, n: 1, value: 3.0}),
+        Element::I(CurrentSource::new(0, 1, 3.0)),
     );
 
     // R with series 0V source to measure branch current
     ckt.elements.push(
-        Element::V(VoltageSource{p: 1, n: 2, value: 0.0, idx: 0}),
+        Element::V(VoltageSource::new(1, 2, 0.0, 0)),
     );
 
     ckt.elements.push(
@@ -44,7 +44,7 @@ fn build() -> Circuit {
 
     // D with series 0V source to measure branch current
     ckt.elements.push(
-        Element::V(VoltageSource{p: 1, n: 3, value: 0.0, idx: 1}),
+        Element::V(VoltageSource::new(1, 3, 0.0, 1)),
     );
     ckt.elements.push(
         Element::D(Diode::new("D1", 3, 0, 1e-9, 27.0)),
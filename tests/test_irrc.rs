@@ -29,7 +29,7 @@ fn build() -> Circuit {
 
     // 10V Voltage Source
     ckt.elements.push(
-        Element::I(CurrentSource{p: 0, n: 1, value: 1.0}),
+        Element::I(CurrentSource::new(0, 1, 1.0)),
     );
     ckt.elements.push(
         Element::R(Resistor{ident: "r1".to_string(), a: 1, b: 0, value: 10.0}),
@@ -40,7 +40,7 @@ fn build() -> Circuit {
         Element::R(Resistor{ident: "r2".to_string(), a: 1, b: 2, value: 1.0e-3}),
     );
     ckt.elements.push(
-        Element::C(Capacitor{ident: "c1".to_string(), a: 2, b: 0, value: 0.01e-6}),
+        Element::C(Capacitor{ident: "c1".to_string(), a: 2, b: 0, value: 0.01e-6, ic: None}),
     );
 
     ckt.build_node_id_lut();
@@ -35,7 +35,7 @@ fn test_irrrr() {
 fn build() -> circuit::Circuit {
     let mut ckt = circuit::Circuit::new();
     ckt.elements.push(
-        circuit::Element::I(circuit::CurrentSource{p: 0, n: 1, value: 3.0}),
+        circuit::Element::I(circuit::CurrentSource::new(0, 1, 3.0)),
     );
     ckt.elements.push(
         circuit::Element::R(circuit::Resistor{ident: "r1".to_string(), a: 1, b: 2, value: 5.0}),
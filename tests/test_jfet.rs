@@ -0,0 +1,139 @@
+//! JFET checks - the channel regions, gate junction, PJF and a
+//! voltage-controlled resistor
+
+use std::path::Path;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::circuit::Circuit;
+use tiny_spice::element::Element;
+use tiny_spice::element::diode::thermal_voltage;
+
+mod common;
+use crate::common::assert_nearly;
+
+fn read(filename: &str) -> (Circuit, tiny_spice::analysis::Configuration) {
+    let spice_file = Path::new(filename);
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+    (reader.get_expanded_circuit(), reader.configuration().clone())
+}
+
+/// Set the DC voltage sources, and return the drain current
+fn njf_bias(v_gs: f64, v_ds: f64) -> f64 {
+    let (mut ckt, cfg) = read("./ngspice/jfet_njf_iv.spi");
+
+    for el in &mut ckt.elements {
+        if let Element::V(ref mut src) = el {
+            src.value = match src.ident.as_str() {
+                "Vgs" => v_gs,
+                "Vds" => v_ds,
+                _ => src.value,
+            };
+        }
+    }
+
+    let mut eng = engine::Engine::new();
+    eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+
+    // branch current of Vds is the current out of the drain
+    let idx_vds = ckt.count_nodes() + 1;
+    -v[idx_vds]
+}
+
+// VTO=-2 BETA=1m LAMBDA=0.01
+const BETA: f64 = 1e-3;
+const LAMBDA: f64 = 0.01;
+
+#[test]
+fn test_njf_cutoff() {
+    let i_d = njf_bias(-3.0, 5.0);
+    assert_nearly(i_d * 1e6, 0.0);
+}
+
+#[test]
+fn test_njf_linear() {
+    let i_d = njf_bias(-1.0, 0.5);
+    let expected = BETA * 0.5 * (2.0 - 0.5) * (1.0 + LAMBDA * 0.5);
+    assert_nearly(i_d, expected);
+}
+
+#[test]
+fn test_njf_saturation() {
+    let i_d = njf_bias(-1.0, 5.0);
+    let expected = BETA * 1.0 * (1.0 + LAMBDA * 5.0);
+    assert_nearly(i_d, expected);
+}
+
+#[test]
+fn test_njf_reversed() {
+    // drain below the source, so they swap over and current flows backwards
+    let i_d = njf_bias(-1.0, -0.5);
+    let expected = BETA * 0.5 * (2.0 * 1.5 - 0.5) * (1.0 + LAMBDA * 0.5);
+    assert_nearly(i_d, -expected);
+}
+
+#[test]
+fn test_njf_gate_forward_biased() {
+    let (ckt, cfg) = read("./ngspice/jfet_gate_forward.spi");
+    let mut eng = engine::Engine::new();
+    eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+
+    // gate current through Rg matches the gate-source junction
+    let v_g = v[ckt.get_node_id("g").unwrap()];
+    let i_g = (5.0 - v_g) / 10e3;
+    let i_gs = 1e-14 * ((v_g / thermal_voltage(27.0)).exp() - 1.0);
+    assert!(v_g > 0.5 && v_g < 0.9, "v(g) = {}", v_g);
+    assert_nearly(i_g * 1e3, i_gs * 1e3);
+}
+
+#[test]
+fn test_pjf_saturation() {
+    let (ckt, cfg) = read("./ngspice/jfet_pjf_iv.spi");
+    let mut eng = engine::Engine::new();
+    eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+
+    // v_gs = 1, v_ds = -5; VTO=-2 BETA=0.5m LAMBDA=0.01
+    let expected = 0.5e-3 * 1.0 * (1.0 + 0.01 * 5.0);
+
+    // current flows out of the drain, and through Vd to ground
+    let idx_vd = ckt.count_nodes() + 1;
+    assert_nearly(v[idx_vd], expected);
+}
+
+#[test]
+fn test_jfet_voltage_controlled_resistor() {
+    let (mut ckt, cfg) = read("./ngspice/jfet_vcr.spi");
+
+    let mut v_out_prev = 0.0;
+    for v_ctl in [0.0, -0.5, -1.0, -1.5] {
+        for el in &mut ckt.elements {
+            if let Element::V(ref mut src) = el {
+                if src.ident == "Vctl" {
+                    src.value = v_ctl;
+                }
+            }
+        }
+
+        let mut eng = engine::Engine::new();
+        eng.dc_operating_point(&ckt, &cfg);
+        let v = eng.dc().unwrap();
+        let v_out = v[ckt.get_node_id("out").unwrap()];
+
+        // current through R1 all goes down the channel
+        let v_gst = v_ctl + 2.0;
+        let i_r = (0.1 - v_out) / 10e3;
+        let i_d = BETA * v_out * (2.0 * v_gst - v_out) * (1.0 + LAMBDA * v_out);
+        assert_nearly(i_r * 1e3, i_d * 1e3);
+
+        // less attenuation as the channel is pinched off
+        assert!(v_out > v_out_prev, "v(out) = {} at v(ctl) = {}", v_out, v_ctl);
+        v_out_prev = v_out;
+    }
+}
@@ -0,0 +1,44 @@
+//! Truncation error timestep control: an RC decay gets short steps while it
+//! falls quickly, and longer ones as it flattens out
+
+extern crate tiny_spice;
+
+mod common;
+use crate::common::run_spi;
+
+/// Worst error against `exp(-t/1ms)`
+fn max_error(rows: &[Vec<f64>]) -> f64 {
+    rows.iter()
+        .map(|r| (r[1] - (-r[0] / 1e-3).exp()).abs())
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn test_lte_rc_decay() {
+    let rows = run_spi("./ngspice/lte_rc.spi", &["Time", "v(out)"], |cfg| {
+        assert_eq!(cfg.TRTOL, 7.0);
+        cfg.set_wavefile("waves/lte_rc/trtol7.dat");
+    });
+    assert!(max_error(&rows) < 2e-3, "error {}", max_error(&rows));
+    assert!(rows.len() < 40, "{} timepoints", rows.len());
+
+    // steps grow as the decay slows, without passing the 500us from tmax
+    let steps: Vec<f64> = rows.windows(2).map(|w| w[1][0] - w[0][0]).collect();
+    assert!(steps[1] < 100e-6);
+    assert!(steps[steps.len() - 2] > 150e-6);
+    assert!(steps.iter().all(|&h| h <= 500e-6 + 1e-12));
+}
+
+#[test]
+fn test_lte_trtol() {
+    // a smaller trtol takes more, shorter, steps for a smaller error
+    let loose = run_spi("./ngspice/lte_rc.spi", &["Time", "v(out)"], |cfg| {
+        cfg.set_wavefile("waves/lte_rc/loose.dat");
+    });
+    let tight = run_spi("./ngspice/lte_rc.spi", &["Time", "v(out)"], |cfg| {
+        cfg.TRTOL = 0.1;
+        cfg.set_wavefile("waves/lte_rc/tight.dat");
+    });
+    assert!(tight.len() > 2 * loose.len());
+    assert!(max_error(&tight) < max_error(&loose));
+}
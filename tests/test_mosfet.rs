@@ -0,0 +1,160 @@
+//! MOSFET level-1 checks - the three operating regions, body effect, PMOS
+//! and sizes from bracket expressions
+
+use std::path::Path;
+use std::fs;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::circuit::Circuit;
+use tiny_spice::element::Element;
+use tiny_spice::element::mosfet::Region;
+
+mod common;
+use crate::common::assert_nearly;
+
+fn read(filename: &str) -> (Circuit, tiny_spice::analysis::Configuration) {
+    let spice_file = Path::new(filename);
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+    (reader.get_expanded_circuit(), reader.configuration().clone())
+}
+
+/// Set the DC voltage sources, and return the drain current & region
+fn nmos_bias(v_gs: f64, v_ds: f64, v_bs: f64) -> (f64, Region) {
+    let (mut ckt, cfg) = read("./ngspice/mos_nmos_iv.spi");
+
+    for el in &mut ckt.elements {
+        if let Element::V(ref mut src) = el {
+            src.value = match src.ident.as_str() {
+                "Vgs" => v_gs,
+                "Vds" => v_ds,
+                "Vbs" => v_bs,
+                _ => src.value,
+            };
+        }
+    }
+
+    let mut region = Region::Cutoff;
+    for el in &ckt.elements {
+        if let Element::M(ref mos) = el {
+            region = mos.region(v_gs, v_ds, v_bs);
+        }
+    }
+
+    let mut eng = engine::Engine::new();
+    eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+
+    // branch current of Vds is the current out of the drain
+    let idx_vds = ckt.count_nodes() + 1;
+    (-v[idx_vds], region)
+}
+
+// VTO=1 KP=50u LAMBDA=0.02 GAMMA=0.5 PHI=0.6, W/L=10
+const BETA: f64 = 50e-6 * 10.0;
+const LAMBDA: f64 = 0.02;
+
+#[test]
+fn test_nmos_cutoff() {
+    let (i_d, region) = nmos_bias(0.5, 5.0, 0.0);
+    assert_eq!(region, Region::Cutoff);
+    assert_nearly(i_d * 1e6, 0.0); // only GMIN
+}
+
+#[test]
+fn test_nmos_linear() {
+    let (i_d, region) = nmos_bias(3.0, 0.5, 0.0);
+    assert_eq!(region, Region::Linear);
+    let expected = BETA * (2.0 - 0.25) * 0.5 * (1.0 + LAMBDA * 0.5);
+    assert_nearly(i_d, expected);
+}
+
+#[test]
+fn test_nmos_saturation() {
+    let (i_d, region) = nmos_bias(3.0, 5.0, 0.0);
+    assert_eq!(region, Region::Saturation);
+    let expected = BETA / 2.0 * 2.0 * 2.0 * (1.0 + LAMBDA * 5.0);
+    assert_nearly(i_d, expected);
+}
+
+#[test]
+fn test_nmos_reversed() {
+    // drain below the source, so they swap over and current flows backwards
+    let (i_d, region) = nmos_bias(3.0, -0.5, -0.5);
+    assert_eq!(region, Region::Linear);
+    let expected = BETA * (3.5 - 1.0 - 0.25) * 0.5 * (1.0 + LAMBDA * 0.5);
+    assert_nearly(i_d, -expected);
+}
+
+#[test]
+fn test_nmos_body_effect() {
+    let (i_d, region) = nmos_bias(3.0, 5.0, -1.0);
+    assert_eq!(region, Region::Saturation);
+    let v_th = 1.0 + 0.5 * ((0.6_f64 + 1.0).sqrt() - 0.6_f64.sqrt());
+    let expected = BETA / 2.0 * (3.0 - v_th).powi(2) * (1.0 + LAMBDA * 5.0);
+    assert_nearly(i_d, expected);
+}
+
+#[test]
+fn test_pmos_saturation() {
+    let (ckt, cfg) = read("./ngspice/mos_pmos_iv.spi");
+    let mut eng = engine::Engine::new();
+    eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+
+    // v_sg = 3, v_sd = 5; VTO=-1 KP=25u LAMBDA=0.02, W/L=20
+    let expected = 25e-6 * 20.0 / 2.0 * 2.0 * 2.0 * (1.0 + 0.02 * 5.0);
+
+    // current flows out of the drain, and through Vd to ground
+    let idx_vd = ckt.count_nodes() + 2;
+    assert_nearly(v[idx_vd], expected);
+}
+
+#[test]
+fn test_mosfet_sizes_from_parameters() {
+    let (ckt, _) = read("./ngspice/mos_cmos_inverter.spi");
+
+    let mut c_mos = 0;
+    for el in &ckt.elements {
+        if let Element::M(ref mos) = el {
+            // Mn is overridden on the instance, Mp keeps the default
+            let w = if mos.ident == "Xinv.Mn" { 4e-6 } else { 8e-6 };
+            assert_nearly(mos.w, w);
+            assert_nearly(mos.l, 1e-6);
+            c_mos += 1;
+        }
+    }
+    assert_eq!(c_mos, 2);
+}
+
+#[test]
+fn test_cmos_inverter() {
+    let (ckt, mut cfg) = read("./ngspice/mos_cmos_inverter.spi");
+    cfg.set_wavefile("waves/mos_cmos_inverter/tran.dat");
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert!(stats.end >= cfg.TSTOP);
+
+    // output is low while the input pulse is high, and high after
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let v_out = names.iter().position(|n| n.ends_with("out)")).unwrap();
+
+    let mut last = 0.0;
+    for line in lines.skip(1) {
+        let vals: Vec<f64> = line.split('\t')
+            .map(|x| x.parse::<f64>().unwrap())
+            .collect();
+        if vals[0] > 20e-9 && vals[0] < 30e-9 {
+            assert!(vals[v_out] < 0.01, "v(out) = {} at {}", vals[v_out], vals[0]);
+        }
+        last = vals[v_out];
+    }
+    assert_nearly(last, 3.3);
+}
@@ -0,0 +1,79 @@
+//! Mutual inductance checks
+
+use std::path::Path;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::element::Element;
+use tiny_spice::complex::Complex;
+
+mod common;
+use crate::common::assert_nearly;
+
+#[test]
+fn test_ac_transformer() {
+
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/ac_transformer.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration();
+
+    let _ = eng.go(&ckt, cfg).unwrap();
+    let sols = eng.ac().unwrap();
+
+    // v(out)/v(in) = (M/L1) / (1 + jwL2(1-k^2)/R)
+    let (l1, l2, k, r): (f64, f64, f64, f64) = (1e-3, 4e-3, 0.99, 1e3);
+    let m = k * (l1 * l2).sqrt();
+    for (freq, v) in &sols {
+        let w = 2.0 * std::f64::consts::PI * freq;
+        let gain = Complex::from(m / l1) /
+            Complex::new(1.0, w * l2 * (1.0 - k * k) / r);
+
+        assert_nearly(v[2].norm(), gain.norm()); // v(out)
+        assert!((v[2].arg_deg() - gain.arg_deg()).abs() < 1e-3,
+            "phase {} isn't {} at {}Hz", v[2].arg_deg(), gain.arg_deg(), freq);
+    }
+}
+
+#[test]
+fn test_mutual_in_subckt() {
+
+    let spice_file = Path::new("./ngspice/transformer_fullwave_rectifier.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+
+    let mut found = false;
+    for el in &ckt.elements {
+        if let Element::K(k) = el {
+            assert_eq!(k.ident, "Xtx.Kps");
+            assert_eq!(k.l1, "Xtx.Lp");
+            assert_eq!(k.l2, "Xtx.Ls");
+            assert_nearly(k.value, 0.99 * (4e-3_f64 * 1e-3).sqrt());
+            found = true;
+        }
+    }
+    assert!(found);
+}
+
+#[test]
+#[should_panic]
+fn test_mutual_missing_inductor() {
+
+    let spice_file = Path::new("./ngspice/mutual_missing_l.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    // L3 doesn't exist, so this falls over
+    let _ckt = reader.get_expanded_circuit();
+}
@@ -0,0 +1,64 @@
+//! `.nodeset` starting guesses for the DC operating point
+
+use std::path::Path;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+
+mod common;
+use crate::common::assert_nearly;
+
+/// DC operating point of the latch, returning `(q, qb)`
+fn latch(nodesets: Option<Vec<(String, f64)>>) -> (f64, f64) {
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(Path::new("./ngspice/nodeset_latch.spi")));
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    if let Some(nodesets) = nodesets {
+        cfg.nodesets = nodesets;
+    }
+
+    let mut eng = engine::Engine::new();
+    eng.dc_operating_point(&ckt, &cfg);
+    let q = ckt.get_node_id("Xlatch.q").unwrap();
+    let qb = ckt.get_node_id("Xlatch.qb").unwrap();
+    let v = eng.dc().unwrap();
+    (v[q], v[qb])
+}
+
+#[test]
+fn test_nodeset_latch() {
+    // from the deck, through the subcircuit hierarchy
+    let (q, qb) = latch(None);
+    assert_nearly(q, 3.3);
+    assert_nearly(qb, 0.0);
+
+    // the other way
+    let (q, qb) = latch(Some(vec![
+        ("Xlatch.q".to_string(), 0.0),
+        ("Xlatch.qb".to_string(), 3.3),
+    ]));
+    assert_nearly(q, 0.0);
+    assert_nearly(qb, 3.3);
+}
+
+#[test]
+fn test_nodeset_released() {
+    // the guess is only a starting point, so the latch still falls to one
+    // of the rails
+    let (q, qb) = latch(Some(vec![
+        ("Xlatch.q".to_string(), 2.5),
+        ("Xlatch.qb".to_string(), 1.0),
+    ]));
+    assert_nearly((q - qb).abs(), 3.3);
+}
+
+#[test]
+fn test_no_nodeset_balanced() {
+    // a perfectly balanced latch, started from zero, sits in the middle
+    let (q, qb) = latch(Some(vec![]));
+    assert_nearly(q, qb);
+}
@@ -0,0 +1,122 @@
+//! PULSE sources - the waveform, and landing on every corner of it
+
+use std::path::Path;
+use std::fs;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::element::Element;
+use tiny_spice::element::waveform::Waveform;
+
+mod common;
+use crate::common::assert_nearly;
+
+/// Run the transient, and return the rows of the waveform file
+fn pulse_rc() -> (Vec<String>, Vec<Vec<f64>>) {
+    let spice_file = Path::new("./ngspice/pulse_rc.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    cfg.set_wavefile("waves/pulse_rc/tran.dat");
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert_eq!(stats.end, cfg.TSTOP);
+
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names = lines.next().unwrap().split('\t').map(|n| n.to_string()).collect();
+    let rows = lines.skip(1)
+        .map(|line| line.split('\t').map(|x| x.parse::<f64>().unwrap()).collect())
+        .collect();
+    (names, rows)
+}
+
+#[test]
+fn test_pulse_parse() {
+    let spice_file = Path::new("./ngspice/pulse_rc.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+    let ckt = reader.get_expanded_circuit();
+
+    let mut c_pulses = 0;
+    for el in &ckt.elements {
+        match el {
+            Element::Vwave(ref src) => {
+                let Waveform::Pulse(ref p) = src.wave else { panic!("not a pulse") };
+                assert_eq!(src.ident, "Vp");
+                assert_nearly(p.v2, 5.0);
+                assert_nearly(p.per * 1e6, 50.0);
+                c_pulses += 1;
+            }
+            Element::Iwave(ref src) => {
+                let Waveform::Pulse(ref p) = src.wave else { panic!("not a pulse") };
+                assert_nearly(p.v2 * 1e3, 1.0);
+                assert_eq!(p.tr, 0.0);
+                assert!(p.per.is_infinite());
+                c_pulses += 1;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(c_pulses, 2);
+}
+
+#[test]
+fn test_pulse_lands_on_corners() {
+    let (_, rows) = pulse_rc();
+    let times: Vec<f64> = rows.iter().map(|r| r[0]).collect();
+
+    // every edge of the voltage pulse train...
+    let mut corners = vec![];
+    for k in 0..4 {
+        let t_start = 10.0 + 50.0 * k as f64;
+        corners.extend([t_start, t_start + 1.0, t_start + 21.0, t_start + 22.0]);
+    }
+    // ... and the ideal edges of the current pulse
+    corners.extend([30.0, 45.0]);
+
+    for corner in corners {
+        assert!(times.iter().any(|t| (t * 1e6 - corner).abs() < 1e-9),
+            "no timepoint at {}us", corner);
+    }
+}
+
+#[test]
+fn test_pulse_waveforms() {
+    let (names, rows) = pulse_rc();
+    let col = |name: &str| names.iter().position(|n| n == name).unwrap();
+    let (v_in, v_iout, v_out) = (col("v(in)"), col("v(i_out)"), col("v(out)"));
+
+    for r in &rows {
+        let t = r[0] * 1e6;
+        let t_cycle = (t - 10.0).rem_euclid(50.0);
+        let expected = if t <= 10.0 {
+            0.0
+        } else if t_cycle < 1.0 {
+            5.0 * t_cycle
+        } else if t_cycle < 21.0 {
+            5.0
+        } else if t_cycle < 22.0 {
+            5.0 * (22.0 - t_cycle)
+        } else {
+            0.0
+        };
+        assert_nearly(r[v_in], expected);
+
+        // 1mA into 2k, with ideal edges
+        let expected = if t > 30.0 + 1e-9 && t <= 45.0 { 2.0 } else { 0.0 };
+        assert_nearly(r[v_iout], expected);
+
+        // RC charges most of the way in the 20us the pulse is high
+        if (t - 31.0).abs() < 1e-9 {
+            let expected = 5.0 * (1.0 - (-20.5_f64 / 10.0).exp());
+            assert!((r[v_out] - expected).abs() < 0.25, "v(out) = {}", r[v_out]);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! PWL sources - current sources, repeats, delays and file input
+
+use std::path::Path;
+use std::fs;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::element::Element;
+use tiny_spice::element::waveform::Waveform;
+
+mod common;
+use crate::common::assert_nearly;
+
+#[test]
+fn test_pwl_parse() {
+    let spice_file = Path::new("./ngspice/pwl_sources.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+    let ckt = reader.get_expanded_circuit();
+
+    let mut c_pwls = 0;
+    for el in &ckt.elements {
+        match el {
+            Element::Iwave(ref src) => {
+                let Waveform::Pwl(ref pwl) = src.wave else { panic!("not a PWL") };
+                assert_eq!(pwl.points.len(), 4);
+                assert_nearly(pwl.td * 1e6, 5.0);
+                assert_nearly(pwl.repeat.unwrap() * 1e6, 10.0);
+                c_pwls += 1;
+            }
+            Element::Vwave(ref src) if src.ident == "Vtrig" => {
+                let Waveform::Pwl(ref pwl) = src.wave else { panic!("not a PWL") };
+                assert_eq!(pwl.points.len(), 5);
+                assert_nearly(pwl.points[2].1, 5.0);
+                assert_eq!(pwl.repeat, Some(0.0));
+                c_pwls += 1;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(c_pwls, 2);
+}
+
+#[test]
+fn test_pwl_bad_decks() {
+    for deck in ["./ngspice/pwl_bad_file.spi", "./ngspice/pwl_bad_repeat.spi"] {
+        let mut reader = spice::Reader::new();
+        assert!(reader.read(Path::new(deck)), "'{}' should have errors", deck);
+    }
+}
+
+#[test]
+fn test_pwl_waveforms() {
+    let spice_file = Path::new("./ngspice/pwl_sources.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    cfg.set_wavefile("waves/pwl_sources/tran.dat");
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert_eq!(stats.end, cfg.TSTOP);
+
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let col = |name: &str| names.iter().position(|n| *n == name).unwrap();
+    let (v_tri, v_trig, v_high) = (col("v(i_tri)"), col("v(trig)"), col("v(high)"));
+
+    let times: Vec<f64> = lines.clone().skip(1)
+        .map(|line| line.split('\t').next().unwrap().parse::<f64>().unwrap() * 1e6)
+        .collect();
+
+    for line in lines.skip(1) {
+        let r: Vec<f64> = line.split('\t').map(|x| x.parse::<f64>().unwrap()).collect();
+        let t = r[0] * 1e6;
+
+        // 1mA into 1k
+        let expected = if t <= 5.0 {
+            0.0
+        } else if t <= 15.0 {
+            (t - 5.0) / 10.0
+        } else {
+            let t_cycle = (t - 15.0) % 20.0;
+            if t_cycle <= 10.0 { 1.0 + t_cycle / 10.0 } else { 3.0 - t_cycle / 10.0 }
+        };
+        assert_nearly(r[v_tri], expected);
+
+        let t_cycle = (t - 10.0).rem_euclid(21.0);
+        let expected = if t <= 10.0 || t_cycle <= 10.0 {
+            0.0
+        } else if t_cycle < 11.0 {
+            5.0 * (t_cycle - 10.0)
+        } else if t_cycle <= 20.0 {
+            5.0
+        } else {
+            5.0 * (21.0 - t_cycle)
+        };
+        assert_nearly(r[v_trig], expected);
+
+        let expected = if t <= 50.0 { 3.0 } else if t < 60.0 { 3.0 * (60.0 - t) / 10.0 } else { 0.0 };
+        assert_nearly(r[v_high], expected);
+    }
+
+    // lands on the corners of the repeats
+    for corner in [35.0, 45.0, 41.0, 42.0, 51.0, 52.0] {
+        assert!(times.iter().any(|t| (t - corner).abs() < 1e-6), "no timepoint at {}us", corner);
+    }
+}
@@ -0,0 +1,120 @@
+//! SIN() sources - delay, damping and phase
+
+use std::path::Path;
+use std::fs;
+use std::f64::consts::PI;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::element::Element;
+
+mod common;
+use crate::common::assert_nearly;
+
+#[test]
+fn test_sine_parse() {
+    let spice_file = Path::new("./ngspice/sin_damped.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+    let ckt = reader.get_expanded_circuit();
+
+    let mut c_sines = 0;
+    for el in &ckt.elements {
+        match el {
+            Element::Vsin(ref src) => {
+                assert_nearly(src.freq, 60.0);
+                assert_nearly(src.td * 1e3, 1.0);
+                assert_nearly(src.theta, 50.0);
+                assert_nearly(src.phase, 90.0);
+                c_sines += 1;
+            }
+            Element::Isin(ref src) => {
+                assert_nearly(src.va * 1e3, 0.5);
+                assert_nearly(src.td * 1e3, 0.5);
+                assert_eq!(src.theta, 0.0);
+                assert_eq!(src.phase, 0.0);
+                c_sines += 1;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(c_sines, 2);
+}
+
+#[test]
+fn test_sine_damped() {
+    let spice_file = Path::new("./ngspice/sin_damped.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    cfg.set_wavefile("waves/sin_damped/tran.dat");
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert_eq!(stats.end, cfg.TSTOP);
+
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let col = |name: &str| names.iter().position(|n| *n == name).unwrap();
+    let (v_kick, v_isin) = (col("v(kick)"), col("v(i_sin)"));
+
+    let mut delays = [false, false];
+    for line in lines.skip(1) {
+        let r: Vec<f64> = line.split('\t').map(|x| x.parse::<f64>().unwrap()).collect();
+        let t = r[0];
+
+        // sits at the top of the sinewave until the delay
+        let expected = if t <= 1e-3 {
+            1.0
+        } else {
+            let t = t - 1e-3;
+            (-50.0 * t).exp() * (2.0 * PI * 60.0 * t).cos()
+        };
+        assert_nearly(r[v_kick], expected);
+
+        let expected = if t <= 0.5e-3 {
+            1.0
+        } else {
+            1.0 + 0.5 * (2.0 * PI * 1e3 * (t - 0.5e-3)).sin()
+        };
+        assert_nearly(r[v_isin], expected);
+
+        for (landed, td) in delays.iter_mut().zip([1e-3, 0.5e-3]) {
+            *landed |= (t - td).abs() < 1e-12;
+        }
+    }
+    assert_eq!(delays, [true, true], "no timepoint at the end of a delay");
+}
+
+#[test]
+fn test_sine_in_subckt() {
+    let spice_file = Path::new("./ngspice/sin_subckt.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    cfg.set_wavefile("waves/sin_subckt/tran.dat");
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert_eq!(stats.end, cfg.TSTOP);
+
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let v_a = names.iter().position(|n| *n == "v(a)").unwrap();
+
+    for line in lines.skip(1) {
+        let r: Vec<f64> = line.split('\t').map(|x| x.parse::<f64>().unwrap()).collect();
+
+        // 1V through 1k into 1k, plus 1mA into the 500R they make together
+        let expected = (0.5 + 0.5) * (2.0 * PI * 1e3 * r[0]).sin();
+        assert_nearly(r[v_a], expected);
+    }
+}
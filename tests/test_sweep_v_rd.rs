@@ -1,32 +1,135 @@
+//! DC sweep of a voltage source driving a resistor and diode
+
+use std::path::Path;
+use std::fs;
+
 extern crate tiny_spice;
 
 use tiny_spice::circuit::*;
 use tiny_spice::engine;
 use tiny_spice::analysis;
+use tiny_spice::spice;
 
-/*
+mod common;
+use crate::common::assert_nearly;
+
+#[test]
 fn test_sweep_v_rd() {
 
     let mut eng = engine::Engine::new();
-    let cfg = analysis::Configuration::new();
+    let mut cfg = analysis::Configuration::new();
 
-    //cfg.set_transient(2.0e-3, 10e-6, 0.0);
-    //cfg.set_wavefile("waves/trans_ird_sine_1kHz_10us.dat");
+    cfg.set_dc_sweep("Vsweep", -3.0, 5.0, 0.1);
+    cfg.set_wavefile("waves/sweep_v_rd/dc.dat");
 
     let ckt = build();
-    let _v = eng.dc_sweep(&ckt, &cfg);
+    let stats = eng.go(&ckt, &cfg).unwrap();
     println!("\n*INFO* Done");
 
-    assert!(false);
+    assert_nearly(stats.end, 5.0);
+
+    let sols = eng.sweep().unwrap();
+    assert_eq!(sols.len(), 81);
+
+    // reverse biased: no current so all the voltage is across the diode
+    assert_nearly(sols[0].0, -3.0);
+    assert!((sols[0].1[2] + 3.0).abs() < 1e-3);
+
+    // forward biased: diode clamps, and R & D currents agree
+    for (v_sweep, v) in &sols {
+        assert_nearly(v[1], *v_sweep);
+        if *v_sweep > 1.0 {
+            assert!(v[2] < 0.8);
+            let i_r = (v[1] - v[2]) / 1e3;
+            assert!((i_r + v[3]).abs() < 1e-9); // branch current of source
+        }
+    }
+}
+
+#[test]
+fn test_sweep_spice_i_r() {
+
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/dc_sweep_ir.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration();
+
+    let _ = eng.go(&ckt, cfg).unwrap();
+    let sols = eng.sweep().unwrap();
+    assert_eq!(sols.len(), 11);
+
+    for (i_sweep, v) in &sols {
+        assert_nearly(v[1], 2e3 * i_sweep);
+    }
+}
+
+#[test]
+fn test_sweep_missing_source() {
+    let mut eng = engine::Engine::new();
+    let mut cfg = analysis::Configuration::new();
+
+    cfg.set_dc_sweep("Vnothere", 0.0, 1.0, 0.1);
+    cfg.set_wavefile("waves/sweep_v_rd/missing.dat");
+
+    let ckt = build();
+    assert!(eng.go(&ckt, &cfg).is_none());
 }
 
 
 fn build() -> Circuit {
     let mut ckt = Circuit::new();
 
-    ckt.elements.push(Element::V(VoltageSource{p: 1, n: 0, value: 0.0}) );
-    ckt.elements.push(Element::R(Resistor{a: 1, b: 2, value: 1e3}) );
-    ckt.elements.push(Element::D(Diode::new(2, 0, 1e-9, 27.0)) );
+    ckt.add_node("1");
+    ckt.add_node("2");
+
+    ckt.elements.push(Element::V(VoltageSource{ident: "Vsweep".to_string(), ..VoltageSource::new(1, 0, 0.0, 0)}));
+    ckt.elements.push(Element::R(Resistor{ident: "R1".to_string(), a: 1, b: 2, value: 1e3}));
+    ckt.elements.push(Element::D(Diode::new("D1", 2, 0, 1e-9, 27.0)));
+    ckt.build_node_id_lut();
     ckt
 }
-*/
+
+#[test]
+fn test_sweep_spice_nested() {
+
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/dc_sweep_nested.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration();
+
+    let _ = eng.go(&ckt, cfg).unwrap();
+    let sols = eng.sweep().unwrap();
+
+    // 11 points of V1 for each of 4 values of V2
+    assert_eq!(sols.len(), 11 * 4);
+
+    let mid = ckt.get_node_id("mid").unwrap();
+    for (i, (v1, v)) in sols.iter().enumerate() {
+        let v2 = (i / 11) as f64;
+        assert_nearly(v[mid], (v1 + v2) / 2.0);
+    }
+
+    // one block of rows per outer sweep value, in a single waveform file
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let blocks: Vec<&str> = waves.split("\n\n").collect();
+    assert_eq!(blocks.len(), 4);
+    for (i, block) in blocks.iter().enumerate() {
+        // the header is on top of the first block
+        let rows: Vec<&str> = block.lines().skip(if i == 0 { 2 } else { 0 }).collect();
+        assert_eq!(rows.len(), 11);
+        for row in rows {
+            let v2 = row.split('\t').nth(1).unwrap().parse::<f64>().unwrap();
+            assert_nearly(v2, i as f64);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Switches - hysteresis, pinning down the switching times in a transient,
+//! sample-and-hold, and current control with an initial state
+
+use std::path::Path;
+use std::fs;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::circuit::Circuit;
+use tiny_spice::element::Element;
+
+mod common;
+use crate::common::assert_nearly;
+
+fn read(filename: &str) -> (Circuit, tiny_spice::analysis::Configuration) {
+    let spice_file = Path::new(filename);
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+    (reader.get_expanded_circuit(), reader.configuration().clone())
+}
+
+/// Run the transient, and return the named columns of the waveform file
+fn transient(filename: &str, wavefile: &str, cols: &[&str]) -> Vec<Vec<f64>> {
+    let (ckt, mut cfg) = read(filename);
+    cfg.set_wavefile(wavefile);
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert!(stats.end >= cfg.TSTOP);
+
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let idxs: Vec<usize> = cols.iter()
+        .map(|c| names.iter().position(|n| n == c).unwrap())
+        .collect();
+
+    lines.skip(1)
+        .map(|line| {
+            let vals: Vec<f64> = line.split('\t')
+                .map(|x| x.parse::<f64>().unwrap())
+                .collect();
+            idxs.iter().map(|&i| vals[i]).collect()
+        })
+        .collect()
+}
+
+#[test]
+fn test_switch_hysteresis() {
+    let rows = transient("./ngspice/switch_hysteresis.spi",
+        "waves/switch_hysteresis/tran.dat", &["Time", "v(in)", "v(out)"]);
+
+    // find the switching times
+    let t_on = rows.iter().find(|r| r[2] > 2.5).unwrap()[0];
+    let t_off = rows.iter().rev().find(|r| r[2] > 2.5).unwrap()[0];
+
+    // triangle is 3V/ms: on at 1.5V on the way up, off at 0.5V on the way
+    // down. The step around each edge is 1000x smaller than TSTEP.
+    assert!((t_on - 0.5e-3).abs() < 20e-9, "switched on at {}", t_on);
+    assert!((t_off - (1e-3 + 2.5 / 3.0 * 1e-3)).abs() < 20e-9, "switched off at {}", t_off);
+
+    // on between the thresholds
+    for r in &rows {
+        if r[0] > t_on && r[0] < t_off {
+            assert_nearly(r[2], 5.0 * 1e3 / (1e3 + 1.0));
+        }
+    }
+}
+
+#[test]
+fn test_switch_sample_hold() {
+    let rows = transient("./ngspice/switch_sample_hold.spi",
+        "waves/switch_sample_hold/tran.dat", &["Time", "v(in)", "v(hold)"]);
+
+    // the clock crosses the threshold half way down its falling edge
+    let t_sample: f64 = 1.0005e-3;
+    let v_sample = (2.0 * std::f64::consts::PI * 125.0 * t_sample).sin();
+
+    for r in &rows {
+        let (t, v_in, v_hold) = (r[0], r[1], r[2]);
+        if (0.1e-3..1e-3).contains(&t) || t > 2.1e-3 {
+            // tracking, with a 1us time constant
+            assert!((v_hold - v_in).abs() < 2e-3, "v(hold) = {} at {}", v_hold, t);
+        } else if (1.01e-3..2e-3).contains(&t) {
+            // holding
+            assert!((v_hold - v_sample).abs() < 2e-3, "v(hold) = {} at {}", v_hold, t);
+        }
+    }
+}
+
+/// Set `Vctl`, and return `v(a)` and `v(b)` at the DC operating point
+fn current_switches_at(v_ctl: f64) -> (f64, f64) {
+    let (mut ckt, cfg) = read("./ngspice/switch_current.spi");
+
+    for el in &mut ckt.elements {
+        if let Element::V(ref mut src) = el {
+            if src.ident == "Vctl" {
+                src.value = v_ctl;
+            }
+        }
+    }
+
+    let mut eng = engine::Engine::new();
+    eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+    (v[ckt.get_node_id("a").unwrap()], v[ckt.get_node_id("b").unwrap()])
+}
+
+#[test]
+fn test_switch_current_controlled() {
+    let v_on = 5.0 * 1e3 / (1e3 + 1.0);
+    let v_off = 5.0 * 1e3 / (1e3 + 1e9);
+
+    // in the hysteresis band, the switches stay as they started
+    let (v_a, v_b) = current_switches_at(1.2);
+    assert_nearly(v_a, v_on);
+    assert_nearly(v_b, v_off);
+
+    // outside it, the initial state doesn't matter
+    let (v_a, v_b) = current_switches_at(2.0);
+    assert_nearly(v_a, v_on);
+    assert_nearly(v_b, v_on);
+
+    let (v_a, v_b) = current_switches_at(0.2);
+    assert_nearly(v_a, v_off);
+    assert_nearly(v_b, v_off);
+}
+
+#[test]
+#[should_panic]
+fn test_switch_wrong_model() {
+    let spice_file = Path::new("./ngspice/switch_wrong_model.spi");
+    let mut reader = spice::Reader::new();
+    let _ = reader.read(spice_file);
+    let _ = reader.get_expanded_circuit();
+}
@@ -0,0 +1,55 @@
+//! `option interp` - transient results on the `TSTEP` grid
+
+extern crate tiny_spice;
+
+mod common;
+use crate::common::{assert_nearly, run_spi};
+
+#[test]
+fn test_tran_interp_grid() {
+    let rows = run_spi("./ngspice/tran_interp.spi", &["Time", "v(in)", "v(out)"], |cfg| {
+        assert!(cfg.interp);
+        cfg.set_wavefile("waves/tran_interp/grid.dat");
+    });
+
+    // 5us to 50us by 1us
+    assert_eq!(rows.len(), 46);
+    for (k, r) in rows.iter().enumerate() {
+        let t = r[0] * 1e6;
+        assert_nearly(t, 5.0 + k as f64);
+
+        // the ramp is linear between timepoints, so interpolates exactly
+        let expected = if t <= 10.5 { 0.0 } else if t < 13.5 { t - 10.5 } else { 3.0 };
+        assert_nearly(r[1], expected);
+    }
+}
+
+#[test]
+fn test_tran_interp_matches_raw() {
+    let grid = run_spi("./ngspice/tran_interp.spi", &["Time", "v(in)", "v(out)"], |cfg| {
+        cfg.set_wavefile("waves/tran_interp/grid_vs_raw.dat");
+    });
+    let raw = run_spi("./ngspice/tran_interp.spi", &["Time", "v(in)", "v(out)"], |cfg| {
+        cfg.interp = false;
+        cfg.set_wavefile("waves/tran_interp/raw.dat");
+    });
+
+    // the raw timepoints are off the grid, and land on the corners. Apart
+    // from the DC solution, there's nothing before TSTART.
+    assert!(raw.iter().any(|r| (r[0] * 1e6 - 10.5).abs() < 1e-6));
+    assert!(raw.iter().skip(1).all(|r| r[0] >= 5e-6));
+
+    // grid values sit on the straight lines between raw timepoints
+    for g in &grid {
+        let i = raw.iter().position(|r| r[0] >= g[0] - 1e-12).unwrap();
+        if i == 1 {
+            continue; // interpolated from a timepoint before TSTART
+        } else if (raw[i][0] - g[0]).abs() < 1e-12 {
+            assert_nearly(g[2], raw[i][2]);
+        } else {
+            let (r1, r2) = (&raw[i - 1], &raw[i]);
+            let frac = (g[0] - r1[0]) / (r2[0] - r1[0]);
+            assert_nearly(g[2], r1[2] + (r2[2] - r1[2]) * frac);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+//! `tmax` and `uic` in `tran`, from the `.tran` dot-card
+
+use std::path::Path;
+use std::fs;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+
+mod common;
+use crate::common::assert_nearly;
+
+#[test]
+fn test_tran_tmax_uic() {
+    let spice_file = Path::new("./ngspice/tran_tmax.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+
+    let ckt = reader.get_expanded_circuit();
+    let cfg = reader.configuration().clone();
+    assert_nearly(cfg.TSTEP * 1e6, 1.0);
+    assert_nearly(cfg.TSTOP * 1e6, 50.0);
+    assert_eq!(cfg.TMAX, Some(0.5e-6));
+    assert!(cfg.uic);
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert_eq!(stats.end, cfg.TSTOP);
+
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let i_time = names.iter().position(|n| *n == "Time").unwrap();
+    let i_out = names.iter().position(|n| *n == "v(out)").unwrap();
+    let rows: Vec<(f64, f64)> = lines.skip(1)
+        .map(|line| {
+            let vals: Vec<f64> = line.split('\t').map(|x| x.parse::<f64>().unwrap()).collect();
+            (vals[i_time], vals[i_out])
+        })
+        .collect();
+
+    // no DC operating point, so the capacitor starts off discharged
+    assert_eq!(rows[0], (0.0, 0.0));
+
+    // the default cap would be 5us
+    for w in rows.windows(2) {
+        assert!(w[1].0 - w[0].0 <= 0.5e-6 * (1.0 + 1e-9),
+            "step from {} to {} is over tmax", w[0].0, w[1].0);
+    }
+
+    // charging with a 10us time constant
+    for &(t, v) in &rows {
+        let expected = 1.0 - (-t / 10e-6).exp();
+        assert!((v - expected).abs() < 0.02, "v(out) = {} at {}, not {}", v, t, expected);
+    }
+}
+
+#[test]
+fn test_tran_default_tmax() {
+    let spice_file = Path::new("./ngspice/tran_interp.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+
+    let cfg = reader.configuration();
+    assert_eq!(cfg.TMAX, None);
+    assert!(!cfg.uic);
+    assert_nearly(cfg.t_delta_max() * 1e6, 5.0);
+    assert_nearly(cfg.TSTART * 1e6, 5.0);
+}
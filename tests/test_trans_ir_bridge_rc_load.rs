@@ -104,14 +104,15 @@ fn build(amp: f64, freq: f64, cap: f64) -> Circuit {
     // bridge input voltage
     //ckt.elements.push(Element::V(VoltageSource{p: 1, n: 2, value: 10.0}));
     ckt.elements.push(
-        Element::Isin(CurrentSourceSine{p: 0, n: 1, vo: 0.0, va: amp, freq: freq}),
+        Element::Isin(CurrentSourceSine{p: 0, n: 1, vo: 0.0, va: amp, freq: freq,
+            td: 0.0, theta: 0.0, phase: 0.0}),
     );
     ckt.elements.push(
         Element::R(Resistor{ident: "r1".to_string(), a: 1, b: 0, value: 10.0}),
     );
 
 
-    ckt.elements.push(Element::V(VoltageSource{p: 2, n: 0, value: 0.0, idx:0}));
+    ckt.elements.push(Element::V(VoltageSource::new(2, 0, 0.0, 0)));
 
     // Diode bridge
     //  (1) is top
@@ -129,7 +130,7 @@ fn build(amp: f64, freq: f64, cap: f64) -> Circuit {
 
     // load
     ckt.elements.push( Element::R(Resistor{ident: "r2".to_string(), a: 3, b: 4, value: 1000.0}) );
-    ckt.elements.push( Element::C(Capacitor{ident: "c199".to_string(), a: 3, b: 4, value: cap}) );
+    ckt.elements.push( Element::C(Capacitor{ident: "c199".to_string(), a: 3, b: 4, value: cap, ic: None}) );
 
     ckt.build_node_id_lut();
     ckt
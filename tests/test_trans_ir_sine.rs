@@ -48,7 +48,8 @@ fn build( freq: f64 ) -> Circuit {
     ckt.add_node("1");
 
     ckt.elements.push(
-        Element::Isin(CurrentSourceSine{p: 0, n: 1, vo: 3.0, va: 1.0, freq: freq}),
+        Element::Isin(CurrentSourceSine{p: 0, n: 1, vo: 3.0, va: 1.0, freq: freq,
+            td: 0.0, theta: 0.0, phase: 0.0}),
     );
     ckt.elements.push(
         Element::R(Resistor{ident: "R1".to_string(), a: 1, b: 0, value: 10.0}),
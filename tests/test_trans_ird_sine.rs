@@ -95,7 +95,8 @@ fn build( amp: f64, freq: f64, isat: f64 ) -> Circuit {
     ckt.add_node("1");
 
     ckt.elements.push(
-        Element::Isin(CurrentSourceSine{p: 0, n: 1, vo: i_offset, va: amp, freq: freq}),
+        Element::Isin(CurrentSourceSine{p: 0, n: 1, vo: i_offset, va: amp, freq: freq,
+            td: 0.0, theta: 0.0, phase: 0.0}),
     );
     ckt.elements.push(
         Element::R(Resistor{ident: "r1".to_string(), a: 1, b: 0, value: 10.0}),
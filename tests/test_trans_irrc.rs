@@ -116,7 +116,8 @@ fn build( freq: f64 ) -> Circuit {
 
     // 10V Voltage Source
     ckt.elements.push(
-        Element::Isin(CurrentSourceSine{p: 0, n: 1, vo: 0.0, va: 10.0, freq: freq}),
+        Element::Isin(CurrentSourceSine{p: 0, n: 1, vo: 0.0, va: 10.0, freq: freq,
+            td: 0.0, theta: 0.0, phase: 0.0}),
     );
     ckt.elements.push(
         Element::R(Resistor{ident: "r1".to_string(), a: 1, b: 0, value: 1.0}),
@@ -127,7 +128,7 @@ fn build( freq: f64 ) -> Circuit {
         Element::R(Resistor{ident: "r2".to_string(), a: 1, b: 2, value: 1.0e3}),
     );
     ckt.elements.push(
-        Element::C(Capacitor{ident: "c1".to_string(), a: 2, b: 0, value: 0.032e-6}),
+        Element::C(Capacitor{ident: "c1".to_string(), a: 2, b: 0, value: 0.032e-6, ic: None}),
     );
 
     ckt.build_node_id_lut();
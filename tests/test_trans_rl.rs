@@ -0,0 +1,75 @@
+//! Inductor checks - DC short and RL step response
+
+use std::path::Path;
+use std::fs;
+
+extern crate tiny_spice;
+
+use tiny_spice::circuit::*;
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::analysis;
+
+mod common;
+use crate::common::assert_nearly;
+
+#[test]
+fn test_dc_inductor_short() {
+
+    let mut eng = engine::Engine::new();
+    let cfg = analysis::Configuration::new();
+
+    let mut ckt = Circuit::new();
+    ckt.add_node("1");
+
+    ckt.elements.push(
+        Element::I(CurrentSource::new(0, 1, 2.0)),
+    );
+    ckt.elements.push(
+        Element::R(Resistor{ident: "R1".to_string(), a: 1, b: 0, value: 10.0}),
+    );
+    ckt.elements.push(
+        Element::L(Inductor::new("L1", 1, 0, 1e-3, 0)),
+    );
+    ckt.build_node_id_lut();
+
+    eng.dc_operating_point(&ckt, &cfg);
+    let v = eng.dc().unwrap();
+
+    assert_nearly(v[1], 0.0); // shorted
+    assert_nearly(v[2], 2.0); // all the current goes through L1
+}
+
+#[test]
+fn test_trans_rl_step() {
+
+    let mut eng = engine::Engine::new();
+
+    let spice_file = Path::new("./ngspice/trans_rl.spi");
+    let mut reader = spice::Reader::new();
+    let errors_exist = reader.read(spice_file);
+    assert!(!errors_exist);
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    cfg.set_wavefile("waves/trans_rl/tran.dat");
+
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert!(stats.end >= cfg.TSTOP);
+
+    // dig the final values out of the waveform file
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let last: Vec<f64> = lines.last().unwrap()
+        .split('\t')
+        .map(|x| x.parse::<f64>().unwrap())
+        .collect();
+
+    let i_l = names.iter().position(|n| *n == "i(Xind.L1)").unwrap();
+    let v_in = names.iter().position(|n| *n == "v(in)").unwrap();
+
+    // 19 time constants after the step, so fully settled
+    assert_nearly(last[v_in], 1.0);
+    assert_nearly(last[i_l], 1.0/10.0);
+}
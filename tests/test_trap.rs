@@ -0,0 +1,42 @@
+//! Trapezoidal integration: an LC tank keeps ringing, where backward Euler
+//! damps it away
+
+extern crate tiny_spice;
+
+use tiny_spice::analysis::Method;
+
+mod common;
+use crate::common::run_spi;
+
+/// Biggest swing in the last period of the 5kHz ring
+fn last_peak(rows: &[Vec<f64>]) -> f64 {
+    rows.iter()
+        .filter(|r| r[0] > 1.8e-3)
+        .map(|r| r[1].abs())
+        .fold(0.0, f64::max)
+}
+
+#[test]
+fn test_trap_lc_tank() {
+    let rows = run_spi("./ngspice/trap_lc.spi", &["Time", "v(tank)"], |cfg| {
+        assert_eq!(cfg.method, Method::Trapezoidal);
+        cfg.set_wavefile("waves/trap_lc/trap.dat");
+    });
+
+    // follows the lossless cosine
+    let omega = 1.0 / (1e-3 * 1e-6_f64).sqrt();
+    for r in &rows {
+        let (t, v) = (r[0], r[1]);
+        assert!((v - (omega * t).cos()).abs() < 0.02, "v(tank) = {} at {}", v, t);
+    }
+    assert!(last_peak(&rows) > 0.99);
+}
+
+#[test]
+fn test_euler_lc_tank() {
+    let rows = run_spi("./ngspice/trap_lc.spi", &["Time", "v(tank)"], |cfg| {
+        cfg.method = Method::Euler;
+        cfg.set_wavefile("waves/trap_lc/euler.dat");
+    });
+    assert!(last_peak(&rows) < 0.5, "peak {}", last_peak(&rows));
+}
@@ -43,7 +43,7 @@ fn build() -> circuit::Circuit {
         circuit::Element::R(circuit::Resistor{ident: "r1".to_string(), a: 0, b: 1, value: 2.0}),
     );
     ckt.elements.push(
-        circuit::Element::V(circuit::VoltageSource{p: 2, n: 1, value: 32.0, idx: 0}),
+        circuit::Element::V(circuit::VoltageSource::new(2, 1, 32.0, 0)),
     );
     ckt.elements.push(
         circuit::Element::R(circuit::Resistor{ident: "r2".to_string(), a: 2, b: 3, value: 4.0}),
@@ -52,7 +52,7 @@ fn build() -> circuit::Circuit {
         circuit::Element::R(circuit::Resistor{ident: "r3".to_string(), a: 2, b: 0, value: 8.0}),
     );
     ckt.elements.push(
-        circuit::Element::V(circuit::VoltageSource{p: 3, n: 0, value: 20.0, idx: 1}),
+        circuit::Element::V(circuit::VoltageSource::new(3, 0, 20.0, 1)),
     );
     ckt
 }
@@ -0,0 +1,56 @@
+//! `wav` command - resampling transient results into WAV files
+
+use std::path::Path;
+use std::f64::consts::PI;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::analysis::WavSignal;
+use tiny_spice::wavfile;
+
+#[test]
+fn test_wav_output() {
+    let spice_file = Path::new("./ngspice/wav_output.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    cfg.set_wavefile("waves/wav_output/tran.dat");
+
+    assert_eq!(cfg.wav_outputs.len(), 2);
+    assert_eq!(cfg.wav_outputs[1].signals[1],
+        WavSignal::Voltage("in".to_string(), Some("out".to_string())));
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert_eq!(stats.end, cfg.TSTOP);
+
+    // the interpolation between timepoints is a little off the sinewave
+    let sine = |t: f64| (2.0 * PI * 1e3 * t).sin();
+    let tolerance = 0.02;
+
+    // mono: 1V peak, full scale is 1V
+    let wav = wavfile::read(Path::new("waves/wav_output/out.wav")).unwrap();
+    assert_eq!(wav.sample_rate, 8000);
+    assert_eq!(wav.channels.len(), 1);
+    assert_eq!(wav.channels[0].len(), 41);
+    for (k, x) in wav.channels[0].iter().enumerate() {
+        let expected = sine(k as f64 / 8000.0);
+        assert!((x - expected).abs() < tolerance, "sample {} is {}, not {}", k, x, expected);
+    }
+
+    // stereo: 2V and 1V peaks, normalised to the bigger one
+    let wav = wavfile::read(Path::new("waves/wav_output/stereo.wav")).unwrap();
+    assert_eq!(wav.sample_rate, 16000);
+    assert_eq!(wav.channels.len(), 2);
+    let peak = wav.channels[0].iter().fold(0.0_f64, |m, x| m.max(x.abs()));
+    assert!((peak - 1.0).abs() < 1e-6, "peak is {}", peak);
+    for k in 0..81 {
+        let expected = sine(k as f64 / 16000.0);
+        assert!((wav.channels[0][k] - expected).abs() < tolerance);
+        assert!((wav.channels[1][k] - 0.5 * expected).abs() < tolerance);
+    }
+}
@@ -0,0 +1,102 @@
+//! WAV sources - reading a channel out of a file, and landing on its samples
+
+use std::path::Path;
+use std::fs;
+
+extern crate tiny_spice;
+
+use tiny_spice::spice;
+use tiny_spice::engine;
+use tiny_spice::element::Element;
+use tiny_spice::element::waveform::Waveform;
+
+mod common;
+use crate::common::assert_nearly;
+
+/// `wav_ramp.wav` channel 0 is a ramp up by 1/64 per sample, and channel 1
+/// a triangle up by 1/32 per sample for 20 samples and back down
+fn triangle(k: f64) -> f64 {
+    if k <= 20.0 { k / 32.0 } else { (40.0 - k) / 32.0 }
+}
+
+#[test]
+fn test_wav_source_parse() {
+    let spice_file = Path::new("./ngspice/wav_source.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+    let ckt = reader.get_expanded_circuit();
+
+    let mut c_wavs = 0;
+    for el in &ckt.elements {
+        match el {
+            Element::Vwave(ref src) => {
+                let Waveform::Wav(ref wav) = src.wave else { panic!("not a WAV") };
+                assert_eq!(wav.samples.len(), 40);
+                assert_nearly(wav.sample_rate, 8000.0);
+                assert_nearly(wav.samples[10], triangle(10.0));
+                assert_nearly(wav.samples[30], triangle(30.0));
+                assert_nearly(wav.gain, 4.0);
+                c_wavs += 1;
+            }
+            Element::Iwave(ref src) => {
+                let Waveform::Wav(ref wav) = src.wave else { panic!("not a WAV") };
+                assert_nearly(wav.samples[30], 30.0 / 64.0);
+                assert_eq!(wav.td, 0.0);
+                c_wavs += 1;
+            }
+            _ => {}
+        }
+    }
+    assert_eq!(c_wavs, 2);
+}
+
+#[test]
+fn test_wav_source_bad_decks() {
+    for deck in ["./ngspice/wav_bad_file.spi", "./ngspice/wav_bad_chan.spi"] {
+        let mut reader = spice::Reader::new();
+        assert!(reader.read(Path::new(deck)), "'{}' should have errors", deck);
+    }
+}
+
+#[test]
+fn test_wav_source_waveforms() {
+    let spice_file = Path::new("./ngspice/wav_source.spi");
+    let mut reader = spice::Reader::new();
+    assert!(!reader.read(spice_file));
+
+    let ckt = reader.get_expanded_circuit();
+    let mut cfg = reader.configuration().clone();
+    cfg.set_wavefile("waves/wav_source/tran.dat");
+
+    let mut eng = engine::Engine::new();
+    let stats = eng.transient_analysis(&ckt, &cfg);
+    assert_eq!(stats.end, cfg.TSTOP);
+
+    let waves = fs::read_to_string(&cfg.wavefile).unwrap();
+    let mut lines = waves.lines();
+    let names: Vec<&str> = lines.next().unwrap().split('\t').collect();
+    let col = |name: &str| names.iter().position(|n| *n == name).unwrap();
+    let (v_in, v_iout) = (col("v(in)"), col("v(i_out)"));
+
+    let mut times = vec![];
+    for line in lines.skip(1) {
+        let r: Vec<f64> = line.split('\t').map(|x| x.parse::<f64>().unwrap()).collect();
+        let t = r[0];
+        times.push(t);
+
+        // interpolated between the 125us samples
+        let k = ((t - 1e-3) * 8000.0).clamp(0.0, 39.0);
+        assert_nearly(r[v_in], 4.0 * triangle(k));
+
+        // 1mA full scale into 1k
+        let k = (t * 8000.0).min(39.0);
+        assert_nearly(r[v_iout], k / 64.0);
+    }
+
+    // a timepoint on every sample
+    for k in 0..40 {
+        let t_sample = 1e-3 + k as f64 / 8000.0;
+        assert!(times.iter().any(|t| (t - t_sample).abs() < 1e-12),
+            "no timepoint at {}", t_sample);
+    }
+}